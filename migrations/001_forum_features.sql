@@ -0,0 +1,228 @@
+/*
+    Upgrades a database created from the original schema.sql to the forum
+    features: editing, reactions, threads, polls, drafts, notifications...
+    Run once, before starting the new server.
+*/
+
+CREATE TABLE IF NOT EXISTS dg_migrations (
+  version varchar(64) NOT NULL,
+  applied_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+  PRIMARY KEY (version)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4;
+
+ALTER TABLE dg_apps
+  ADD COLUMN edit_window int unsigned NOT NULL DEFAULT 0 AFTER icon_url,
+  ADD COLUMN upload_max_size int unsigned NOT NULL DEFAULT 5242880 AFTER edit_window,
+  ADD COLUMN upload_mime_types varchar(512) NOT NULL DEFAULT 'image/jpeg,image/png,image/gif,image/webp' AFTER upload_max_size,
+  ADD COLUMN author_lock tinyint(1) NOT NULL DEFAULT 0 AFTER upload_mime_types,
+  ADD COLUMN auto_lock_days int unsigned NOT NULL DEFAULT 0 AFTER author_lock,
+  ADD COLUMN reaction_types varchar(512) NOT NULL DEFAULT 'like' AFTER auto_lock_days,
+  ADD COLUMN downvote_enabled tinyint(1) NOT NULL DEFAULT 0 AFTER reaction_types,
+  ADD COLUMN qa_categories varchar(512) NOT NULL DEFAULT '' AFTER downvote_enabled;
+
+ALTER TABLE dg_topics
+  ADD COLUMN content_format tinyint NOT NULL DEFAULT 0 AFTER title,
+  ADD COLUMN content_html text NOT NULL AFTER content,
+  ADD COLUMN excerpt varchar(1024) NOT NULL DEFAULT '' AFTER content_html,
+  ADD COLUMN score bigint NOT NULL DEFAULT 0 AFTER like_count,
+  ADD COLUMN view_count bigint unsigned NOT NULL DEFAULT 0 AFTER reply_count,
+  ADD COLUMN bookmark_count bigint unsigned NOT NULL DEFAULT 0 AFTER view_count,
+  ADD COLUMN locked tinyint(1) NOT NULL DEFAULT 0 AFTER bookmark_count,
+  ADD COLUMN locked_by tinyint NOT NULL DEFAULT 0 AFTER locked,
+  ADD COLUMN moved_to bigint unsigned NOT NULL DEFAULT 0 AFTER locked_by,
+  ADD COLUMN accepted_reply_id bigint unsigned NOT NULL DEFAULT 0 AFTER moved_to,
+  ADD COLUMN reaction_counts varchar(1024) NOT NULL DEFAULT '{}' AFTER accepted_reply_id,
+  ADD COLUMN edited_at DATETIME AFTER reaction_counts,
+  ADD COLUMN publish_at DATETIME AFTER edited_at,
+  ADD COLUMN topped_until DATETIME AFTER publish_at,
+  ADD COLUMN updated_at DATETIME DEFAULT CURRENT_TIMESTAMP ON UPDATE CURRENT_TIMESTAMP AFTER topped_until,
+  ADD KEY IX_topic_publish (publish_at),
+  ADD KEY IX_topic_topped_until (topped_until),
+  ADD KEY IX_topic_score (app_id,category,topped,score),
+  ADD KEY IX_topic_updated (updated_at,id);
+
+ALTER TABLE dg_replies
+  ADD COLUMN parent_reply_id bigint unsigned NOT NULL DEFAULT 0 AFTER user_id,
+  ADD COLUMN root_reply_id bigint unsigned NOT NULL DEFAULT 0 AFTER parent_reply_id,
+  ADD COLUMN quote_reply_id bigint unsigned NOT NULL DEFAULT 0 AFTER root_reply_id,
+  ADD COLUMN content_format tinyint NOT NULL DEFAULT 0 AFTER quote_reply_id,
+  ADD COLUMN content_html text NOT NULL AFTER content,
+  ADD COLUMN excerpt varchar(1024) NOT NULL DEFAULT '' AFTER content_html,
+  ADD COLUMN score bigint NOT NULL DEFAULT 0 AFTER like_count,
+  ADD COLUMN child_count bigint unsigned NOT NULL DEFAULT 0 AFTER score,
+  ADD COLUMN reaction_counts varchar(1024) NOT NULL DEFAULT '{}' AFTER child_count,
+  ADD COLUMN edited_at DATETIME AFTER reaction_counts,
+  ADD COLUMN updated_at DATETIME DEFAULT CURRENT_TIMESTAMP ON UPDATE CURRENT_TIMESTAMP AFTER edited_at,
+  ADD KEY IX_reply_parent (parent_reply_id,topped,created_at),
+  ADD KEY IX_reply_updated (updated_at,id);
+
+/*
+    existing likes become the like reaction, content_html and excerpt are
+    rendered by the server on start
+*/
+
+UPDATE dg_topics SET reaction_counts=JSON_OBJECT('like',like_count) WHERE like_count>0 AND reaction_counts='{}';
+UPDATE dg_replies SET reaction_counts=JSON_OBJECT('like',like_count) WHERE like_count>0 AND reaction_counts='{}';
+
+CREATE TABLE IF NOT EXISTS dg_topic_revisions (
+  id bigint unsigned NOT NULL AUTO_INCREMENT,
+  app_id bigint unsigned NOT NULL,
+  topic_id bigint unsigned NOT NULL,
+  editor_type tinyint NOT NULL,
+  editor_id bigint unsigned NOT NULL,
+  title varchar(1024) NOT NULL,
+  content_format tinyint NOT NULL DEFAULT 0,
+  content text NOT NULL,
+  created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+  PRIMARY KEY (id),
+  KEY IX_revision_topic (topic_id,id)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 AUTO_INCREMENT=1000;
+
+CREATE TABLE IF NOT EXISTS dg_tags (
+  id bigint unsigned NOT NULL AUTO_INCREMENT,
+  app_id bigint unsigned NOT NULL,
+  `name` varchar(128) NOT NULL,
+  status tinyint NOT NULL,
+  merged_to bigint unsigned NOT NULL DEFAULT 0,
+  topic_count bigint unsigned NOT NULL DEFAULT 0,
+  created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+  PRIMARY KEY (id),
+  UNIQUE KEY UK_tag_name (app_id,`name`),
+  KEY IX_tag_popular (app_id,status,merged_to,topic_count)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 AUTO_INCREMENT=1000;
+
+CREATE TABLE IF NOT EXISTS dg_topic_tags (
+  topic_id bigint unsigned NOT NULL,
+  tag_id bigint unsigned NOT NULL,
+  app_id bigint unsigned NOT NULL,
+  created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+  PRIMARY KEY (topic_id,tag_id),
+  KEY IX_topic_tag (tag_id,topic_id)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4;
+
+CREATE TABLE IF NOT EXISTS dg_reply_revisions (
+  id bigint unsigned NOT NULL AUTO_INCREMENT,
+  app_id bigint unsigned NOT NULL,
+  reply_id bigint unsigned NOT NULL,
+  editor_type tinyint NOT NULL,
+  editor_id bigint unsigned NOT NULL,
+  content_format tinyint NOT NULL DEFAULT 0,
+  content text NOT NULL,
+  created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+  PRIMARY KEY (id),
+  KEY IX_revision_reply (reply_id,id)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 AUTO_INCREMENT=1000;
+
+CREATE TABLE IF NOT EXISTS dg_attachments (
+  id bigint unsigned NOT NULL AUTO_INCREMENT,
+  app_id bigint unsigned NOT NULL,
+  user_id bigint unsigned NOT NULL,
+  target_type tinyint NOT NULL,
+  target_id bigint unsigned NOT NULL,
+  storage_key varchar(256) NOT NULL,
+  file_name varchar(256) NOT NULL,
+  mime_type varchar(128) NOT NULL,
+  size int unsigned NOT NULL,
+  created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+  PRIMARY KEY (id),
+  KEY IX_attachment_target (target_type,target_id),
+  KEY IX_attachment_unbound (target_id,created_at)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 AUTO_INCREMENT=1000;
+
+CREATE TABLE IF NOT EXISTS dg_polls (
+  id bigint unsigned NOT NULL AUTO_INCREMENT,
+  app_id bigint unsigned NOT NULL,
+  topic_id bigint unsigned NOT NULL,
+  multiple tinyint(1) NOT NULL DEFAULT 0,
+  anonymous tinyint(1) NOT NULL DEFAULT 0,
+  voter_count bigint unsigned NOT NULL DEFAULT 0,
+  close_at DATETIME DEFAULT NULL,
+  created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+  PRIMARY KEY (id),
+  UNIQUE KEY UK_poll_topic (topic_id)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 AUTO_INCREMENT=1000;
+
+CREATE TABLE IF NOT EXISTS dg_poll_options (
+  id bigint unsigned NOT NULL AUTO_INCREMENT,
+  poll_id bigint unsigned NOT NULL,
+  position int unsigned NOT NULL,
+  title varchar(512) NOT NULL,
+  vote_count bigint unsigned NOT NULL DEFAULT 0,
+  PRIMARY KEY (id),
+  KEY IX_option_poll (poll_id,position)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 AUTO_INCREMENT=1000;
+
+CREATE TABLE IF NOT EXISTS dg_poll_voters (
+  poll_id bigint unsigned NOT NULL,
+  user_id bigint unsigned NOT NULL,
+  created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+  PRIMARY KEY (poll_id,user_id)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4;
+
+CREATE TABLE IF NOT EXISTS dg_poll_votes (
+  poll_id bigint unsigned NOT NULL,
+  option_id bigint unsigned NOT NULL,
+  user_id bigint unsigned NOT NULL,
+  created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+  PRIMARY KEY (poll_id,user_id,option_id),
+  KEY IX_vote_option (option_id,created_at)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4;
+
+CREATE TABLE IF NOT EXISTS dg_bookmarks (
+  user_id bigint unsigned NOT NULL,
+  topic_id bigint unsigned NOT NULL,
+  app_id bigint unsigned NOT NULL,
+  created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+  PRIMARY KEY (user_id,topic_id),
+  KEY IX_bookmark_topic (topic_id)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4;
+
+CREATE TABLE IF NOT EXISTS dg_drafts (
+  id bigint unsigned NOT NULL AUTO_INCREMENT,
+  app_id bigint unsigned NOT NULL,
+  user_id bigint unsigned NOT NULL,
+  target_type tinyint NOT NULL,
+  topic_id bigint unsigned NOT NULL DEFAULT 0,
+  category bigint unsigned NOT NULL DEFAULT 0,
+  title varchar(1024) NOT NULL DEFAULT '',
+  content_format tinyint NOT NULL DEFAULT 0,
+  content text NOT NULL,
+  tags varchar(512) NOT NULL DEFAULT '',
+  created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+  updated_at DATETIME NOT NULL,
+  PRIMARY KEY (id),
+  KEY IX_draft_user (user_id,updated_at),
+  KEY IX_draft_updated (updated_at)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 AUTO_INCREMENT=1000;
+
+CREATE TABLE IF NOT EXISTS dg_notifications (
+  id bigint unsigned NOT NULL AUTO_INCREMENT,
+  app_id bigint unsigned NOT NULL,
+  user_id bigint unsigned NOT NULL,
+  kind tinyint NOT NULL,
+  topic_id bigint unsigned NOT NULL DEFAULT 0,
+  reply_id bigint unsigned NOT NULL DEFAULT 0,
+  actor_id bigint unsigned NOT NULL DEFAULT 0,
+  actor_count int unsigned NOT NULL DEFAULT 1,
+  content varchar(512) NOT NULL DEFAULT '',
+  is_read tinyint(1) NOT NULL DEFAULT 0,
+  created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+  updated_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+  PRIMARY KEY (id),
+  KEY IX_notification_user (user_id,updated_at),
+  KEY IX_notification_unread (user_id,is_read,kind,topic_id)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 AUTO_INCREMENT=1000;
+
+CREATE TABLE IF NOT EXISTS dg_likes (
+  id bigint unsigned NOT NULL AUTO_INCREMENT,
+  app_id bigint unsigned NOT NULL,
+  target_type tinyint NOT NULL,
+  target_id bigint unsigned NOT NULL,
+  user_id bigint unsigned NOT NULL,
+  reaction varchar(32) NOT NULL,
+  created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+  PRIMARY KEY (id),
+  UNIQUE KEY UX_like_target (target_type,target_id,reaction,user_id)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 AUTO_INCREMENT=1000;
+
+INSERT IGNORE dg_migrations(version) VALUES('001_forum_features');
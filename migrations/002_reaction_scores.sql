@@ -0,0 +1,9 @@
+/*
+    score is likes minus downvotes, recounted from dg_likes for the rows
+    upgraded by 001 with a score of 0
+*/
+
+UPDATE dg_topics t SET t.score=t.like_count-(SELECT COUNT(*) FROM dg_likes l WHERE l.target_type=1 AND l.target_id=t.id AND l.reaction='downvote');
+UPDATE dg_replies r SET r.score=r.like_count-(SELECT COUNT(*) FROM dg_likes l WHERE l.target_type=2 AND l.target_id=r.id AND l.reaction='downvote');
+
+INSERT IGNORE dg_migrations(version) VALUES('002_reaction_scores');
@@ -0,0 +1,19 @@
+/*
+    unread notifications fold by (user_id,group_key) and count their distinct
+    actors in dg_notification_actors. Notifications stored before keep a NULL
+    group_key, the next event on their target starts a new one.
+*/
+
+ALTER TABLE dg_notifications
+  ADD COLUMN group_key varchar(64) DEFAULT NULL AFTER content,
+  DROP KEY IX_notification_unread,
+  ADD KEY IX_notification_unread (user_id,is_read),
+  ADD UNIQUE KEY UK_notification_group (user_id,group_key);
+
+CREATE TABLE IF NOT EXISTS dg_notification_actors (
+  notification_id bigint unsigned NOT NULL,
+  actor_id bigint unsigned NOT NULL,
+  PRIMARY KEY (notification_id,actor_id)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4;
+
+INSERT IGNORE dg_migrations(version) VALUES('003_notification_groups');
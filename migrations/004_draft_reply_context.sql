@@ -0,0 +1,10 @@
+/*
+    reply drafts keep the reply they answer and the one they quote, so
+    publishing posts them in the same thread.
+*/
+
+ALTER TABLE dg_drafts
+  ADD COLUMN parent_reply_id bigint unsigned NOT NULL DEFAULT 0 AFTER topic_id,
+  ADD COLUMN quote_reply_id bigint unsigned NOT NULL DEFAULT 0 AFTER parent_reply_id;
+
+INSERT IGNORE dg_migrations(version) VALUES('004_draft_reply_context');
@@ -9,12 +9,44 @@ CREATE TABLE IF NOT EXISTS dg_managers (
 
 INSERT IGNORE dg_managers(username,password) VALUES('admin',SHA2('admin', 256));
 
+/*
+    version - file name of an applied migration under migrations/, or a data
+              migration the server ran on start
+*/
+
+CREATE TABLE IF NOT EXISTS dg_migrations (
+  version varchar(64) NOT NULL,
+  applied_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+  PRIMARY KEY (version)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4;
+
+INSERT IGNORE dg_migrations(version) VALUES('001_forum_features'),('002_reaction_scores'),('003_notification_groups'),('004_draft_reply_context');
+
+/*
+    edit_window - seconds authors may edit after posting, 0-not allowed
+    upload_max_size - bytes per uploaded file
+    upload_mime_types - comma separated MIME types allowed for uploads
+    author_lock - 1-authors may lock their own topics
+    auto_lock_days - lock topics after this many days without replies, 0-never
+    reaction_types - comma separated reaction names, like is always included
+    downvote_enabled - 1-users may downvote topics and replies
+    qa_categories - comma separated categories accepting answers, 0-every category
+*/
+
 CREATE TABLE IF NOT EXISTS dg_apps (
   id bigint unsigned NOT NULL AUTO_INCREMENT,
   app_key varchar(100) NOT NULL,
   app_secret varchar(100) NOT NULL,
   `name` varchar(100) NOT NULL,
   icon_url varchar(256),
+  edit_window int unsigned NOT NULL DEFAULT 0,
+  upload_max_size int unsigned NOT NULL DEFAULT 5242880,
+  upload_mime_types varchar(512) NOT NULL DEFAULT 'image/jpeg,image/png,image/gif,image/webp',
+  author_lock tinyint(1) NOT NULL DEFAULT 0,
+  auto_lock_days int unsigned NOT NULL DEFAULT 0,
+  reaction_types varchar(512) NOT NULL DEFAULT 'like',
+  downvote_enabled tinyint(1) NOT NULL DEFAULT 0,
+  qa_categories varchar(512) NOT NULL DEFAULT '',
   created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
   PRIMARY KEY (id),
   UNIQUE KEY UK_app_key (app_key)
@@ -42,7 +74,16 @@ CREATE TABLE IF NOT EXISTS dg_users (
 ) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 AUTO_INCREMENT=1000;
 
 /*
-    topped - 0-normal, >0 topped, -1-hidden, -2 deleted
+    topped - 0-normal, >0 topped, -1-hidden, -2 deleted, -3 deleted by author
+    content_format - 0-Plain,1-Markdown
+    publish_at - hidden until this time when scheduled
+    topped_until - pin expiry of a topped topic
+    locked_by - who last locked it, 0-never,1-Author,2-Manager,3-Auto, kept after unlocking
+    moved_to - placeholder left behind by a move, id of the moved topic
+    accepted_reply_id - reply accepted as the answer, 0 for unsolved
+    reaction_counts - JSON object of reaction name to count, like_count mirrors the like reaction
+    score - like_count minus downvotes
+    updated_at - last change of the indexed columns (counters keep it), followed by the search index of each instance
 */
 
 CREATE TABLE IF NOT EXISTS dg_topics (
@@ -51,21 +92,93 @@ CREATE TABLE IF NOT EXISTS dg_topics (
   user_id bigint unsigned NOT NULL,
   category bigint unsigned NOT NULL,
   title varchar(1024) NOT NULL,
+  content_format tinyint NOT NULL DEFAULT 0,
   content text NOT NULL,
+  content_html text NOT NULL,
+  excerpt varchar(1024) NOT NULL DEFAULT '',
   topped bigint NOT NULL,
   created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
   refreshed_at DATETIME NOT NULL,
   like_count bigint unsigned NOT NULL DEFAULT 0,
+  score bigint NOT NULL DEFAULT 0,
   reply_count bigint unsigned NOT NULL DEFAULT 0,
+  view_count bigint unsigned NOT NULL DEFAULT 0,
+  bookmark_count bigint unsigned NOT NULL DEFAULT 0,
+  locked tinyint(1) NOT NULL DEFAULT 0,
+  locked_by tinyint NOT NULL DEFAULT 0,
+  moved_to bigint unsigned NOT NULL DEFAULT 0,
+  accepted_reply_id bigint unsigned NOT NULL DEFAULT 0,
+  reaction_counts varchar(1024) NOT NULL DEFAULT '{}',
+  edited_at DATETIME,
+  publish_at DATETIME,
+  topped_until DATETIME,
+  updated_at DATETIME DEFAULT CURRENT_TIMESTAMP ON UPDATE CURRENT_TIMESTAMP,
   PRIMARY KEY (id),
   KEY IX_topic_user (user_id,topped),
+  KEY IX_topic_publish (publish_at),
+  KEY IX_topic_topped_until (topped_until),
   KEY IX_topic_create (app_id,category,topped,created_at),
-  KEY IX_topic_refresh (app_id,category,topped,refreshed_at)
+  KEY IX_topic_refresh (app_id,category,topped,refreshed_at),
+  KEY IX_topic_score (app_id,category,topped,score),
+  KEY IX_topic_updated (updated_at,id)
 ) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 AUTO_INCREMENT=1000;
 
+/*
+    snapshot of a topic taken before each edit
+    editor_type - 0-User,1-Manager
+*/
+
+CREATE TABLE IF NOT EXISTS dg_topic_revisions (
+  id bigint unsigned NOT NULL AUTO_INCREMENT,
+  app_id bigint unsigned NOT NULL,
+  topic_id bigint unsigned NOT NULL,
+  editor_type tinyint NOT NULL,
+  editor_id bigint unsigned NOT NULL,
+  title varchar(1024) NOT NULL,
+  content_format tinyint NOT NULL DEFAULT 0,
+  content text NOT NULL,
+  created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+  PRIMARY KEY (id),
+  KEY IX_revision_topic (topic_id,id)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 AUTO_INCREMENT=1000;
 
 /*
-    topped - 0-normal, >0 topped,  -1-hidden, -2 deleted
+    status - 0-Normal,1-Banned
+    merged_to - the tag this one was merged into, 0-not merged
+*/
+
+CREATE TABLE IF NOT EXISTS dg_tags (
+  id bigint unsigned NOT NULL AUTO_INCREMENT,
+  app_id bigint unsigned NOT NULL,
+  `name` varchar(128) NOT NULL,
+  status tinyint NOT NULL,
+  merged_to bigint unsigned NOT NULL DEFAULT 0,
+  topic_count bigint unsigned NOT NULL DEFAULT 0,
+  created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+  PRIMARY KEY (id),
+  UNIQUE KEY UK_tag_name (app_id,`name`),
+  KEY IX_tag_popular (app_id,status,merged_to,topic_count)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 AUTO_INCREMENT=1000;
+
+CREATE TABLE IF NOT EXISTS dg_topic_tags (
+  topic_id bigint unsigned NOT NULL,
+  tag_id bigint unsigned NOT NULL,
+  app_id bigint unsigned NOT NULL,
+  created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+  PRIMARY KEY (topic_id,tag_id),
+  KEY IX_topic_tag (tag_id,topic_id)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4;
+
+/*
+    topped - 0-normal, >0 topped,  -1-hidden, -2 deleted, -3 deleted by author
+    content_format - 0-Plain,1-Markdown
+    parent_reply_id - replied reply, 0 for a top-level reply
+    root_reply_id - top-level reply of the thread, 0 for a top-level reply
+    quote_reply_id - quoted reply of the same topic, 0 for none
+    child_count - direct children visible
+    reaction_counts - JSON object of reaction name to count, like_count mirrors the like reaction
+    score - like_count minus downvotes
+    updated_at - last change of the indexed columns (counters keep it), followed by the search index of each instance
 */
 
 CREATE TABLE IF NOT EXISTS dg_replies (
@@ -73,11 +186,196 @@ CREATE TABLE IF NOT EXISTS dg_replies (
   app_id bigint unsigned NOT NULL,
   topic_id bigint unsigned NOT NULL,
   user_id bigint unsigned NOT NULL,
+  parent_reply_id bigint unsigned NOT NULL DEFAULT 0,
+  root_reply_id bigint unsigned NOT NULL DEFAULT 0,
+  quote_reply_id bigint unsigned NOT NULL DEFAULT 0,
+  content_format tinyint NOT NULL DEFAULT 0,
   content text NOT NULL,
+  content_html text NOT NULL,
+  excerpt varchar(1024) NOT NULL DEFAULT '',
   topped bigint NOT NULL,
   created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
   like_count bigint unsigned NOT NULL DEFAULT 0,
+  score bigint NOT NULL DEFAULT 0,
+  child_count bigint unsigned NOT NULL DEFAULT 0,
+  reaction_counts varchar(1024) NOT NULL DEFAULT '{}',
+  edited_at DATETIME,
+  updated_at DATETIME DEFAULT CURRENT_TIMESTAMP ON UPDATE CURRENT_TIMESTAMP,
   PRIMARY KEY (id),
   KEY IX_reply_user (user_id,topped),
-  KEY IX_reply_create (topic_id,topped,created_at)
+  KEY IX_reply_create (topic_id,topped,created_at),
+  KEY IX_reply_parent (parent_reply_id,topped,created_at),
+  KEY IX_reply_updated (updated_at,id)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 AUTO_INCREMENT=1000;
+
+/*
+    snapshot of a reply taken before each edit
+    editor_type - 0-User,1-Manager
+*/
+
+CREATE TABLE IF NOT EXISTS dg_reply_revisions (
+  id bigint unsigned NOT NULL AUTO_INCREMENT,
+  app_id bigint unsigned NOT NULL,
+  reply_id bigint unsigned NOT NULL,
+  editor_type tinyint NOT NULL,
+  editor_id bigint unsigned NOT NULL,
+  content_format tinyint NOT NULL DEFAULT 0,
+  content text NOT NULL,
+  created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+  PRIMARY KEY (id),
+  KEY IX_revision_reply (reply_id,id)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 AUTO_INCREMENT=1000;
+
+/*
+    target_type - 0-None,1-Topic,2-Reply
+*/
+
+CREATE TABLE IF NOT EXISTS dg_attachments (
+  id bigint unsigned NOT NULL AUTO_INCREMENT,
+  app_id bigint unsigned NOT NULL,
+  user_id bigint unsigned NOT NULL,
+  target_type tinyint NOT NULL,
+  target_id bigint unsigned NOT NULL,
+  storage_key varchar(256) NOT NULL,
+  file_name varchar(256) NOT NULL,
+  mime_type varchar(128) NOT NULL,
+  size int unsigned NOT NULL,
+  created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+  PRIMARY KEY (id),
+  KEY IX_attachment_target (target_type,target_id),
+  KEY IX_attachment_unbound (target_id,created_at)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 AUTO_INCREMENT=1000;
+
+CREATE TABLE IF NOT EXISTS dg_polls (
+  id bigint unsigned NOT NULL AUTO_INCREMENT,
+  app_id bigint unsigned NOT NULL,
+  topic_id bigint unsigned NOT NULL,
+  multiple tinyint(1) NOT NULL DEFAULT 0,
+  anonymous tinyint(1) NOT NULL DEFAULT 0,
+  voter_count bigint unsigned NOT NULL DEFAULT 0,
+  close_at DATETIME DEFAULT NULL,
+  created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+  PRIMARY KEY (id),
+  UNIQUE KEY UK_poll_topic (topic_id)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 AUTO_INCREMENT=1000;
+
+CREATE TABLE IF NOT EXISTS dg_poll_options (
+  id bigint unsigned NOT NULL AUTO_INCREMENT,
+  poll_id bigint unsigned NOT NULL,
+  position int unsigned NOT NULL,
+  title varchar(512) NOT NULL,
+  vote_count bigint unsigned NOT NULL DEFAULT 0,
+  PRIMARY KEY (id),
+  KEY IX_option_poll (poll_id,position)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 AUTO_INCREMENT=1000;
+
+/*
+    one row per voter of a poll, guards against voting twice
+*/
+
+CREATE TABLE IF NOT EXISTS dg_poll_voters (
+  poll_id bigint unsigned NOT NULL,
+  user_id bigint unsigned NOT NULL,
+  created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+  PRIMARY KEY (poll_id,user_id)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4;
+
+CREATE TABLE IF NOT EXISTS dg_poll_votes (
+  poll_id bigint unsigned NOT NULL,
+  option_id bigint unsigned NOT NULL,
+  user_id bigint unsigned NOT NULL,
+  created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+  PRIMARY KEY (poll_id,user_id,option_id),
+  KEY IX_vote_option (option_id,created_at)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4;
+
+CREATE TABLE IF NOT EXISTS dg_bookmarks (
+  user_id bigint unsigned NOT NULL,
+  topic_id bigint unsigned NOT NULL,
+  app_id bigint unsigned NOT NULL,
+  created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+  PRIMARY KEY (user_id,topic_id),
+  KEY IX_bookmark_topic (topic_id)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4;
+
+/*
+    target_type - 1-Topic,2-Reply
+    parent_reply_id - reply drafts only, the reply answered
+    quote_reply_id - reply drafts only, the reply quoted
+    tags - comma separated tag names
+*/
+
+CREATE TABLE IF NOT EXISTS dg_drafts (
+  id bigint unsigned NOT NULL AUTO_INCREMENT,
+  app_id bigint unsigned NOT NULL,
+  user_id bigint unsigned NOT NULL,
+  target_type tinyint NOT NULL,
+  topic_id bigint unsigned NOT NULL DEFAULT 0,
+  parent_reply_id bigint unsigned NOT NULL DEFAULT 0,
+  quote_reply_id bigint unsigned NOT NULL DEFAULT 0,
+  category bigint unsigned NOT NULL DEFAULT 0,
+  title varchar(1024) NOT NULL DEFAULT '',
+  content_format tinyint NOT NULL DEFAULT 0,
+  content text NOT NULL,
+  tags varchar(512) NOT NULL DEFAULT '',
+  created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+  updated_at DATETIME NOT NULL,
+  PRIMARY KEY (id),
+  KEY IX_draft_user (user_id,updated_at),
+  KEY IX_draft_updated (updated_at)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 AUTO_INCREMENT=1000;
+
+/*
+    kind - 1-TopicMoved,2-TopicReplied,3-ReplyReplied,4-TopicLiked,5-ReplyLiked,6-Mentioned
+    actor_id - latest user behind the event
+    actor_count - users folded into an unread notification
+    group_key - kind and target shared by the events folded together, NULL once read
+    content - title of the topic at the time of the event
+*/
+
+CREATE TABLE IF NOT EXISTS dg_notifications (
+  id bigint unsigned NOT NULL AUTO_INCREMENT,
+  app_id bigint unsigned NOT NULL,
+  user_id bigint unsigned NOT NULL,
+  kind tinyint NOT NULL,
+  topic_id bigint unsigned NOT NULL DEFAULT 0,
+  reply_id bigint unsigned NOT NULL DEFAULT 0,
+  actor_id bigint unsigned NOT NULL DEFAULT 0,
+  actor_count int unsigned NOT NULL DEFAULT 1,
+  content varchar(512) NOT NULL DEFAULT '',
+  group_key varchar(64) DEFAULT NULL,
+  is_read tinyint(1) NOT NULL DEFAULT 0,
+  created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+  updated_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+  PRIMARY KEY (id),
+  UNIQUE KEY UK_notification_group (user_id,group_key),
+  KEY IX_notification_user (user_id,updated_at),
+  KEY IX_notification_unread (user_id,is_read)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 AUTO_INCREMENT=1000;
+
+/*
+    distinct users folded into an unread notification, dropped once it is read
+*/
+
+CREATE TABLE IF NOT EXISTS dg_notification_actors (
+  notification_id bigint unsigned NOT NULL,
+  actor_id bigint unsigned NOT NULL,
+  PRIMARY KEY (notification_id,actor_id)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4;
+
+/*
+    target_type - 1-Topic,2-Reply
+    reaction - name of the reaction, like for plain likes
+*/
+
+CREATE TABLE IF NOT EXISTS dg_likes (
+  id bigint unsigned NOT NULL AUTO_INCREMENT,
+  app_id bigint unsigned NOT NULL,
+  target_type tinyint NOT NULL,
+  target_id bigint unsigned NOT NULL,
+  user_id bigint unsigned NOT NULL,
+  reaction varchar(32) NOT NULL,
+  created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+  PRIMARY KEY (id),
+  UNIQUE KEY UX_like_target (target_type,target_id,reaction,user_id)
 ) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 AUTO_INCREMENT=1000;
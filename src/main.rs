@@ -12,8 +12,14 @@ async fn main() {
         .parse()
         .expect("WEB_PORT must an int");
 
+    tracing_subscriber::fmt::init();
     shared::init();
 
+    if let Err(e) = shared::scheduler::migrate().await {
+        panic!("data migration failed: {}", e);
+    }
+    tokio::spawn(shared::scheduler::run());
+
     let app = Router::new()
         .route("/", get(root))
         .nest("/sdk", service_sdk::setup_routers())
@@ -22,7 +28,7 @@ async fn main() {
 
     let addr = SocketAddr::from(([0, 0, 0, 0], web_port));
     axum::Server::bind(&addr)
-        .serve(app.into_make_service())
+        .serve(app.into_make_service_with_connect_info::<SocketAddr>())
         .await
         .unwrap();
 }
@@ -6,6 +6,7 @@ use axum::{
 use super::base::*;
 use crate::shared::data::*;
 use crate::shared::model::*;
+use crate::shared::reaction;
 use crate::shared::web::*;
 
 async fn root() -> &'static str {
@@ -18,6 +19,7 @@ pub fn setup_routers() -> Router {
         .route("/create", post(app_create))
         .route("/detail", get(app_detail))
         .route("/list", get(app_list))
+        .route("/update/setting", post(app_update_setting))
 }
 
 #[derive(Validate, Deserialize)]
@@ -107,3 +109,71 @@ async fn app_list(
 
     Ok(api_success(AppListResponse { total, apps }))
 }
+
+#[derive(Deserialize)]
+struct AppUpdateSettingPayload {
+    app_id: u64,
+    edit_window: Option<u32>,
+    upload_max_size: Option<u32>,
+    upload_mime_types: Option<String>,
+    author_lock: Option<bool>,
+    auto_lock_days: Option<u32>,
+    reaction_types: Option<Vec<String>>,
+    downvote_enabled: Option<bool>,
+    qa_categories: Option<Vec<u64>>,
+}
+
+async fn app_update_setting(
+    _claims: MgrClaims,
+    Json(payload): Json<AppUpdateSettingPayload>,
+) -> Result<ApiSuccess<AppDetailResponse>, ApiError> {
+    let mut conn = database_connect().await?;
+
+    let mut app = app::get_by_id(&mut conn, payload.app_id).await?;
+    if let Some(edit_window) = payload.edit_window {
+        app.edit_window = edit_window;
+    }
+    if let Some(upload_max_size) = payload.upload_max_size {
+        // larger bodies are cut off by the upload route anyway
+        app.upload_max_size = upload_max_size.min(attachment::MAX_UPLOAD_SIZE as u32);
+    }
+    if let Some(upload_mime_types) = payload.upload_mime_types {
+        app.upload_mime_types = upload_mime_types;
+    }
+    if let Some(author_lock) = payload.author_lock {
+        app.author_lock = author_lock;
+    }
+    if let Some(auto_lock_days) = payload.auto_lock_days {
+        app.auto_lock_days = auto_lock_days;
+    }
+    if let Some(reaction_types) = payload.reaction_types {
+        if reaction_types.len() > reaction::MAX_REACTION_TYPES
+            || !reaction_types
+                .iter()
+                .all(|s| reaction::is_valid_name(s) && s != reaction::DOWNVOTE_REACTION)
+        {
+            return Err(api_error(ApiErrorCode::InvalidParams));
+        }
+        app.reaction_types = reaction_types.join(",");
+        // dedup and keep the default reaction first
+        app.reaction_types = app.reaction_list().join(",");
+    }
+    if let Some(downvote_enabled) = payload.downvote_enabled {
+        app.downvote_enabled = downvote_enabled;
+    }
+    if let Some(mut qa_categories) = payload.qa_categories {
+        qa_categories.sort();
+        qa_categories.dedup();
+        app.qa_categories = qa_categories
+            .iter()
+            .map(|c| c.to_string())
+            .collect::<Vec<String>>()
+            .join(",");
+        if app.qa_categories.len() > 512 {
+            return Err(api_error(ApiErrorCode::InvalidParams));
+        }
+    }
+    app::update_setting(&mut conn, &app).await?;
+
+    Ok(api_success(AppDetailResponse { app }))
+}
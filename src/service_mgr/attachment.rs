@@ -0,0 +1,42 @@
+use axum::{
+    routing::{get, post},
+    Router,
+};
+
+use super::base::*;
+use crate::shared::data::*;
+use crate::shared::model::*;
+use crate::shared::web::*;
+
+async fn root() -> &'static str {
+    "DoggTalk MGR Attachment API"
+}
+
+pub fn setup_routers() -> Router {
+    Router::new()
+        .route("/", get(root))
+        .route("/gc", post(attachment_gc))
+}
+
+#[derive(Deserialize)]
+struct AttachmentGcPayload {
+    ttl: Option<u32>,
+}
+
+#[derive(Serialize)]
+struct AttachmentGcResponse {
+    purged: u32,
+}
+
+async fn attachment_gc(
+    _claims: MgrClaims,
+    Json(payload): Json<AttachmentGcPayload>,
+) -> Result<ApiSuccess<AttachmentGcResponse>, ApiError> {
+    let mut conn = database_connect().await?;
+
+    let purged =
+        attachment::purge_unbound(&mut conn, payload.ttl.unwrap_or(attachment::UNBOUND_TTL))
+            .await?;
+
+    Ok(api_success(AttachmentGcResponse { purged }))
+}
@@ -1,9 +1,12 @@
 use axum::{routing::get, Router};
 
 mod app;
+mod attachment;
 mod base;
 mod manager;
 mod reply;
+mod search;
+mod tag;
 mod topic;
 mod user;
 
@@ -19,4 +22,7 @@ pub fn setup_routers() -> Router {
         .nest("/user", user::setup_routers())
         .nest("/topic", topic::setup_routers())
         .nest("/reply", reply::setup_routers())
+        .nest("/search", search::setup_routers())
+        .nest("/tag", tag::setup_routers())
+        .nest("/attachment", attachment::setup_routers())
 }
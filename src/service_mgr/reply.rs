@@ -6,7 +6,11 @@ use axum::{
 use super::base::*;
 use crate::shared::base::*;
 use crate::shared::data::*;
+use crate::shared::diff::*;
+use crate::shared::inbox;
 use crate::shared::model::*;
+use crate::shared::reaction;
+use crate::shared::search;
 use crate::shared::web::*;
 
 async fn root() -> &'static str {
@@ -18,14 +22,25 @@ pub fn setup_routers() -> Router {
         .route("/", get(root))
         .route("/create", post(reply_create))
         .route("/list", get(reply_list))
+        .route("/likers", get(reply_likers))
+        .route("/update", post(reply_update))
         .route("/update/status", post(reply_update_status))
+        .route("/revision/list", get(reply_revision_list))
+        .route("/revision/diff", get(reply_revision_diff))
 }
 
-#[derive(Deserialize)]
+#[derive(Validate, Deserialize)]
 struct ReplyCreatePayload {
     app_id: u64,
     user_id: u64,
     topic_id: u64,
+    #[serde(default)]
+    parent_reply_id: u64,
+    #[serde(default)]
+    quote_reply_id: u64,
+    #[serde(default)]
+    #[validate(custom = "validate_content_format")]
+    content_format: i8,
     content: String,
 }
 
@@ -38,6 +53,11 @@ async fn reply_create(
     _claims: MgrClaims,
     Json(payload): Json<ReplyCreatePayload>,
 ) -> Result<ApiSuccess<ReplyCreateResponse>, ApiError> {
+    match payload.validate() {
+        Err(e) => return Err(api_errore(ApiErrorCode::InvalidParams, &e)),
+        _ => {}
+    };
+
     let mut conn = database_connect().await?;
 
     let user = user::get_by_id(&mut conn, payload.user_id).await?;
@@ -52,19 +72,52 @@ async fn reply_create(
     if !topic.is_actived() {
         return Err(api_error(ApiErrorCode::TopicNotFound));
     }
+    if topic.moved_to > 0 {
+        return Err(api_error(ApiErrorCode::TopicMoved));
+    }
+
+    let app = app::get_by_id(&mut conn, topic.app_id).await?;
+    if topic.locked || app.is_auto_locked(topic.refreshed_at) {
+        return Err(api_error(ApiErrorCode::TopicLocked));
+    }
+
+    let mut root_reply_id = 0;
+    if payload.parent_reply_id > 0 {
+        let parent = reply::get_by_id(&mut conn, payload.parent_reply_id).await?;
+        if parent.topic_id != topic.id || !parent.is_actived() {
+            return Err(api_error(ApiErrorCode::ReplyNotFound));
+        }
+        root_reply_id = parent.root_id();
+    }
+
+    if payload.quote_reply_id > 0 {
+        let quoted = reply::get_by_id(&mut conn, payload.quote_reply_id).await?;
+        if quoted.topic_id != topic.id || !quoted.is_actived() {
+            return Err(api_error(ApiErrorCode::ReplyNotFound));
+        }
+    }
 
     let mut reply = reply::ReplyModel {
         app_id: topic.app_id,
         topic_id: topic.id,
         user_id: user.id,
+        parent_reply_id: payload.parent_reply_id,
+        root_reply_id,
+        quote_reply_id: payload.quote_reply_id,
+        content_format: payload.content_format,
         content: payload.content,
         ..Default::default()
     };
 
     let reply_id = reply::create(&mut conn, &mut reply).await?;
     topic::update_reply_count(&mut conn, topic.id, UpdateCountOp::INCR).await?;
+    if payload.parent_reply_id > 0 {
+        reply::update_child_count(&mut conn, payload.parent_reply_id, UpdateCountOp::INCR).await?;
+    }
 
     let reply = reply::get_by_id(&mut conn, reply_id).await?;
+    search::sync_reply(&reply, &topic);
+    inbox::notify_reply(&mut conn, &topic, &reply).await;
 
     Ok(api_success(ReplyCreateResponse { reply_id: reply.id }))
 }
@@ -74,6 +127,8 @@ struct ReplyListPayload {
     app_id: u64,
     topic_id: u64,
     style: reply::VisibleStyle,
+    #[serde(default)]
+    order_by: reply::VisibleOrderBy,
     cursor: u32,
     #[validate(custom = "validate_page_count")]
     count: u32,
@@ -83,6 +138,8 @@ struct ReplyListPayload {
 struct ReplyListItem {
     reply: reply::ReplySimple,
     user: Arc<user::UserSimple>,
+    attachments: Arc<Vec<attachment::AttachmentSimple>>,
+    quote: Option<reply::ReplyQuoteItem>,
 }
 
 #[derive(Serialize)]
@@ -109,21 +166,30 @@ async fn reply_list(
 
     let (total, replies) = reply::fetch_pagging(
         &mut conn,
-        topic.id,
+        &topic,
         payload.style,
+        payload.order_by,
         payload.cursor,
         payload.count,
     )
     .await?;
 
-    let user_map =
-        user::get_simple_map_by_ids(&mut conn, replies.iter().map(|s| s.user_id).collect()).await?;
+    let (quote_map, user_ids) = reply::get_quote_map(&mut conn, &replies).await?;
+    let user_map = user::get_simple_map_by_ids(&mut conn, user_ids).await?;
+    let attachment_map = attachment::get_simple_map_by_targets(
+        &mut conn,
+        attachment::TARGET_REPLY,
+        replies.iter().map(|s| s.id).collect(),
+    )
+    .await?;
 
     let replies = replies
         .iter()
         .map(|s| ReplyListItem {
             user: user_map.get(s.user_id),
             reply: s.to_simple(),
+            attachments: attachment_map.get(s.id),
+            quote: reply::build_quote(s.quote_reply_id, &quote_map, &user_map),
         })
         .collect();
 
@@ -156,12 +222,240 @@ async fn reply_update_status(
         return Err(api_error(ApiErrorCode::ReplyNotFound));
     }
 
-    reply::update_status(&mut conn, reply.id, payload.action.clone()).await?;
     if payload.action == reply::StatusAction::DELETE {
-        topic::update_reply_count(&mut conn, reply.topic_id, UpdateCountOp::DECR).await?;
+        if reply::delete(&mut conn, reply.id, reply::StatusOperator::MANAGER).await? {
+            topic::update_reply_count(&mut conn, reply.topic_id, UpdateCountOp::DECR).await?;
+            topic::clear_accepted_reply(&mut conn, reply.topic_id, reply.id).await?;
+            // hidden replies were taken off the count already
+            if reply.parent_reply_id > 0 && reply.is_actived() {
+                reply::update_child_count(&mut conn, reply.parent_reply_id, UpdateCountOp::DECR)
+                    .await?;
+            }
+            attachment::purge_target(&mut conn, attachment::TARGET_REPLY, reply.id).await?;
+        }
+    } else {
+        reply::update_status(
+            &mut conn,
+            reply.id,
+            payload.action.clone(),
+            reply::StatusOperator::MANAGER,
+        )
+        .await?;
+
+        // child_count only counts visible children
+        let hidden = payload.action == reply::StatusAction::HIDDEN;
+        if hidden {
+            topic::clear_accepted_reply(&mut conn, reply.topic_id, reply.id).await?;
+        }
+        if reply.parent_reply_id > 0 && reply.is_actived() == hidden {
+            let op = match hidden {
+                true => UpdateCountOp::DECR,
+                _ => UpdateCountOp::INCR,
+            };
+            reply::update_child_count(&mut conn, reply.parent_reply_id, op).await?;
+        }
     }
 
+    let topic = topic::get_by_id(&mut conn, reply.topic_id).await?;
+    let reply = reply::get_by_id(&mut conn, reply.id).await?;
+    search::sync_reply(&reply, &topic);
+
     Ok(api_success(ReplyUpdateStatusResponse {
         reply_id: reply.id,
     }))
 }
+
+#[derive(Validate, Deserialize)]
+struct ReplyUpdatePayload {
+    app_id: u64,
+    reply_id: u64,
+    #[serde(default)]
+    #[validate(custom = "validate_content_format")]
+    content_format: i8,
+    #[validate(length(min = 1, max = 16000))]
+    content: String,
+}
+
+#[derive(Serialize)]
+struct ReplyUpdateResponse {
+    reply: reply::ReplySimple,
+}
+
+async fn reply_update(
+    claims: MgrClaims,
+    Json(payload): Json<ReplyUpdatePayload>,
+) -> Result<ApiSuccess<ReplyUpdateResponse>, ApiError> {
+    match payload.validate() {
+        Err(e) => return Err(api_errore(ApiErrorCode::InvalidParams, &e)),
+        _ => {}
+    };
+
+    let mut conn = database_connect().await?;
+
+    let mut reply = reply::get_by_id(&mut conn, payload.reply_id).await?;
+    if reply.app_id != payload.app_id {
+        return Err(api_error(ApiErrorCode::NoPermission));
+    }
+    if reply.is_deleted() {
+        return Err(api_error(ApiErrorCode::ReplyNotFound));
+    }
+
+    let mut revision = reply_revision::ReplyRevisionModel::from_reply(
+        &reply,
+        reply_revision::EDITOR_MANAGER,
+        claims.mgr_id,
+    );
+    reply_revision::create(&mut conn, &mut revision).await?;
+
+    reply.content_format = payload.content_format;
+    reply.content = payload.content;
+    reply::update_content(&mut conn, &mut reply).await?;
+
+    let topic = topic::get_by_id(&mut conn, reply.topic_id).await?;
+    let reply = reply::get_by_id(&mut conn, reply.id).await?;
+    search::sync_reply(&reply, &topic);
+
+    Ok(api_success(ReplyUpdateResponse {
+        reply: reply.to_simple(),
+    }))
+}
+
+#[derive(Validate, Deserialize)]
+struct ReplyRevisionListPayload {
+    app_id: u64,
+    reply_id: u64,
+    cursor: u32,
+    #[validate(custom = "validate_page_count")]
+    count: u32,
+}
+
+#[derive(Serialize)]
+struct ReplyRevisionListResponse {
+    total: u32,
+    revisions: Vec<reply_revision::ReplyRevisionSimple>,
+}
+
+async fn reply_revision_list(
+    _claims: MgrClaims,
+    Query(payload): Query<ReplyRevisionListPayload>,
+) -> Result<ApiSuccess<ReplyRevisionListResponse>, ApiError> {
+    match payload.validate() {
+        Err(e) => return Err(api_errore(ApiErrorCode::InvalidParams, &e)),
+        _ => {}
+    };
+
+    let mut conn = database_connect().await?;
+
+    let reply = reply::get_by_id(&mut conn, payload.reply_id).await?;
+    if reply.app_id != payload.app_id {
+        return Err(api_error(ApiErrorCode::NoPermission));
+    }
+
+    let (total, revisions) =
+        reply_revision::fetch_pagging(&mut conn, reply.id, payload.cursor, payload.count).await?;
+
+    let revisions = revisions.iter().map(|s| s.to_simple()).collect();
+
+    Ok(api_success(ReplyRevisionListResponse { total, revisions }))
+}
+
+#[derive(Deserialize)]
+struct ReplyRevisionDiffPayload {
+    app_id: u64,
+    reply_id: u64,
+    from_id: u64,
+    to_id: u64,
+}
+
+#[derive(Serialize)]
+struct ReplyRevisionDiffResponse {
+    content: Vec<DiffLine>,
+}
+
+async fn reply_revision_diff(
+    _claims: MgrClaims,
+    Query(payload): Query<ReplyRevisionDiffPayload>,
+) -> Result<ApiSuccess<ReplyRevisionDiffResponse>, ApiError> {
+    let mut conn = database_connect().await?;
+
+    let reply = reply::get_by_id(&mut conn, payload.reply_id).await?;
+    if reply.app_id != payload.app_id {
+        return Err(api_error(ApiErrorCode::NoPermission));
+    }
+
+    let from = reply_revision::get_by_id(&mut conn, payload.from_id).await?;
+    if from.reply_id != reply.id {
+        return Err(api_error(ApiErrorCode::RevisionNotFound));
+    }
+
+    // to_id 0 compares against the current reply
+    let content = if payload.to_id == 0 {
+        reply.content
+    } else {
+        let to = reply_revision::get_by_id(&mut conn, payload.to_id).await?;
+        if to.reply_id != reply.id {
+            return Err(api_error(ApiErrorCode::RevisionNotFound));
+        }
+        to.content
+    };
+
+    Ok(api_success(ReplyRevisionDiffResponse {
+        content: diff_lines(&from.content, &content),
+    }))
+}
+
+#[derive(Validate, Deserialize)]
+struct ReplyLikersPayload {
+    app_id: u64,
+    reply_id: u64,
+    reaction: Option<String>,
+    cursor: u32,
+    #[validate(custom = "validate_page_count")]
+    count: u32,
+}
+
+#[derive(Serialize)]
+struct ReplyLikersResponse {
+    total: u32,
+    users: Vec<reaction::ReactorItem>,
+}
+
+/// Users who liked the reply, or reacted with `reaction`, newest first.
+async fn reply_likers(
+    _claims: MgrClaims,
+    Query(payload): Query<ReplyLikersPayload>,
+) -> Result<ApiSuccess<ReplyLikersResponse>, ApiError> {
+    match payload.validate() {
+        Err(e) => return Err(api_errore(ApiErrorCode::InvalidParams, &e)),
+        _ => {}
+    };
+
+    let name = payload
+        .reaction
+        .unwrap_or(String::from(reaction::DEFAULT_REACTION));
+    if !reaction::is_valid_name(&name) {
+        return Err(api_error(ApiErrorCode::InvalidParams));
+    }
+
+    let mut conn = database_connect().await?;
+
+    let reply = reply::get_by_id(&mut conn, payload.reply_id).await?;
+    if payload.app_id != reply.app_id {
+        return Err(api_error(ApiErrorCode::NoPermission));
+    }
+    if reply.is_deleted() {
+        return Err(api_error(ApiErrorCode::ReplyNotFound));
+    }
+
+    let (total, users) = reaction::fetch_reactors(
+        &mut conn,
+        reaction::TARGET_REPLY,
+        reply.id,
+        &name,
+        payload.cursor,
+        payload.count,
+    )
+    .await?;
+
+    Ok(api_success(ReplyLikersResponse { total, users }))
+}
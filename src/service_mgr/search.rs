@@ -0,0 +1,91 @@
+use axum::{
+    routing::{get, post},
+    Router,
+};
+
+use super::base::*;
+use crate::shared::data::*;
+use crate::shared::model::*;
+use crate::shared::search;
+use crate::shared::web::*;
+
+async fn root() -> &'static str {
+    "DoggTalk MGR Search API"
+}
+
+pub fn setup_routers() -> Router {
+    Router::new()
+        .route("/", get(root))
+        .route("/query", get(search_query))
+        .route("/rebuild", post(search_rebuild))
+}
+
+#[derive(Validate, Deserialize)]
+struct SearchQueryPayload {
+    app_id: u64,
+    category: u64,
+    #[validate(length(min = 1, max = 100))]
+    keyword: String,
+    cursor: u32,
+    #[validate(custom = "validate_page_count")]
+    count: u32,
+}
+
+#[derive(Serialize)]
+struct SearchQueryResponse {
+    total: u32,
+    items: Vec<search::SearchItem>,
+}
+
+async fn search_query(
+    _claims: MgrClaims,
+    Query(payload): Query<SearchQueryPayload>,
+) -> Result<ApiSuccess<SearchQueryResponse>, ApiError> {
+    match payload.validate() {
+        Err(e) => return Err(api_errore(ApiErrorCode::InvalidParams, &e)),
+        _ => {}
+    };
+
+    let mut conn = database_connect().await?;
+
+    app::get_by_id(&mut conn, payload.app_id).await?;
+
+    let (total, items) = search::query(
+        &mut conn,
+        payload.app_id,
+        payload.category,
+        &payload.keyword,
+        payload.cursor,
+        payload.count,
+    )
+    .await?;
+
+    Ok(api_success(SearchQueryResponse { total, items }))
+}
+
+#[derive(Deserialize)]
+struct SearchRebuildPayload {
+    app_id: u64,
+}
+
+#[derive(Serialize)]
+struct SearchRebuildResponse {
+    topic_count: u32,
+    reply_count: u32,
+}
+
+async fn search_rebuild(
+    _claims: MgrClaims,
+    Json(payload): Json<SearchRebuildPayload>,
+) -> Result<ApiSuccess<SearchRebuildResponse>, ApiError> {
+    let mut conn = database_connect().await?;
+
+    let app = app::get_by_id(&mut conn, payload.app_id).await?;
+
+    let (topic_count, reply_count) = search::rebuild(&mut conn, app.id).await?;
+
+    Ok(api_success(SearchRebuildResponse {
+        topic_count,
+        reply_count,
+    }))
+}
@@ -0,0 +1,137 @@
+use axum::{
+    routing::{get, post},
+    Router,
+};
+
+use super::base::*;
+use crate::shared::data::*;
+use crate::shared::model::*;
+use crate::shared::web::*;
+
+async fn root() -> &'static str {
+    "DoggTalk MGR Tag API"
+}
+
+pub fn setup_routers() -> Router {
+    Router::new()
+        .route("/", get(root))
+        .route("/list", get(tag_list))
+        .route("/merge", post(tag_merge))
+        .route("/update/status", post(tag_update_status))
+}
+
+#[derive(Validate, Deserialize)]
+struct TagListPayload {
+    app_id: u64,
+    style: tag::VisibleStyle,
+    cursor: u32,
+    #[validate(custom = "validate_page_count")]
+    count: u32,
+}
+
+#[derive(Serialize)]
+struct TagListResponse {
+    total: u32,
+    tags: Vec<tag::TagModel>,
+}
+
+async fn tag_list(
+    _claims: MgrClaims,
+    Query(payload): Query<TagListPayload>,
+) -> Result<ApiSuccess<TagListResponse>, ApiError> {
+    match payload.validate() {
+        Err(e) => return Err(api_errore(ApiErrorCode::InvalidParams, &e)),
+        _ => {}
+    };
+
+    let mut conn = database_connect().await?;
+
+    app::get_by_id(&mut conn, payload.app_id).await?;
+
+    let (total, tags) = tag::fetch_pagging(
+        &mut conn,
+        payload.app_id,
+        payload.style,
+        payload.cursor,
+        payload.count,
+    )
+    .await?;
+
+    Ok(api_success(TagListResponse { total, tags }))
+}
+
+#[derive(Deserialize)]
+struct TagMergePayload {
+    app_id: u64,
+    from_id: u64,
+    to_id: u64,
+}
+
+#[derive(Serialize)]
+struct TagMergeResponse {
+    tag: tag::TagModel,
+}
+
+async fn tag_merge(
+    _claims: MgrClaims,
+    Json(payload): Json<TagMergePayload>,
+) -> Result<ApiSuccess<TagMergeResponse>, ApiError> {
+    if payload.from_id == payload.to_id {
+        return Err(api_error2(ApiErrorCode::InvalidParams, "merge into itself"));
+    }
+
+    let mut conn = database_connect().await?;
+
+    let from = tag::get_by_id(&mut conn, payload.from_id).await?;
+    if from.app_id != payload.app_id {
+        return Err(api_error(ApiErrorCode::NoPermission));
+    }
+    if from.merged_to > 0 {
+        return Err(api_error(ApiErrorCode::TagNotFound));
+    }
+
+    let to = tag::get_by_id(&mut conn, payload.to_id).await?;
+    if to.app_id != payload.app_id {
+        return Err(api_error(ApiErrorCode::NoPermission));
+    }
+    if !to.is_actived() {
+        return Err(api_error(ApiErrorCode::TagNotFound));
+    }
+
+    tag::merge(&mut conn, &from, &to).await?;
+
+    let tag = tag::get_by_id(&mut conn, to.id).await?;
+
+    Ok(api_success(TagMergeResponse { tag }))
+}
+
+#[derive(Deserialize)]
+struct TagUpdateStatusPayload {
+    app_id: u64,
+    tag_id: u64,
+    action: tag::StatusAction,
+}
+
+#[derive(Serialize)]
+struct TagUpdateStatusResponse {
+    tag_id: u64,
+}
+
+async fn tag_update_status(
+    _claims: MgrClaims,
+    Json(payload): Json<TagUpdateStatusPayload>,
+) -> Result<ApiSuccess<TagUpdateStatusResponse>, ApiError> {
+    let mut conn = database_connect().await?;
+
+    let tag = tag::get_by_id(&mut conn, payload.tag_id).await?;
+    if tag.app_id != payload.app_id {
+        return Err(api_error(ApiErrorCode::NoPermission));
+    }
+    if tag.merged_to > 0 {
+        return Err(api_error(ApiErrorCode::TagNotFound));
+    }
+
+    tag::update_status(&mut conn, tag.id, payload.action).await?;
+
+    Ok(api_success(TagUpdateStatusResponse { tag_id: tag.id }))
+}
@@ -6,7 +6,12 @@ use axum::{
 use super::base::*;
 use crate::shared::base::*;
 use crate::shared::data::*;
+use crate::shared::diff::*;
+use crate::shared::inbox;
 use crate::shared::model::*;
+use crate::shared::ranking;
+use crate::shared::reaction;
+use crate::shared::search;
 use crate::shared::web::*;
 
 async fn root() -> &'static str {
@@ -17,18 +22,43 @@ pub fn setup_routers() -> Router {
     Router::new()
         .route("/", get(root))
         .route("/create", post(topic_create))
+        .route("/update", post(topic_update))
         .route("/detail", get(topic_detail))
         .route("/list", get(topic_list))
+        .route("/likers", get(topic_likers))
         .route("/update/status", post(topic_update_status))
+        .route("/lock", post(topic_lock))
+        .route("/unlock", post(topic_unlock))
+        .route("/move", post(topic_move))
+        .route("/accept", post(topic_accept))
+        .route("/revision/list", get(topic_revision_list))
+        .route("/revision/diff", get(topic_revision_diff))
 }
 
 #[derive(Deserialize)]
+struct TopicPollPayload {
+    options: Vec<String>,
+    #[serde(default)]
+    multiple: bool,
+    #[serde(default)]
+    anonymous: bool,
+    close_at: Option<i64>,
+}
+
+#[derive(Validate, Deserialize)]
 struct TopicCreatePayload {
     app_id: u64,
     user_id: u64,
     category: u64,
     title: String,
+    #[serde(default)]
+    #[validate(custom = "validate_content_format")]
+    content_format: i8,
     content: String,
+    tags: Option<Vec<String>>,
+    poll: Option<TopicPollPayload>,
+    #[validate(custom = "validate_future_time")]
+    publish_at: Option<i64>,
 }
 
 #[derive(Serialize)]
@@ -40,6 +70,11 @@ async fn topic_create(
     _claims: MgrClaims,
     Json(payload): Json<TopicCreatePayload>,
 ) -> Result<ApiSuccess<TopicCreateResponse>, ApiError> {
+    match payload.validate() {
+        Err(e) => return Err(api_errore(ApiErrorCode::InvalidParams, &e)),
+        _ => {}
+    };
+
     let mut conn = database_connect().await?;
 
     let user = user::get_by_id(&mut conn, payload.user_id).await?;
@@ -47,23 +82,122 @@ async fn topic_create(
         return Err(api_error(ApiErrorCode::NoPermission));
     }
 
+    let tag_names = tag::collect_names(payload.tags, &payload.content);
+    let poll = match payload.poll {
+        Some(poll) => {
+            let (options, close_at) = poll::check_options(poll.options, poll.close_at)?;
+            let poll = poll::PollModel {
+                app_id: user.app_id,
+                multiple: poll.multiple,
+                anonymous: poll.anonymous,
+                close_at,
+                ..Default::default()
+            };
+            Some((poll, options))
+        }
+        None => None,
+    };
+
     let mut topic = topic::TopicModel {
         app_id: user.app_id,
         user_id: user.id,
         category: payload.category,
         title: payload.title,
+        content_format: payload.content_format,
         content: payload.content,
+        topped: match payload.publish_at {
+            Some(_) => topic::STATUS_HIDDEN,
+            None => 0,
+        },
+        publish_at: payload.publish_at.map(sql_datetime),
         ..Default::default()
     };
 
     let topic_id = topic::create(&mut conn, &mut topic).await?;
+    tag::update_topic_tags(&mut conn, topic.app_id, topic_id, tag_names).await?;
+    if let Some((mut poll, options)) = poll {
+        poll.topic_id = topic_id;
+        poll::create(&mut conn, &mut poll, options).await?;
+    }
     user::update_topic_count(&mut conn, user.id, UpdateCountOp::INCR).await?;
 
     let topic = topic::get_by_id(&mut conn, topic_id).await?;
+    search::sync_topic(&topic);
+    // scheduled topics tell their mentions once published
+    if topic.is_actived() {
+        inbox::notify_topic(&mut conn, &topic).await;
+    }
 
     Ok(api_success(TopicCreateResponse { topic_id: topic.id }))
 }
 
+#[derive(Validate, Deserialize)]
+struct TopicUpdatePayload {
+    app_id: u64,
+    topic_id: u64,
+    #[validate(length(min = 1, max = 1024))]
+    title: String,
+    #[serde(default)]
+    #[validate(custom = "validate_content_format")]
+    content_format: i8,
+    #[validate(length(max = 16000))]
+    content: String,
+    tags: Option<Vec<String>>,
+}
+
+#[derive(Serialize)]
+struct TopicUpdateResponse {
+    topic: topic::TopicSimple,
+}
+
+async fn topic_update(
+    claims: MgrClaims,
+    Json(payload): Json<TopicUpdatePayload>,
+) -> Result<ApiSuccess<TopicUpdateResponse>, ApiError> {
+    match payload.validate() {
+        Err(e) => return Err(api_errore(ApiErrorCode::InvalidParams, &e)),
+        _ => {}
+    };
+
+    let mut conn = database_connect().await?;
+
+    let mut topic = topic::get_by_id(&mut conn, payload.topic_id).await?;
+    if topic.app_id != payload.app_id {
+        return Err(api_error(ApiErrorCode::NoPermission));
+    }
+    if topic.is_deleted() {
+        return Err(api_error(ApiErrorCode::TopicNotFound));
+    }
+    if topic.moved_to > 0 {
+        return Err(api_error(ApiErrorCode::TopicMoved));
+    }
+
+    let mut revision = topic_revision::TopicRevisionModel::from_topic(
+        &topic,
+        topic_revision::EDITOR_MANAGER,
+        claims.mgr_id,
+    );
+    let tag_names = tag::collect_names(payload.tags, &payload.content);
+
+    topic.title = payload.title;
+    topic.content_format = payload.content_format;
+    topic.content = payload.content;
+
+    let mut tx = database_begin(&mut conn).await?;
+    topic_revision::create(&mut tx, &mut revision).await?;
+    topic::update_content(&mut tx, &mut topic).await?;
+    database_commit(tx).await?;
+
+    tag::update_topic_tags(&mut conn, topic.app_id, topic.id, tag_names).await?;
+
+    let topic = topic::get_by_id(&mut conn, topic.id).await?;
+    search::sync_topic(&topic);
+
+    Ok(api_success(TopicUpdateResponse {
+        topic: topic.to_simple(),
+    }))
+}
+
 #[derive(Deserialize)]
 struct TopicDetailPayload {
     app_id: u64,
@@ -74,6 +208,9 @@ struct TopicDetailPayload {
 struct TopicDetailResponse {
     user: user::UserSimple,
     topic: topic::TopicSimple,
+    tags: Vec<tag::TagSimple>,
+    attachments: Vec<attachment::AttachmentSimple>,
+    poll: Option<poll::PollSimple>,
 }
 
 async fn topic_detail(
@@ -91,10 +228,18 @@ async fn topic_detail(
     }
 
     let user = user::get_by_id(&mut conn, topic.user_id).await?;
+    let tags = tag::get_simple_list_by_topic(&mut conn, topic.id).await?;
+    let attachments =
+        attachment::get_simple_list_by_target(&mut conn, attachment::TARGET_TOPIC, topic.id)
+            .await?;
+    let poll = poll::get_simple_by_topic(&mut conn, topic.id).await?;
 
     Ok(api_success(TopicDetailResponse {
         user: user.to_simple(),
         topic: topic.to_simple(),
+        tags,
+        attachments,
+        poll,
     }))
 }
 
@@ -102,8 +247,12 @@ async fn topic_detail(
 struct TopicListPayload {
     app_id: u64,
     category: u64,
+    tag: Option<String>,
+    solved: Option<bool>,
     style: topic::VisibleStyle,
     order_by: topic::VisibleOrderBy,
+    #[serde(default)]
+    window: topic::VisibleTopWindow,
     cursor: u32,
     #[validate(custom = "validate_page_count")]
     count: u32,
@@ -134,12 +283,24 @@ async fn topic_list(
 
     app::get_by_id(&mut conn, payload.app_id).await?;
 
+    let tag_id = match &payload.tag {
+        Some(name) => {
+            tag::get_actived_by_name(&mut conn, payload.app_id, name)
+                .await?
+                .id
+        }
+        None => 0,
+    };
+
     let (total, topics) = topic::fetch_pagging(
         &mut conn,
         payload.app_id,
         payload.category,
+        tag_id,
+        payload.solved,
         payload.style,
         payload.order_by,
+        payload.window,
         payload.cursor,
         payload.count,
     )
@@ -152,18 +313,20 @@ async fn topic_list(
         .iter()
         .map(|s| TopicListItem {
             user: user_map.get(s.user_id),
-            topic: s.to_simple(),
+            topic: s.to_preview(),
         })
         .collect();
 
     Ok(api_success(TopicListResponse { total, topics }))
 }
 
-#[derive(Deserialize)]
+#[derive(Validate, Deserialize)]
 struct TopicUpdateStatusPayload {
     app_id: u64,
     topic_id: u64,
     action: topic::StatusAction,
+    #[validate(custom = "validate_future_time")]
+    topped_until: Option<i64>,
 }
 
 #[derive(Serialize)]
@@ -175,6 +338,11 @@ async fn topic_update_status(
     _claims: MgrClaims,
     Json(payload): Json<TopicUpdateStatusPayload>,
 ) -> Result<ApiSuccess<TopicUpdateStatusResponse>, ApiError> {
+    match payload.validate() {
+        Err(e) => return Err(api_errore(ApiErrorCode::InvalidParams, &e)),
+        _ => {}
+    };
+
     let mut conn = database_connect().await?;
 
     let topic = topic::get_by_id(&mut conn, payload.topic_id).await?;
@@ -185,12 +353,342 @@ async fn topic_update_status(
         return Err(api_error(ApiErrorCode::TopicNotFound));
     }
 
-    topic::update_status(&mut conn, topic.id, payload.action.clone()).await?;
+    topic::update_status(
+        &mut conn,
+        topic.id,
+        payload.action.clone(),
+        topic::StatusOperator::MANAGER,
+    )
+    .await?;
+    if payload.action == topic::StatusAction::MOVEUP && payload.topped_until.is_some() {
+        topic::update_topped_until(&mut conn, topic.id, payload.topped_until.map(sql_datetime))
+            .await?;
+    }
     if payload.action == topic::StatusAction::DELETE {
-        user::update_topic_count(&mut conn, topic.user_id, UpdateCountOp::DECR).await?;
+        // placeholders of moved topics were never counted
+        if topic.moved_to == 0 {
+            user::update_topic_count(&mut conn, topic.user_id, UpdateCountOp::DECR).await?;
+        }
+        tag::unlink_topic(&mut conn, topic.id).await?;
+        attachment::purge_target(&mut conn, attachment::TARGET_TOPIC, topic.id).await?;
+    }
+
+    let was_actived = topic.is_actived();
+    let topic = topic::get_by_id(&mut conn, topic.id).await?;
+    if topic.topped != 0 {
+        ranking::remove_topic(topic.app_id, topic.id, topic.category).await?;
+    }
+    if topic.is_actived() != was_actived {
+        search::sync_topic_tree(&mut conn, &topic).await;
+    } else {
+        search::sync_topic(&topic);
     }
 
     Ok(api_success(TopicUpdateStatusResponse {
         topic_id: topic.id,
     }))
 }
+
+#[derive(Deserialize)]
+struct TopicLockPayload {
+    app_id: u64,
+    topic_id: u64,
+}
+
+#[derive(Serialize)]
+struct TopicLockResponse {
+    topic_id: u64,
+    locked: bool,
+}
+
+/// Managers lock and unlock any topic but the placeholders of moved ones.
+async fn update_lock(
+    payload: TopicLockPayload,
+    locked: bool,
+) -> Result<ApiSuccess<TopicLockResponse>, ApiError> {
+    let mut conn = database_connect().await?;
+
+    let topic = topic::get_by_id(&mut conn, payload.topic_id).await?;
+    if topic.app_id != payload.app_id {
+        return Err(api_error(ApiErrorCode::NoPermission));
+    }
+    if topic.is_deleted() {
+        return Err(api_error(ApiErrorCode::TopicNotFound));
+    }
+    if topic.moved_to > 0 {
+        return Err(api_error(ApiErrorCode::TopicMoved));
+    }
+
+    if locked {
+        topic::lock(&mut conn, topic.id, topic::LOCKED_BY_MANAGER).await?;
+    } else {
+        topic::unlock(&mut conn, topic.id, None).await?;
+    }
+
+    Ok(api_success(TopicLockResponse {
+        topic_id: topic.id,
+        locked,
+    }))
+}
+
+async fn topic_lock(
+    _claims: MgrClaims,
+    Json(payload): Json<TopicLockPayload>,
+) -> Result<ApiSuccess<TopicLockResponse>, ApiError> {
+    update_lock(payload, true).await
+}
+
+async fn topic_unlock(
+    _claims: MgrClaims,
+    Json(payload): Json<TopicLockPayload>,
+) -> Result<ApiSuccess<TopicLockResponse>, ApiError> {
+    update_lock(payload, false).await
+}
+
+#[derive(Deserialize)]
+struct TopicMovePayload {
+    app_id: u64,
+    topic_id: u64,
+    category: u64,
+    #[serde(default)]
+    placeholder: bool,
+}
+
+#[derive(Serialize)]
+struct TopicMoveResponse {
+    topic_id: u64,
+    placeholder_id: u64,
+}
+
+async fn topic_move(
+    _claims: MgrClaims,
+    Json(payload): Json<TopicMovePayload>,
+) -> Result<ApiSuccess<TopicMoveResponse>, ApiError> {
+    let mut conn = database_connect().await?;
+
+    let topic = topic::get_by_id(&mut conn, payload.topic_id).await?;
+    if topic.app_id != payload.app_id {
+        return Err(api_error(ApiErrorCode::NoPermission));
+    }
+    if topic.is_deleted() {
+        return Err(api_error(ApiErrorCode::TopicNotFound));
+    }
+    if payload.category == 0 || topic.moved_to > 0 || topic.category == payload.category {
+        return Err(api_error(ApiErrorCode::InvalidParams));
+    }
+
+    let from = topic.category;
+    let mut tx = database_begin(&mut conn).await?;
+
+    topic::update_category(&mut tx, topic.id, payload.category).await?;
+
+    let mut placeholder_id = 0;
+    if payload.placeholder {
+        placeholder_id = topic::create_placeholder(&mut tx, &topic, from).await?;
+    }
+
+    database_commit(tx).await?;
+
+    let topic = topic::get_by_id(&mut conn, topic.id).await?;
+    search::sync_topic_tree(&mut conn, &topic).await;
+    inbox::notify_moved(&mut conn, &topic).await;
+    ranking::move_topic(topic.app_id, topic.id, from, topic.category).await?;
+
+    Ok(api_success(TopicMoveResponse {
+        topic_id: topic.id,
+        placeholder_id,
+    }))
+}
+
+#[derive(Deserialize)]
+struct TopicAcceptPayload {
+    app_id: u64,
+    topic_id: u64,
+    reply_id: u64,
+}
+
+#[derive(Serialize)]
+struct TopicAcceptResponse {
+    topic_id: u64,
+    accepted_reply_id: u64,
+}
+
+/// Marks a reply as the accepted answer, reply_id 0 clears it.
+async fn topic_accept(
+    _claims: MgrClaims,
+    Json(payload): Json<TopicAcceptPayload>,
+) -> Result<ApiSuccess<TopicAcceptResponse>, ApiError> {
+    let mut conn = database_connect().await?;
+
+    let topic = topic::get_by_id(&mut conn, payload.topic_id).await?;
+    if topic.app_id != payload.app_id {
+        return Err(api_error(ApiErrorCode::NoPermission));
+    }
+    if topic.is_deleted() {
+        return Err(api_error(ApiErrorCode::TopicNotFound));
+    }
+    if topic.moved_to > 0 {
+        return Err(api_error(ApiErrorCode::TopicMoved));
+    }
+
+    if payload.reply_id > 0 {
+        let app = app::get_by_id(&mut conn, topic.app_id).await?;
+        if !app.is_qa_category(topic.category) {
+            return Err(api_error(ApiErrorCode::TopicNotQa));
+        }
+
+        let reply = reply::get_by_id(&mut conn, payload.reply_id).await?;
+        if reply.topic_id != topic.id || !reply.is_actived() {
+            return Err(api_error(ApiErrorCode::ReplyNotFound));
+        }
+    }
+
+    topic::update_accepted_reply(&mut conn, topic.id, payload.reply_id).await?;
+
+    Ok(api_success(TopicAcceptResponse {
+        topic_id: topic.id,
+        accepted_reply_id: payload.reply_id,
+    }))
+}
+
+#[derive(Validate, Deserialize)]
+struct TopicRevisionListPayload {
+    app_id: u64,
+    topic_id: u64,
+    cursor: u32,
+    #[validate(custom = "validate_page_count")]
+    count: u32,
+}
+
+#[derive(Serialize)]
+struct TopicRevisionListResponse {
+    total: u32,
+    revisions: Vec<topic_revision::TopicRevisionSimple>,
+}
+
+async fn topic_revision_list(
+    _claims: MgrClaims,
+    Query(payload): Query<TopicRevisionListPayload>,
+) -> Result<ApiSuccess<TopicRevisionListResponse>, ApiError> {
+    match payload.validate() {
+        Err(e) => return Err(api_errore(ApiErrorCode::InvalidParams, &e)),
+        _ => {}
+    };
+
+    let mut conn = database_connect().await?;
+
+    let topic = topic::get_by_id(&mut conn, payload.topic_id).await?;
+    if topic.app_id != payload.app_id {
+        return Err(api_error(ApiErrorCode::NoPermission));
+    }
+
+    let (total, revisions) =
+        topic_revision::fetch_pagging(&mut conn, topic.id, payload.cursor, payload.count).await?;
+
+    let revisions = revisions.iter().map(|s| s.to_simple()).collect();
+
+    Ok(api_success(TopicRevisionListResponse { total, revisions }))
+}
+
+#[derive(Deserialize)]
+struct TopicRevisionDiffPayload {
+    app_id: u64,
+    topic_id: u64,
+    from_id: u64,
+    to_id: u64,
+}
+
+#[derive(Serialize)]
+struct TopicRevisionDiffResponse {
+    title: Vec<DiffLine>,
+    content: Vec<DiffLine>,
+}
+
+async fn topic_revision_diff(
+    _claims: MgrClaims,
+    Query(payload): Query<TopicRevisionDiffPayload>,
+) -> Result<ApiSuccess<TopicRevisionDiffResponse>, ApiError> {
+    let mut conn = database_connect().await?;
+
+    let topic = topic::get_by_id(&mut conn, payload.topic_id).await?;
+    if topic.app_id != payload.app_id {
+        return Err(api_error(ApiErrorCode::NoPermission));
+    }
+
+    let from = topic_revision::get_by_id(&mut conn, payload.from_id).await?;
+    if from.topic_id != topic.id {
+        return Err(api_error(ApiErrorCode::RevisionNotFound));
+    }
+
+    // to_id 0 compares against the current topic
+    let (title, content) = if payload.to_id == 0 {
+        (topic.title, topic.content)
+    } else {
+        let to = topic_revision::get_by_id(&mut conn, payload.to_id).await?;
+        if to.topic_id != topic.id {
+            return Err(api_error(ApiErrorCode::RevisionNotFound));
+        }
+        (to.title, to.content)
+    };
+
+    Ok(api_success(TopicRevisionDiffResponse {
+        title: diff_lines(&from.title, &title),
+        content: diff_lines(&from.content, &content),
+    }))
+}
+
+#[derive(Validate, Deserialize)]
+struct TopicLikersPayload {
+    app_id: u64,
+    topic_id: u64,
+    reaction: Option<String>,
+    cursor: u32,
+    #[validate(custom = "validate_page_count")]
+    count: u32,
+}
+
+#[derive(Serialize)]
+struct TopicLikersResponse {
+    total: u32,
+    users: Vec<reaction::ReactorItem>,
+}
+
+/// Users who liked the topic, or reacted with `reaction`, newest first.
+async fn topic_likers(
+    _claims: MgrClaims,
+    Query(payload): Query<TopicLikersPayload>,
+) -> Result<ApiSuccess<TopicLikersResponse>, ApiError> {
+    match payload.validate() {
+        Err(e) => return Err(api_errore(ApiErrorCode::InvalidParams, &e)),
+        _ => {}
+    };
+
+    let name = payload
+        .reaction
+        .unwrap_or(String::from(reaction::DEFAULT_REACTION));
+    if !reaction::is_valid_name(&name) {
+        return Err(api_error(ApiErrorCode::InvalidParams));
+    }
+
+    let mut conn = database_connect().await?;
+
+    let topic = topic::get_by_id(&mut conn, payload.topic_id).await?;
+    if payload.app_id != topic.app_id {
+        return Err(api_error(ApiErrorCode::NoPermission));
+    }
+    if topic.is_deleted() {
+        return Err(api_error(ApiErrorCode::TopicNotFound));
+    }
+
+    let (total, users) = reaction::fetch_reactors(
+        &mut conn,
+        reaction::TARGET_TOPIC,
+        topic.id,
+        &name,
+        payload.cursor,
+        payload.count,
+    )
+    .await?;
+
+    Ok(api_success(TopicLikersResponse { total, users }))
+}
@@ -0,0 +1,116 @@
+use axum::{
+    extract::{DefaultBodyLimit, Multipart},
+    routing::{get, post},
+    Router,
+};
+
+use super::base::*;
+use crate::shared::data::*;
+use crate::shared::model::*;
+use crate::shared::storage::*;
+use crate::shared::web::*;
+
+async fn root() -> &'static str {
+    "DoggTalk SDK Attachment API"
+}
+
+pub fn setup_routers() -> Router {
+    Router::new().route("/", get(root)).route(
+        "/upload",
+        post(attachment_upload).layer(DefaultBodyLimit::max(attachment::MAX_UPLOAD_SIZE)),
+    )
+}
+
+#[derive(Serialize)]
+struct AttachmentUploadResponse {
+    attachment: attachment::AttachmentSimple,
+}
+
+async fn attachment_upload(
+    claims: UserClaims,
+    mut multipart: Multipart,
+) -> Result<ApiSuccess<AttachmentUploadResponse>, ApiError> {
+    let mut app_id = 0u64;
+    let mut file: Option<(String, Vec<u8>)> = None;
+
+    while let Some(field) = multipart
+        .next_field()
+        .await
+        .map_err(|e| api_errore(ApiErrorCode::InvalidParams, &e))?
+    {
+        match field.name().unwrap_or_default() {
+            "app_id" => {
+                let text = field
+                    .text()
+                    .await
+                    .map_err(|e| api_errore(ApiErrorCode::InvalidParams, &e))?;
+                app_id = text
+                    .trim()
+                    .parse()
+                    .map_err(|e| api_errore(ApiErrorCode::InvalidParams, &e))?;
+            }
+            "file" => {
+                let file_name = field.file_name().unwrap_or_default().to_string();
+                let data = field
+                    .bytes()
+                    .await
+                    .map_err(|e| api_errore(ApiErrorCode::InvalidParams, &e))?;
+                file = Some((file_name, data.to_vec()));
+            }
+            _ => {}
+        }
+    }
+
+    if app_id != claims.app_id {
+        return Err(api_error(ApiErrorCode::NoPermission));
+    }
+    let (file_name, data) = match file {
+        Some(file) if !file.1.is_empty() => file,
+        _ => return Err(api_error2(ApiErrorCode::InvalidParams, "file is required")),
+    };
+
+    let mut conn = database_connect().await?;
+
+    let app = app::get_by_id(&mut conn, app_id).await?;
+    if data.len() > app.upload_max_size as usize {
+        return Err(api_error(ApiErrorCode::AttachmentTooLarge));
+    }
+
+    // type and extension come from the bytes only, the file name and the
+    // content type of the part are up to the client
+    let (mime_type, extension) = match infer::get(&data) {
+        Some(kind) => (kind.mime_type().to_string(), kind.extension().to_string()),
+        None => return Err(api_error(ApiErrorCode::AttachmentTypeNotAllowed)),
+    };
+    if !app.is_upload_allowed(&mime_type) {
+        return Err(api_error(ApiErrorCode::AttachmentTypeNotAllowed));
+    }
+
+    let user = user::get_by_id(&mut conn, claims.user_id).await?;
+    if app_id != user.app_id {
+        return Err(api_error(ApiErrorCode::NoPermission));
+    }
+    if !user.is_actived() {
+        return Err(api_error(ApiErrorCode::AccountNotActived));
+    }
+
+    let mut attachment = attachment::AttachmentModel {
+        app_id: app.id,
+        user_id: user.id,
+        storage_key: build_key(app.id, &extension),
+        file_name: file_name.chars().take(256).collect(),
+        mime_type,
+        size: data.len() as u32,
+        ..Default::default()
+    };
+
+    storage()
+        .put(&attachment.storage_key, data, &attachment.mime_type)
+        .await?;
+
+    attachment.id = attachment::create(&mut conn, &mut attachment).await?;
+
+    Ok(api_success(AttachmentUploadResponse {
+        attachment: attachment.to_simple(),
+    }))
+}
@@ -1,11 +1,13 @@
 use axum::{
     async_trait,
-    extract::{FromRequestParts, TypedHeader},
+    extract::{ConnectInfo, FromRequestParts, TypedHeader},
     headers::{authorization::Bearer, Authorization},
     http::request::Parts,
     RequestPartsExt,
 };
+use std::net::{IpAddr, SocketAddr};
 
+use crate::shared::base::*;
 use crate::shared::jwt::*;
 use crate::shared::web::*;
 
@@ -61,3 +63,94 @@ pub fn build_user_token(claims: UserClaims) -> Result<String, ApiError> {
     out.push_str(&claims.user_id.to_string());
     return jwt_build(SDK_TC, out);
 }
+
+/// Proxies allowed to report the client address, from `TRUSTED_PROXIES` as
+/// comma separated addresses or CIDR ranges.
+static TRUSTED_PROXIES: Lazy<Vec<(IpAddr, u8)>> = Lazy::new(|| {
+    std::env::var("TRUSTED_PROXIES")
+        .unwrap_or_default()
+        .split(',')
+        .map(|s| s.trim())
+        .filter(|s| !s.is_empty())
+        .map(|s| {
+            let (ip, prefix) = s.split_once('/').unwrap_or((s, ""));
+            let ip: IpAddr = ip.parse().expect("TRUSTED_PROXIES must be IPs or CIDRs");
+            let max = if ip.is_ipv4() { 32 } else { 128 };
+            let prefix = match prefix {
+                "" => max,
+                _ => prefix.parse().expect("TRUSTED_PROXIES must be IPs or CIDRs"),
+            };
+            (ip, prefix.min(max))
+        })
+        .collect()
+});
+
+fn ip_bits(ip: IpAddr) -> (u128, u8) {
+    match ip {
+        IpAddr::V4(s) => (u32::from(s) as u128, 32),
+        IpAddr::V6(s) => match s.to_ipv4_mapped() {
+            Some(s) => (u32::from(s) as u128, 32),
+            None => (u128::from(s), 128),
+        },
+    }
+}
+
+fn is_trusted_proxy(ip: IpAddr) -> bool {
+    let (bits, len) = ip_bits(ip);
+    TRUSTED_PROXIES.iter().any(|(net, prefix)| {
+        let (net_bits, net_len) = ip_bits(*net);
+        if net_len != len {
+            return false;
+        }
+        let shift = (len - prefix.min(&len)) as u32;
+        bits.checked_shr(shift).unwrap_or(0) == net_bits.checked_shr(shift).unwrap_or(0)
+    })
+}
+
+/// Address of the player. The proxy headers are only believed when the peer
+/// is a trusted proxy, `X-Forwarded-For` is read from the right skipping the
+/// trusted proxies appended to it.
+pub struct ClientIp(pub String);
+
+#[async_trait]
+impl<S> FromRequestParts<S> for ClientIp
+where
+    S: Send + Sync,
+{
+    type Rejection = ApiError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let peer = parts
+            .extensions
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|s| s.0.ip());
+        let peer = match peer {
+            Some(peer) => peer,
+            None => return Ok(Self(String::new())),
+        };
+        if !is_trusted_proxy(peer) {
+            return Ok(Self(peer.to_string()));
+        }
+
+        let header = |name| {
+            parts
+                .headers
+                .get(name)
+                .and_then(|s| s.to_str().ok())
+                .unwrap_or_default()
+        };
+
+        let forwarded = header("x-forwarded-for")
+            .rsplit(',')
+            .filter_map(|s| s.trim().parse::<IpAddr>().ok())
+            .find(|s| !is_trusted_proxy(*s));
+        if let Some(ip) = forwarded {
+            return Ok(Self(ip.to_string()));
+        }
+
+        match header("x-real-ip").trim().parse::<IpAddr>() {
+            Ok(ip) => Ok(Self(ip.to_string())),
+            Err(_) => Ok(Self(peer.to_string())),
+        }
+    }
+}
@@ -0,0 +1,342 @@
+use axum::{
+    routing::{get, post},
+    Router,
+};
+
+use super::base::*;
+use super::reply::{create_reply, ReplyCreatePayload};
+use super::topic::{create_topic, TopicCreatePayload};
+use crate::shared::data::*;
+use crate::shared::model::*;
+use crate::shared::web::*;
+
+async fn root() -> &'static str {
+    "DoggTalk SDK Draft API"
+}
+
+pub fn setup_routers() -> Router {
+    Router::new()
+        .route("/", get(root))
+        .route("/create", post(draft_create))
+        .route("/update", post(draft_update))
+        .route("/delete", post(draft_delete))
+        .route("/publish", post(draft_publish))
+        .route("/list", get(draft_list))
+}
+
+fn join_tags(tags: Option<Vec<String>>) -> String {
+    tags.unwrap_or_default()
+        .iter()
+        .filter_map(|s| tag::normalize_name(s))
+        .take(tag::MAX_TOPIC_TAGS)
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+async fn get_own_draft(
+    conn: &mut SqlConnection,
+    claims: &UserClaims,
+    app_id: u64,
+    draft_id: u64,
+) -> Result<draft::DraftModel, ApiError> {
+    if app_id != claims.app_id {
+        return Err(api_error(ApiErrorCode::NoPermission));
+    }
+
+    let draft = draft::get_by_id(conn, draft_id).await?;
+    if draft.app_id != app_id || draft.user_id != claims.user_id {
+        return Err(api_error(ApiErrorCode::NoPermission));
+    }
+
+    Ok(draft)
+}
+
+#[derive(Validate, Deserialize)]
+struct DraftCreatePayload {
+    app_id: u64,
+    target_type: i8,
+    #[serde(default)]
+    topic_id: u64,
+    #[serde(default)]
+    parent_reply_id: u64,
+    #[serde(default)]
+    quote_reply_id: u64,
+    #[serde(default)]
+    category: u64,
+    #[serde(default)]
+    #[validate(length(max = 1024))]
+    title: String,
+    #[serde(default)]
+    #[validate(custom = "validate_content_format")]
+    content_format: i8,
+    #[validate(length(max = 16000))]
+    content: String,
+    tags: Option<Vec<String>>,
+}
+
+#[derive(Serialize)]
+struct DraftCreateResponse {
+    draft_id: u64,
+}
+
+async fn draft_create(
+    claims: UserClaims,
+    Json(payload): Json<DraftCreatePayload>,
+) -> Result<ApiSuccess<DraftCreateResponse>, ApiError> {
+    match payload.validate() {
+        Err(e) => return Err(api_errore(ApiErrorCode::InvalidParams, &e)),
+        _ => {}
+    };
+
+    if payload.app_id != claims.app_id {
+        return Err(api_error(ApiErrorCode::NoPermission));
+    }
+
+    let mut conn = database_connect().await?;
+
+    let user = user::get_by_id(&mut conn, claims.user_id).await?;
+    if payload.app_id != user.app_id {
+        return Err(api_error(ApiErrorCode::NoPermission));
+    }
+    if !user.is_actived() {
+        return Err(api_error(ApiErrorCode::AccountNotActived));
+    }
+
+    match payload.target_type {
+        draft::TARGET_TOPIC => {
+            if payload.parent_reply_id > 0 || payload.quote_reply_id > 0 {
+                return Err(api_error2(
+                    ApiErrorCode::InvalidParams,
+                    "topic drafts take no reply ids",
+                ));
+            }
+        }
+        draft::TARGET_REPLY => {
+            let topic = topic::get_by_id(&mut conn, payload.topic_id).await?;
+            if topic.app_id != payload.app_id {
+                return Err(api_error(ApiErrorCode::NoPermission));
+            }
+            // checked again on publish, the replies may be gone by then
+            for reply_id in [payload.parent_reply_id, payload.quote_reply_id] {
+                if reply_id == 0 {
+                    continue;
+                }
+                let reply = reply::get_by_id(&mut conn, reply_id).await?;
+                if reply.topic_id != topic.id {
+                    return Err(api_error(ApiErrorCode::ReplyNotFound));
+                }
+            }
+        }
+        _ => {
+            return Err(api_error2(
+                ApiErrorCode::InvalidParams,
+                "target_type must be 1 or 2",
+            ))
+        }
+    }
+
+    if draft::count_by_user(&mut conn, user.id).await? >= draft::MAX_USER_DRAFTS {
+        return Err(api_error2(ApiErrorCode::InvalidParams, "too many drafts"));
+    }
+
+    let draft = draft::DraftModel {
+        app_id: user.app_id,
+        user_id: user.id,
+        target_type: payload.target_type,
+        topic_id: payload.topic_id,
+        parent_reply_id: payload.parent_reply_id,
+        quote_reply_id: payload.quote_reply_id,
+        category: payload.category,
+        title: payload.title,
+        content_format: payload.content_format,
+        content: payload.content,
+        tags: join_tags(payload.tags),
+        ..Default::default()
+    };
+
+    let draft_id = draft::create(&mut conn, &draft).await?;
+
+    Ok(api_success(DraftCreateResponse { draft_id }))
+}
+
+#[derive(Validate, Deserialize)]
+struct DraftUpdatePayload {
+    app_id: u64,
+    draft_id: u64,
+    #[serde(default)]
+    category: u64,
+    #[serde(default)]
+    #[validate(length(max = 1024))]
+    title: String,
+    #[serde(default)]
+    #[validate(custom = "validate_content_format")]
+    content_format: i8,
+    #[validate(length(max = 16000))]
+    content: String,
+    tags: Option<Vec<String>>,
+}
+
+#[derive(Serialize)]
+struct DraftDetailResponse {
+    draft: draft::DraftSimple,
+}
+
+async fn draft_update(
+    claims: UserClaims,
+    Json(payload): Json<DraftUpdatePayload>,
+) -> Result<ApiSuccess<DraftDetailResponse>, ApiError> {
+    match payload.validate() {
+        Err(e) => return Err(api_errore(ApiErrorCode::InvalidParams, &e)),
+        _ => {}
+    };
+
+    let mut conn = database_connect().await?;
+
+    let mut draft = get_own_draft(&mut conn, &claims, payload.app_id, payload.draft_id).await?;
+
+    draft.category = payload.category;
+    draft.title = payload.title;
+    draft.content_format = payload.content_format;
+    draft.content = payload.content;
+    draft.tags = join_tags(payload.tags);
+    draft::update(&mut conn, &draft).await?;
+
+    let draft = draft::get_by_id(&mut conn, draft.id).await?;
+
+    Ok(api_success(DraftDetailResponse {
+        draft: draft.to_simple(),
+    }))
+}
+
+#[derive(Deserialize)]
+struct DraftDeletePayload {
+    app_id: u64,
+    draft_id: u64,
+}
+
+#[derive(Serialize)]
+struct DraftDeleteResponse {
+    draft_id: u64,
+}
+
+async fn draft_delete(
+    claims: UserClaims,
+    Json(payload): Json<DraftDeletePayload>,
+) -> Result<ApiSuccess<DraftDeleteResponse>, ApiError> {
+    let mut conn = database_connect().await?;
+
+    let draft = get_own_draft(&mut conn, &claims, payload.app_id, payload.draft_id).await?;
+    draft::delete(&mut conn, draft.id).await?;
+
+    Ok(api_success(DraftDeleteResponse { draft_id: draft.id }))
+}
+
+#[derive(Deserialize)]
+struct DraftPublishPayload {
+    app_id: u64,
+    draft_id: u64,
+}
+
+#[derive(Serialize)]
+struct DraftPublishResponse {
+    topic_id: u64,
+    reply_id: u64,
+}
+
+/// Posts the draft through the same path as `topic_create` or `reply_create`,
+/// the draft is removed only when that succeeds.
+async fn draft_publish(
+    claims: UserClaims,
+    Json(payload): Json<DraftPublishPayload>,
+) -> Result<ApiSuccess<DraftPublishResponse>, ApiError> {
+    let mut conn = database_connect().await?;
+
+    let draft = get_own_draft(&mut conn, &claims, payload.app_id, payload.draft_id).await?;
+    // taken first, a second publish of the same draft finds nothing to post
+    if !draft::delete(&mut conn, draft.id).await? {
+        return Err(api_error(ApiErrorCode::DraftNotFound));
+    }
+    // the create takes a connection of its own
+    drop(conn);
+
+    let res = if draft.target_type == draft::TARGET_REPLY {
+        create_reply(
+            &claims,
+            ReplyCreatePayload {
+                app_id: draft.app_id,
+                topic_id: draft.topic_id,
+                parent_reply_id: draft.parent_reply_id,
+                quote_reply_id: draft.quote_reply_id,
+                content_format: draft.content_format,
+                content: draft.content.clone(),
+                attachment_ids: None,
+            },
+        )
+        .await
+        .map(|reply_id| (draft.topic_id, reply_id))
+    } else {
+        create_topic(
+            &claims,
+            TopicCreatePayload {
+                app_id: draft.app_id,
+                category: draft.category,
+                title: draft.title.clone(),
+                content_format: draft.content_format,
+                content: draft.content.clone(),
+                tags: Some(draft.tag_list()),
+                attachment_ids: None,
+                poll: None,
+            },
+        )
+        .await
+        .map(|topic_id| (topic_id, 0))
+    };
+
+    let (topic_id, reply_id) = match res {
+        Ok(res) => res,
+        Err(e) => {
+            let mut conn = database_connect().await?;
+            draft::restore(&mut conn, &draft).await?;
+            return Err(e);
+        }
+    };
+
+    Ok(api_success(DraftPublishResponse { topic_id, reply_id }))
+}
+
+#[derive(Validate, Deserialize)]
+struct DraftListPayload {
+    app_id: u64,
+    cursor: u32,
+    #[validate(custom = "validate_page_count")]
+    count: u32,
+}
+
+#[derive(Serialize)]
+struct DraftListResponse {
+    total: u32,
+    drafts: Vec<draft::DraftSimple>,
+}
+
+async fn draft_list(
+    claims: UserClaims,
+    Query(payload): Query<DraftListPayload>,
+) -> Result<ApiSuccess<DraftListResponse>, ApiError> {
+    match payload.validate() {
+        Err(e) => return Err(api_errore(ApiErrorCode::InvalidParams, &e)),
+        _ => {}
+    };
+
+    if payload.app_id != claims.app_id {
+        return Err(api_error(ApiErrorCode::NoPermission));
+    }
+
+    let mut conn = database_connect().await?;
+
+    let (total, drafts) =
+        draft::fetch_pagging(&mut conn, claims.user_id, payload.cursor, payload.count).await?;
+
+    let drafts = drafts.iter().map(|s| s.to_simple()).collect();
+
+    Ok(api_success(DraftListResponse { total, drafts }))
+}
@@ -1,8 +1,13 @@
 use axum::{routing::get, Router};
 
+mod attachment;
 mod base;
+mod draft;
+mod notification;
 mod reply;
+mod search;
 mod start;
+mod tag;
 mod topic;
 mod user;
 
@@ -17,4 +22,9 @@ pub fn setup_routers() -> Router {
         .nest("/user", user::setup_routers())
         .nest("/topic", topic::setup_routers())
         .nest("/reply", reply::setup_routers())
+        .nest("/search", search::setup_routers())
+        .nest("/tag", tag::setup_routers())
+        .nest("/attachment", attachment::setup_routers())
+        .nest("/draft", draft::setup_routers())
+        .nest("/notification", notification::setup_routers())
 }
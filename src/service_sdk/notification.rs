@@ -0,0 +1,172 @@
+use axum::{
+    routing::{get, post},
+    Router,
+};
+
+use super::base::*;
+use crate::shared::base::*;
+use crate::shared::data::*;
+use crate::shared::model::*;
+use crate::shared::web::*;
+
+async fn root() -> &'static str {
+    "DoggTalk SDK Notification API"
+}
+
+pub fn setup_routers() -> Router {
+    Router::new()
+        .route("/", get(root))
+        .route("/list", get(notification_list))
+        .route("/unread", get(notification_unread))
+        .route("/read", post(notification_read))
+        .route("/read/all", post(notification_read_all))
+}
+
+#[derive(Validate, Deserialize)]
+struct NotificationListPayload {
+    app_id: u64,
+    #[serde(default)]
+    unread: bool,
+    cursor: u32,
+    #[validate(custom = "validate_page_count")]
+    count: u32,
+}
+
+#[derive(Serialize)]
+struct NotificationListItem {
+    notification: notification::NotificationSimple,
+    actor: Option<Arc<user::UserSimple>>,
+}
+
+#[derive(Serialize)]
+struct NotificationListResponse {
+    total: u32,
+    unread: u32,
+    notifications: Vec<NotificationListItem>,
+}
+
+/// Latest activity first, `unread` keeps only the unread ones.
+async fn notification_list(
+    claims: UserClaims,
+    Query(payload): Query<NotificationListPayload>,
+) -> Result<ApiSuccess<NotificationListResponse>, ApiError> {
+    match payload.validate() {
+        Err(e) => return Err(api_errore(ApiErrorCode::InvalidParams, &e)),
+        _ => {}
+    };
+
+    if payload.app_id != claims.app_id {
+        return Err(api_error(ApiErrorCode::NoPermission));
+    }
+
+    let mut conn = database_connect().await?;
+
+    let (total, notifications) = notification::fetch_pagging(
+        &mut conn,
+        claims.user_id,
+        payload.unread,
+        payload.cursor,
+        payload.count,
+    )
+    .await?;
+    let unread = notification::count_unread(&mut conn, claims.user_id).await?;
+
+    let user_map = user::get_simple_map_by_ids(
+        &mut conn,
+        notifications
+            .iter()
+            .filter(|s| s.actor_id > 0)
+            .map(|s| s.actor_id)
+            .collect(),
+    )
+    .await?;
+
+    let notifications = notifications
+        .iter()
+        .map(|s| NotificationListItem {
+            notification: s.to_simple(),
+            actor: user_map.opt(s.actor_id),
+        })
+        .collect();
+
+    Ok(api_success(NotificationListResponse {
+        total,
+        unread,
+        notifications,
+    }))
+}
+
+#[derive(Deserialize)]
+struct NotificationUnreadPayload {
+    app_id: u64,
+}
+
+#[derive(Serialize)]
+struct NotificationUnreadResponse {
+    unread: u32,
+}
+
+async fn notification_unread(
+    claims: UserClaims,
+    Query(payload): Query<NotificationUnreadPayload>,
+) -> Result<ApiSuccess<NotificationUnreadResponse>, ApiError> {
+    if payload.app_id != claims.app_id {
+        return Err(api_error(ApiErrorCode::NoPermission));
+    }
+
+    let mut conn = database_connect().await?;
+
+    let unread = notification::count_unread(&mut conn, claims.user_id).await?;
+
+    Ok(api_success(NotificationUnreadResponse { unread }))
+}
+
+#[derive(Validate, Deserialize)]
+struct NotificationReadPayload {
+    app_id: u64,
+    #[validate(length(min = 1, max = 100))]
+    notification_ids: Vec<u64>,
+}
+
+#[derive(Serialize)]
+struct NotificationReadResponse {
+    affect: u64,
+    unread: u32,
+}
+
+async fn notification_read(
+    claims: UserClaims,
+    Json(payload): Json<NotificationReadPayload>,
+) -> Result<ApiSuccess<NotificationReadResponse>, ApiError> {
+    match payload.validate() {
+        Err(e) => return Err(api_errore(ApiErrorCode::InvalidParams, &e)),
+        _ => {}
+    };
+
+    if payload.app_id != claims.app_id {
+        return Err(api_error(ApiErrorCode::NoPermission));
+    }
+
+    let mut conn = database_connect().await?;
+
+    let affect =
+        notification::mark_read(&mut conn, claims.user_id, payload.notification_ids).await?;
+    let unread = notification::count_unread(&mut conn, claims.user_id).await?;
+
+    Ok(api_success(NotificationReadResponse { affect, unread }))
+}
+
+async fn notification_read_all(
+    claims: UserClaims,
+    Json(payload): Json<NotificationUnreadPayload>,
+) -> Result<ApiSuccess<NotificationReadResponse>, ApiError> {
+    if payload.app_id != claims.app_id {
+        return Err(api_error(ApiErrorCode::NoPermission));
+    }
+
+    let mut conn = database_connect().await?;
+
+    let affect = notification::mark_all_read(&mut conn, claims.user_id).await?;
+
+    Ok(api_success(NotificationReadResponse { affect, unread: 0 }))
+}
@@ -6,7 +6,10 @@ use axum::{
 use super::base::*;
 use crate::shared::base::*;
 use crate::shared::data::*;
+use crate::shared::inbox;
 use crate::shared::model::*;
+use crate::shared::reaction;
+use crate::shared::search;
 use crate::shared::web::*;
 
 async fn root() -> &'static str {
@@ -17,20 +20,35 @@ pub fn setup_routers() -> Router {
     Router::new()
         .route("/", get(root))
         .route("/create", post(reply_create))
+        .route("/update", post(reply_update))
+        .route("/delete", post(reply_delete))
         .route("/like", post(reply_like))
         .route("/unlike", post(reply_unlike))
+        .route("/downvote", post(reply_downvote))
+        .route("/undownvote", post(reply_undownvote))
+        .route("/react", post(reply_react))
+        .route("/unreact", post(reply_unreact))
         .route("/list", get(reply_list))
+        .route("/likers", get(reply_likers))
+        .route("/thread", get(reply_thread))
+        .route("/children", get(reply_children))
+        .route("/locate", get(reply_locate))
 }
 
-fn like_key(id: u64) -> String {
-    format!("replylike:{}", id)
-}
-
-#[derive(Deserialize)]
-struct ReplyCreatePayload {
-    app_id: u64,
-    topic_id: u64,
-    content: String,
+#[derive(Validate, Deserialize)]
+pub(super) struct ReplyCreatePayload {
+    pub(super) app_id: u64,
+    pub(super) topic_id: u64,
+    #[serde(default)]
+    pub(super) parent_reply_id: u64,
+    #[serde(default)]
+    pub(super) quote_reply_id: u64,
+    #[serde(default)]
+    #[validate(custom = "validate_content_format")]
+    pub(super) content_format: i8,
+    #[validate(length(min = 1, max = 16000))]
+    pub(super) content: String,
+    pub(super) attachment_ids: Option<Vec<u64>>,
 }
 
 #[derive(Serialize)]
@@ -42,6 +60,21 @@ async fn reply_create(
     claims: UserClaims,
     Json(payload): Json<ReplyCreatePayload>,
 ) -> Result<ApiSuccess<ReplyCreateResponse>, ApiError> {
+    let reply_id = create_reply(&claims, payload).await?;
+
+    Ok(api_success(ReplyCreateResponse { reply_id }))
+}
+
+/// Shared by `reply_create` and publishing a draft.
+pub(super) async fn create_reply(
+    claims: &UserClaims,
+    payload: ReplyCreatePayload,
+) -> Result<u64, ApiError> {
+    match payload.validate() {
+        Err(e) => return Err(api_errore(ApiErrorCode::InvalidParams, &e)),
+        _ => {}
+    };
+
     if payload.app_id != claims.app_id {
         return Err(api_error(ApiErrorCode::NoPermission));
     }
@@ -55,6 +88,14 @@ async fn reply_create(
     if !topic.is_actived() {
         return Err(api_error(ApiErrorCode::TopicNotFound));
     }
+    if topic.moved_to > 0 {
+        return Err(api_error(ApiErrorCode::TopicMoved));
+    }
+
+    let app = app::get_by_id(&mut conn, topic.app_id).await?;
+    if topic.locked || app.is_auto_locked(topic.refreshed_at) {
+        return Err(api_error(ApiErrorCode::TopicLocked));
+    }
 
     let user = user::get_by_id(&mut conn, claims.user_id).await?;
     if payload.app_id != user.app_id {
@@ -64,38 +105,80 @@ async fn reply_create(
         return Err(api_error(ApiErrorCode::AccountNotActived));
     }
 
+    let mut root_reply_id = 0;
+    if payload.parent_reply_id > 0 {
+        let parent = reply::get_by_id(&mut conn, payload.parent_reply_id).await?;
+        if parent.topic_id != topic.id || !parent.is_actived() {
+            return Err(api_error(ApiErrorCode::ReplyNotFound));
+        }
+        root_reply_id = parent.root_id();
+    }
+
+    if payload.quote_reply_id > 0 {
+        let quoted = reply::get_by_id(&mut conn, payload.quote_reply_id).await?;
+        if quoted.topic_id != topic.id || !quoted.is_actived() {
+            return Err(api_error(ApiErrorCode::ReplyNotFound));
+        }
+    }
+
     let mut reply = reply::ReplyModel {
         app_id: topic.app_id,
         topic_id: topic.id,
         user_id: user.id,
+        parent_reply_id: payload.parent_reply_id,
+        root_reply_id,
+        quote_reply_id: payload.quote_reply_id,
+        content_format: payload.content_format,
         content: payload.content,
         ..Default::default()
     };
 
     let reply_id = reply::create(&mut conn, &mut reply).await?;
+    attachment::bind_target(
+        &mut conn,
+        user.id,
+        attachment::TARGET_REPLY,
+        reply_id,
+        payload.attachment_ids,
+    )
+    .await?;
     topic::update_reply_count(&mut conn, topic.id, UpdateCountOp::INCR).await?;
+    if payload.parent_reply_id > 0 {
+        reply::update_child_count(&mut conn, payload.parent_reply_id, UpdateCountOp::INCR).await?;
+    }
 
     let reply = reply::get_by_id(&mut conn, reply_id).await?;
+    search::sync_reply(&reply, &topic);
+    inbox::notify_reply(&mut conn, &topic, &reply).await;
 
-    Ok(api_success(ReplyCreateResponse { reply_id: reply.id }))
+    Ok(reply.id)
 }
 
-#[derive(Deserialize)]
-struct ReplyLikePayload {
+#[derive(Validate, Deserialize)]
+struct ReplyUpdatePayload {
     app_id: u64,
     reply_id: u64,
+    #[serde(default)]
+    #[validate(custom = "validate_content_format")]
+    content_format: i8,
+    #[validate(length(min = 1, max = 16000))]
+    content: String,
 }
 
 #[derive(Serialize)]
-struct ReplyLikeResponse {
-    affect: u64,
-    like_count: u64,
+struct ReplyUpdateResponse {
+    reply: reply::ReplySimple,
 }
 
-async fn reply_like(
+async fn reply_update(
     claims: UserClaims,
-    Json(payload): Json<ReplyLikePayload>,
-) -> Result<ApiSuccess<ReplyLikeResponse>, ApiError> {
+    Json(payload): Json<ReplyUpdatePayload>,
+) -> Result<ApiSuccess<ReplyUpdateResponse>, ApiError> {
+    match payload.validate() {
+        Err(e) => return Err(api_errore(ApiErrorCode::InvalidParams, &e)),
+        _ => {}
+    };
+
     if payload.app_id != claims.app_id {
         return Err(api_error(ApiErrorCode::NoPermission));
     }
@@ -110,32 +193,131 @@ async fn reply_like(
         return Err(api_error(ApiErrorCode::AccountNotActived));
     }
 
-    let reply = reply::get_by_id(&mut conn, payload.reply_id).await?;
-    if reply.app_id != payload.app_id {
+    let mut reply = reply::get_by_id(&mut conn, payload.reply_id).await?;
+    if reply.app_id != payload.app_id || reply.user_id != user.id {
         return Err(api_error(ApiErrorCode::NoPermission));
     }
     if !reply.is_actived() {
         return Err(api_error(ApiErrorCode::ReplyNotFound));
     }
 
-    let mut connr = redis_connect().await?;
+    let topic = topic::get_by_id(&mut conn, reply.topic_id).await?;
+    if !topic.is_actived() {
+        return Err(api_error(ApiErrorCode::TopicNotFound));
+    }
+
+    let app = app::get_by_id(&mut conn, reply.app_id).await?;
+    if topic.locked || app.is_auto_locked(topic.refreshed_at) {
+        return Err(api_error(ApiErrorCode::TopicLocked));
+    }
+    if !app.is_editable(reply.created_at) {
+        return Err(api_error(ApiErrorCode::ReplyNotEditable));
+    }
+
+    let mut revision = reply_revision::ReplyRevisionModel::from_reply(
+        &reply,
+        reply_revision::EDITOR_USER,
+        user.id,
+    );
+    reply_revision::create(&mut conn, &mut revision).await?;
+
+    reply.content_format = payload.content_format;
+    reply.content = payload.content;
+    reply::update_content(&mut conn, &mut reply).await?;
 
-    let affect: u64 = redis::cmd("ZADD")
-        .arg(like_key(reply.id))
-        .arg("NX")
-        .arg(timestamp())
-        .arg(user.id)
-        .query_async(&mut *connr)
-        .await
-        .map_err(|e| api_errore(ApiErrorCode::InvalidDatabase, &e))?;
+    let reply = reply::get_by_id(&mut conn, reply.id).await?;
+    search::sync_reply(&reply, &topic);
+
+    Ok(api_success(ReplyUpdateResponse {
+        reply: reply.to_simple(),
+    }))
+}
 
-    if affect > 0 {
-        reply::update_like_count(&mut conn, reply.id, UpdateCountOp::INCR).await?;
+#[derive(Deserialize)]
+struct ReplyDeletePayload {
+    app_id: u64,
+    reply_id: u64,
+}
+
+#[derive(Serialize)]
+struct ReplyDeleteResponse {
+    reply_id: u64,
+}
+
+async fn reply_delete(
+    claims: UserClaims,
+    Json(payload): Json<ReplyDeletePayload>,
+) -> Result<ApiSuccess<ReplyDeleteResponse>, ApiError> {
+    if payload.app_id != claims.app_id {
+        return Err(api_error(ApiErrorCode::NoPermission));
     }
 
+    let mut conn = database_connect().await?;
+
+    let user = user::get_by_id(&mut conn, claims.user_id).await?;
+    if payload.app_id != user.app_id {
+        return Err(api_error(ApiErrorCode::NoPermission));
+    }
+    if !user.is_actived() {
+        return Err(api_error(ApiErrorCode::AccountNotActived));
+    }
+
+    let reply = reply::get_by_id(&mut conn, payload.reply_id).await?;
+    if reply.app_id != payload.app_id || reply.user_id != user.id {
+        return Err(api_error(ApiErrorCode::NoPermission));
+    }
+    if reply.is_deleted() {
+        return Err(api_error(ApiErrorCode::ReplyNotFound));
+    }
+
+    if reply::delete(&mut conn, reply.id, reply::StatusOperator::AUTHOR).await? {
+        topic::update_reply_count(&mut conn, reply.topic_id, UpdateCountOp::DECR).await?;
+        topic::clear_accepted_reply(&mut conn, reply.topic_id, reply.id).await?;
+        // hidden replies were taken off the count already
+        if reply.parent_reply_id > 0 && reply.is_actived() {
+            reply::update_child_count(&mut conn, reply.parent_reply_id, UpdateCountOp::DECR)
+                .await?;
+        }
+        attachment::purge_target(&mut conn, attachment::TARGET_REPLY, reply.id).await?;
+    }
+
+    let topic = topic::get_by_id(&mut conn, reply.topic_id).await?;
+    let reply = reply::get_by_id(&mut conn, reply.id).await?;
+    search::sync_reply(&reply, &topic);
+
+    Ok(api_success(ReplyDeleteResponse { reply_id: reply.id }))
+}
+
+#[derive(Deserialize)]
+struct ReplyLikePayload {
+    app_id: u64,
+    reply_id: u64,
+}
+
+#[derive(Serialize)]
+struct ReplyLikeResponse {
+    affect: u64,
+    like_count: u64,
+    score: i64,
+}
+
+async fn reply_like(
+    claims: UserClaims,
+    Json(payload): Json<ReplyLikePayload>,
+) -> Result<ApiSuccess<ReplyLikeResponse>, ApiError> {
+    let (affect, reply) = update_reaction(
+        &claims,
+        payload.app_id,
+        payload.reply_id,
+        reaction::DEFAULT_REACTION,
+        UpdateCountOp::INCR,
+    )
+    .await?;
+
     Ok(api_success(ReplyLikeResponse {
         affect,
-        like_count: reply.like_count + affect,
+        like_count: reply.like_count,
+        score: reply.score,
     }))
 }
 
@@ -143,55 +325,202 @@ async fn reply_unlike(
     claims: UserClaims,
     Json(payload): Json<ReplyLikePayload>,
 ) -> Result<ApiSuccess<ReplyLikeResponse>, ApiError> {
-    if payload.app_id != claims.app_id {
+    let (affect, reply) = update_reaction(
+        &claims,
+        payload.app_id,
+        payload.reply_id,
+        reaction::DEFAULT_REACTION,
+        UpdateCountOp::DECR,
+    )
+    .await?;
+
+    Ok(api_success(ReplyLikeResponse {
+        affect,
+        like_count: reply.like_count,
+        score: reply.score,
+    }))
+}
+
+async fn reply_downvote(
+    claims: UserClaims,
+    Json(payload): Json<ReplyLikePayload>,
+) -> Result<ApiSuccess<ReplyLikeResponse>, ApiError> {
+    let (affect, reply) = update_reaction(
+        &claims,
+        payload.app_id,
+        payload.reply_id,
+        reaction::DOWNVOTE_REACTION,
+        UpdateCountOp::INCR,
+    )
+    .await?;
+
+    Ok(api_success(ReplyLikeResponse {
+        affect,
+        like_count: reply.like_count,
+        score: reply.score,
+    }))
+}
+
+async fn reply_undownvote(
+    claims: UserClaims,
+    Json(payload): Json<ReplyLikePayload>,
+) -> Result<ApiSuccess<ReplyLikeResponse>, ApiError> {
+    let (affect, reply) = update_reaction(
+        &claims,
+        payload.app_id,
+        payload.reply_id,
+        reaction::DOWNVOTE_REACTION,
+        UpdateCountOp::DECR,
+    )
+    .await?;
+
+    Ok(api_success(ReplyLikeResponse {
+        affect,
+        like_count: reply.like_count,
+        score: reply.score,
+    }))
+}
+
+#[derive(Deserialize)]
+struct ReplyReactPayload {
+    app_id: u64,
+    reply_id: u64,
+    reaction: String,
+}
+
+#[derive(Serialize)]
+struct ReplyReactResponse {
+    affect: u64,
+    reaction_counts: HashMap<String, u64>,
+}
+
+async fn reply_react(
+    claims: UserClaims,
+    Json(payload): Json<ReplyReactPayload>,
+) -> Result<ApiSuccess<ReplyReactResponse>, ApiError> {
+    let (affect, reply) = update_reaction(
+        &claims,
+        payload.app_id,
+        payload.reply_id,
+        &payload.reaction,
+        UpdateCountOp::INCR,
+    )
+    .await?;
+
+    Ok(api_success(ReplyReactResponse {
+        affect,
+        reaction_counts: reply.reaction_map(),
+    }))
+}
+
+async fn reply_unreact(
+    claims: UserClaims,
+    Json(payload): Json<ReplyReactPayload>,
+) -> Result<ApiSuccess<ReplyReactResponse>, ApiError> {
+    let (affect, reply) = update_reaction(
+        &claims,
+        payload.app_id,
+        payload.reply_id,
+        &payload.reaction,
+        UpdateCountOp::DECR,
+    )
+    .await?;
+
+    Ok(api_success(ReplyReactResponse {
+        affect,
+        reaction_counts: reply.reaction_map(),
+    }))
+}
+
+/// Adds or removes a reaction of the user, returns whether anything changed
+/// along with the reply holding the updated counts.
+async fn update_reaction(
+    claims: &UserClaims,
+    app_id: u64,
+    reply_id: u64,
+    name: &str,
+    op: UpdateCountOp,
+) -> Result<(u64, reply::ReplyModel), ApiError> {
+    if app_id != claims.app_id {
         return Err(api_error(ApiErrorCode::NoPermission));
     }
 
     let mut conn = database_connect().await?;
 
     let user = user::get_by_id(&mut conn, claims.user_id).await?;
-    if payload.app_id != user.app_id {
+    if app_id != user.app_id {
         return Err(api_error(ApiErrorCode::NoPermission));
     }
     if !user.is_actived() {
         return Err(api_error(ApiErrorCode::AccountNotActived));
     }
 
-    let reply = reply::get_by_id(&mut conn, payload.reply_id).await?;
-    if reply.app_id != payload.app_id {
+    let reply = reply::get_by_id(&mut conn, reply_id).await?;
+    if reply.app_id != app_id {
         return Err(api_error(ApiErrorCode::NoPermission));
     }
     if !reply.is_actived() {
         return Err(api_error(ApiErrorCode::ReplyNotFound));
     }
 
-    let mut connr = redis_connect().await?;
+    // a reaction taken off the app, or downvotes turned off, can still be
+    // removed by those who left it
+    let app = app::get_by_id(&mut conn, app_id).await?;
+    if op == UpdateCountOp::INCR && !app.is_reaction_allowed(name) {
+        return Err(api_error(ApiErrorCode::ReactionNotAllowed));
+    }
 
-    let affect: u64 = redis::cmd("ZREM")
-        .arg(like_key(reply.id))
-        .arg(user.id)
-        .query_async(&mut *connr)
-        .await
-        .map_err(|e| api_errore(ApiErrorCode::InvalidDatabase, &e))?;
+    let changed = match op {
+        UpdateCountOp::INCR => {
+            reaction::add(
+                &mut conn,
+                reply.app_id,
+                reaction::TARGET_REPLY,
+                reply.id,
+                user.id,
+                name,
+            )
+            .await?
+        }
+        _ => reaction::remove(&mut conn, reaction::TARGET_REPLY, reply.id, user.id, name).await?,
+    };
+    if !changed {
+        return Ok((0, reply));
+    }
+
+    let added = op == UpdateCountOp::INCR;
+    reply::update_reaction_count(&mut conn, reply.id, name, op).await?;
 
-    if affect > 0 {
-        reply::update_like_count(&mut conn, reply.id, UpdateCountOp::DECR).await?;
+    // likes and downvotes exclude each other
+    if let Some(other) = reaction::opposite(name).filter(|_| added) {
+        if reaction::remove(&mut conn, reaction::TARGET_REPLY, reply.id, user.id, other).await? {
+            reply::update_reaction_count(&mut conn, reply.id, other, UpdateCountOp::DECR).await?;
+        }
+    }
+    if added && name == reaction::DEFAULT_REACTION {
+        let topic = topic::get_by_id(&mut conn, reply.topic_id).await?;
+        inbox::notify_like(&mut conn, &topic, Some(&reply), user.id).await;
     }
 
-    Ok(api_success(ReplyLikeResponse {
-        affect,
-        like_count: reply.like_count + affect,
-    }))
+    let reply = reply::get_by_id(&mut conn, reply.id).await?;
+
+    Ok((1, reply))
 }
 
 #[derive(Serialize)]
 struct MySelfData {
     is_liked: bool,
+    is_downvoted: bool,
+    reactions: Vec<String>,
 }
 
 impl Default for MySelfData {
     fn default() -> Self {
-        Self { is_liked: false }
+        Self {
+            is_liked: false,
+            is_downvoted: false,
+            reactions: Vec::new(),
+        }
     }
 }
 
@@ -199,32 +528,31 @@ async fn fetch_myself<C>(
     conn: &mut C,
     user_id: u64,
     reply_ids: Vec<u64>,
+    reactions: &Vec<String>,
 ) -> Result<ArcDataMap<u64, MySelfData>, ApiError>
 where
     C: RedisConnectionLike,
 {
-    let mut pipe = redis::pipe();
-    for id in reply_ids.iter() {
-        pipe.cmd("ZSCORE").arg(like_key(*id)).arg(user_id);
-    }
-    let scores: Vec<Option<u64>> = pipe
-        .query_async(conn)
-        .await
-        .map_err(|e| api_errore(ApiErrorCode::InvalidDatabase, &e))?;
-
-    let mut like_ids = HashSet::new();
-    for (i, o) in scores.iter().enumerate() {
-        if o.is_some() {
-            like_ids.insert(reply_ids.get(i).unwrap());
-        }
-    }
+    let mut reaction_map = reaction::fetch_user_reactions(
+        conn,
+        reaction::TARGET_REPLY,
+        &reply_ids,
+        user_id,
+        reactions,
+    )
+    .await?;
 
     let mut out = ArcDataMap::new();
     for id in reply_ids.iter() {
+        let mut reactions = reaction_map.remove(id).unwrap_or_default();
+        let is_downvoted = reactions.iter().any(|s| s == reaction::DOWNVOTE_REACTION);
+        reactions.retain(|s| s != reaction::DOWNVOTE_REACTION);
         out.insert(
             *id,
             MySelfData {
-                is_liked: like_ids.contains(id),
+                is_liked: reactions.iter().any(|s| s == reaction::DEFAULT_REACTION),
+                is_downvoted,
+                reactions,
             },
         );
     }
@@ -236,6 +564,8 @@ where
 struct ReplyListPayload {
     app_id: u64,
     topic_id: u64,
+    #[serde(default)]
+    order_by: reply::VisibleOrderBy,
     cursor: u32,
     #[validate(custom = "validate_page_count")]
     count: u32,
@@ -245,7 +575,11 @@ struct ReplyListPayload {
 struct ReplyListItem {
     reply: reply::ReplySimple,
     user: Arc<user::UserSimple>,
+    attachments: Arc<Vec<attachment::AttachmentSimple>>,
+    quote: Option<reply::ReplyQuoteItem>,
     myself: Option<Arc<MySelfData>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    children: Option<Vec<ReplyListItem>>,
 }
 
 #[derive(Serialize)]
@@ -254,6 +588,70 @@ struct ReplyListResponse {
     replies: Vec<ReplyListItem>,
 }
 
+/// Hydrates users, attachments and the viewer's own state of the replies.
+async fn build_list_items(
+    conn: &mut SqlConnection,
+    user_id: u64,
+    replies: &Vec<reply::ReplyModel>,
+) -> Result<Vec<ReplyListItem>, ApiError> {
+    let (quote_map, user_ids) = reply::get_quote_map(conn, replies).await?;
+    let user_map = user::get_simple_map_by_ids(conn, user_ids).await?;
+    let attachment_map = attachment::get_simple_map_by_targets(
+        conn,
+        attachment::TARGET_REPLY,
+        replies.iter().map(|s| s.id).collect(),
+    )
+    .await?;
+
+    let myself_map = if user_id == 0 || replies.is_empty() {
+        ArcDataMap::new()
+    } else {
+        let app = app::get_by_id(conn, replies[0].app_id).await?;
+        let mut connr = redis_connect().await?;
+
+        fetch_myself(
+            &mut *connr,
+            user_id,
+            replies.iter().map(|s| s.id).collect(),
+            &app.user_reaction_list(),
+        )
+        .await?
+    };
+
+    let items = replies
+        .iter()
+        .map(|s| {
+            let myself = if user_id == 0 {
+                None
+            } else {
+                myself_map.opt(s.id)
+            };
+
+            if !s.is_actived() {
+                return ReplyListItem {
+                    user: user_map.get(s.user_id),
+                    reply: s.to_tombstone(),
+                    attachments: Default::default(),
+                    quote: None,
+                    myself,
+                    children: None,
+                };
+            }
+
+            ReplyListItem {
+                user: user_map.get(s.user_id),
+                reply: s.to_simple(),
+                attachments: attachment_map.get(s.id),
+                quote: reply::build_quote(s.quote_reply_id, &quote_map, &user_map),
+                myself,
+                children: None,
+            }
+        })
+        .collect();
+
+    Ok(items)
+}
+
 async fn reply_list(
     claims: Option<UserClaims>,
     Query(payload): Query<ReplyListPayload>,
@@ -277,45 +675,239 @@ async fn reply_list(
 
     let (total, replies) = reply::fetch_pagging(
         &mut conn,
-        topic.id,
+        &topic,
         reply::VisibleStyle::NORMAL,
+        payload.order_by,
         payload.cursor,
         payload.count,
     )
     .await?;
 
-    let user_map =
-        user::get_simple_map_by_ids(&mut conn, replies.iter().map(|s| s.user_id).collect()).await?;
+    let replies = build_list_items(&mut conn, claims.user_id, &replies).await?;
 
-    let myself_map = if claims.user_id == 0 || replies.is_empty() {
-        ArcDataMap::new()
-    } else {
-        let mut connr = redis_connect().await?;
+    Ok(api_success(ReplyListResponse { total, replies }))
+}
 
-        fetch_myself(
-            &mut *connr,
-            claims.user_id,
-            replies.iter().map(|s| s.id).collect(),
-        )
-        .await?
+async fn reply_thread(
+    claims: Option<UserClaims>,
+    Query(payload): Query<ReplyListPayload>,
+) -> Result<ApiSuccess<ReplyListResponse>, ApiError> {
+    match payload.validate() {
+        Err(e) => return Err(api_errore(ApiErrorCode::InvalidParams, &e)),
+        _ => {}
     };
 
-    let replies = replies
-        .iter()
-        .map(|s| {
-            let myself = if claims.user_id == 0 {
-                None
-            } else {
-                myself_map.opt(s.id)
-            };
+    let claims = claims.unwrap_or_default();
+    if claims.app_id != 0 && payload.app_id != claims.app_id {
+        return Err(api_error(ApiErrorCode::NoPermission));
+    }
 
-            ReplyListItem {
-                user: user_map.get(s.user_id),
-                reply: s.to_simple(),
-                myself,
-            }
-        })
-        .collect();
+    let mut conn = database_connect().await?;
+
+    let topic = topic::get_by_id(&mut conn, payload.topic_id).await?;
+    if payload.app_id != topic.app_id {
+        return Err(api_error(ApiErrorCode::NoPermission));
+    }
+
+    let (total, roots) = reply::fetch_root_pagging(
+        &mut conn,
+        &topic,
+        payload.order_by,
+        payload.cursor,
+        payload.count,
+    )
+    .await?;
+    let children = reply::fetch_first_children(
+        &mut conn,
+        roots.iter().map(|s| s.id).collect(),
+        reply::INLINE_CHILDREN,
+    )
+    .await?;
+
+    let root_count = roots.len();
+    let mut replies = roots;
+    replies.extend(children);
+
+    let mut items = build_list_items(&mut conn, claims.user_id, &replies).await?;
+    let children = items.split_off(root_count);
+
+    let mut children_map: HashMap<u64, Vec<ReplyListItem>> = HashMap::new();
+    for o in children {
+        children_map
+            .entry(o.reply.parent_reply_id)
+            .or_default()
+            .push(o);
+    }
+    for o in items.iter_mut() {
+        o.children = Some(children_map.remove(&o.reply.id).unwrap_or_default());
+    }
+
+    Ok(api_success(ReplyListResponse {
+        total,
+        replies: items,
+    }))
+}
+
+#[derive(Validate, Deserialize)]
+struct ReplyChildrenPayload {
+    app_id: u64,
+    reply_id: u64,
+    cursor: u32,
+    #[validate(custom = "validate_page_count")]
+    count: u32,
+}
+
+async fn reply_children(
+    claims: Option<UserClaims>,
+    Query(payload): Query<ReplyChildrenPayload>,
+) -> Result<ApiSuccess<ReplyListResponse>, ApiError> {
+    match payload.validate() {
+        Err(e) => return Err(api_errore(ApiErrorCode::InvalidParams, &e)),
+        _ => {}
+    };
+
+    let claims = claims.unwrap_or_default();
+    if claims.app_id != 0 && payload.app_id != claims.app_id {
+        return Err(api_error(ApiErrorCode::NoPermission));
+    }
+
+    let mut conn = database_connect().await?;
+
+    let parent = reply::get_by_id(&mut conn, payload.reply_id).await?;
+    if payload.app_id != parent.app_id {
+        return Err(api_error(ApiErrorCode::NoPermission));
+    }
+
+    let (total, replies) =
+        reply::fetch_children_pagging(&mut conn, parent.id, payload.cursor, payload.count).await?;
+
+    let replies = build_list_items(&mut conn, claims.user_id, &replies).await?;
 
     Ok(api_success(ReplyListResponse { total, replies }))
 }
+
+#[derive(Validate, Deserialize)]
+struct ReplyLocatePayload {
+    app_id: u64,
+    reply_id: u64,
+    #[serde(default)]
+    order_by: reply::VisibleOrderBy,
+    #[validate(range(min = 1), custom = "validate_page_count")]
+    count: u32,
+}
+
+#[derive(Serialize)]
+struct ReplyLocateResponse {
+    cursor: u32,
+    total: u32,
+    replies: Vec<ReplyListItem>,
+}
+
+/// Returns the page of `/reply/list` holding the reply, for deep links.
+async fn reply_locate(
+    claims: Option<UserClaims>,
+    Query(payload): Query<ReplyLocatePayload>,
+) -> Result<ApiSuccess<ReplyLocateResponse>, ApiError> {
+    match payload.validate() {
+        Err(e) => return Err(api_errore(ApiErrorCode::InvalidParams, &e)),
+        _ => {}
+    };
+
+    let claims = claims.unwrap_or_default();
+    if claims.app_id != 0 && payload.app_id != claims.app_id {
+        return Err(api_error(ApiErrorCode::NoPermission));
+    }
+
+    let mut conn = database_connect().await?;
+
+    let reply = reply::get_by_id(&mut conn, payload.reply_id).await?;
+    if payload.app_id != reply.app_id {
+        return Err(api_error(ApiErrorCode::NoPermission));
+    }
+
+    let topic = topic::get_by_id(&mut conn, reply.topic_id).await?;
+    let position = reply::get_position(&mut conn, &topic, &reply, payload.order_by).await?;
+    if position.is_none() {
+        return Err(api_error(ApiErrorCode::ReplyNotFound));
+    }
+
+    let cursor = position.unwrap() / payload.count * payload.count;
+    let (total, replies) = reply::fetch_pagging(
+        &mut conn,
+        &topic,
+        reply::VisibleStyle::NORMAL,
+        payload.order_by,
+        cursor,
+        payload.count,
+    )
+    .await?;
+
+    let replies = build_list_items(&mut conn, claims.user_id, &replies).await?;
+
+    Ok(api_success(ReplyLocateResponse {
+        cursor,
+        total,
+        replies,
+    }))
+}
+
+#[derive(Validate, Deserialize)]
+struct ReplyLikersPayload {
+    app_id: u64,
+    reply_id: u64,
+    reaction: Option<String>,
+    cursor: u32,
+    #[validate(custom = "validate_page_count")]
+    count: u32,
+}
+
+#[derive(Serialize)]
+struct ReplyLikersResponse {
+    total: u32,
+    users: Vec<reaction::ReactorItem>,
+}
+
+/// Users who liked the reply, or reacted with `reaction`, newest first.
+async fn reply_likers(
+    claims: Option<UserClaims>,
+    Query(payload): Query<ReplyLikersPayload>,
+) -> Result<ApiSuccess<ReplyLikersResponse>, ApiError> {
+    match payload.validate() {
+        Err(e) => return Err(api_errore(ApiErrorCode::InvalidParams, &e)),
+        _ => {}
+    };
+
+    let claims = claims.unwrap_or_default();
+    if claims.app_id != 0 && payload.app_id != claims.app_id {
+        return Err(api_error(ApiErrorCode::NoPermission));
+    }
+
+    let name = payload
+        .reaction
+        .unwrap_or(String::from(reaction::DEFAULT_REACTION));
+    if !reaction::is_valid_name(&name) {
+        return Err(api_error(ApiErrorCode::InvalidParams));
+    }
+
+    let mut conn = database_connect().await?;
+
+    let reply = reply::get_by_id(&mut conn, payload.reply_id).await?;
+    if payload.app_id != reply.app_id {
+        return Err(api_error(ApiErrorCode::NoPermission));
+    }
+    if !reply.is_actived() {
+        return Err(api_error(ApiErrorCode::ReplyNotFound));
+    }
+
+    let (total, users) = reaction::fetch_reactors(
+        &mut conn,
+        reaction::TARGET_REPLY,
+        reply.id,
+        &name,
+        payload.cursor,
+        payload.count,
+    )
+    .await?;
+
+    Ok(api_success(ReplyLikersResponse { total, users }))
+}
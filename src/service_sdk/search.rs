@@ -0,0 +1,65 @@
+use axum::{routing::get, Router};
+
+use super::base::*;
+use crate::shared::data::*;
+use crate::shared::model::*;
+use crate::shared::search;
+use crate::shared::web::*;
+
+async fn root() -> &'static str {
+    "DoggTalk SDK Search API"
+}
+
+pub fn setup_routers() -> Router {
+    Router::new()
+        .route("/", get(root))
+        .route("/query", get(search_query))
+}
+
+#[derive(Validate, Deserialize)]
+struct SearchQueryPayload {
+    app_id: u64,
+    category: u64,
+    #[validate(length(min = 1, max = 100))]
+    keyword: String,
+    cursor: u32,
+    #[validate(custom = "validate_page_count")]
+    count: u32,
+}
+
+#[derive(Serialize)]
+struct SearchQueryResponse {
+    total: u32,
+    items: Vec<search::SearchItem>,
+}
+
+async fn search_query(
+    claims: Option<UserClaims>,
+    Query(payload): Query<SearchQueryPayload>,
+) -> Result<ApiSuccess<SearchQueryResponse>, ApiError> {
+    match payload.validate() {
+        Err(e) => return Err(api_errore(ApiErrorCode::InvalidParams, &e)),
+        _ => {}
+    };
+
+    let claims = claims.unwrap_or_default();
+    if claims.app_id != 0 && payload.app_id != claims.app_id {
+        return Err(api_error(ApiErrorCode::NoPermission));
+    }
+
+    let mut conn = database_connect().await?;
+
+    app::get_by_id(&mut conn, payload.app_id).await?;
+
+    let (total, items) = search::query(
+        &mut conn,
+        payload.app_id,
+        payload.category,
+        &payload.keyword,
+        payload.cursor,
+        payload.count,
+    )
+    .await?;
+
+    Ok(api_success(SearchQueryResponse { total, items }))
+}
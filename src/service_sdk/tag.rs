@@ -0,0 +1,62 @@
+use axum::{routing::get, Router};
+
+use super::base::*;
+use crate::shared::data::*;
+use crate::shared::model::*;
+use crate::shared::web::*;
+
+async fn root() -> &'static str {
+    "DoggTalk SDK Tag API"
+}
+
+pub fn setup_routers() -> Router {
+    Router::new()
+        .route("/", get(root))
+        .route("/list", get(tag_list))
+}
+
+#[derive(Validate, Deserialize)]
+struct TagListPayload {
+    app_id: u64,
+    cursor: u32,
+    #[validate(custom = "validate_page_count")]
+    count: u32,
+}
+
+#[derive(Serialize)]
+struct TagListResponse {
+    total: u32,
+    tags: Vec<tag::TagSimple>,
+}
+
+async fn tag_list(
+    claims: Option<UserClaims>,
+    Query(payload): Query<TagListPayload>,
+) -> Result<ApiSuccess<TagListResponse>, ApiError> {
+    match payload.validate() {
+        Err(e) => return Err(api_errore(ApiErrorCode::InvalidParams, &e)),
+        _ => {}
+    };
+
+    let claims = claims.unwrap_or_default();
+    if claims.app_id != 0 && payload.app_id != claims.app_id {
+        return Err(api_error(ApiErrorCode::NoPermission));
+    }
+
+    let mut conn = database_connect().await?;
+
+    app::get_by_id(&mut conn, payload.app_id).await?;
+
+    let (total, tags) = tag::fetch_pagging(
+        &mut conn,
+        payload.app_id,
+        tag::VisibleStyle::NORMAL,
+        payload.cursor,
+        payload.count,
+    )
+    .await?;
+
+    let tags = tags.iter().map(|s| s.to_simple()).collect();
+
+    Ok(api_success(TagListResponse { total, tags }))
+}
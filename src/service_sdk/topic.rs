@@ -6,7 +6,12 @@ use axum::{
 use super::base::*;
 use crate::shared::base::*;
 use crate::shared::data::*;
+use crate::shared::inbox;
 use crate::shared::model::*;
+use crate::shared::ranking;
+use crate::shared::reaction;
+use crate::shared::search;
+use crate::shared::view;
 use crate::shared::web::*;
 
 async fn root() -> &'static str {
@@ -17,22 +22,113 @@ pub fn setup_routers() -> Router {
     Router::new()
         .route("/", get(root))
         .route("/create", post(topic_create))
+        .route("/update", post(topic_update))
+        .route("/delete", post(topic_delete))
+        .route("/lock", post(topic_lock))
+        .route("/unlock", post(topic_unlock))
+        .route("/accept", post(topic_accept))
         .route("/detail", get(topic_detail))
         .route("/like", post(topic_like))
         .route("/unlike", post(topic_unlike))
+        .route("/downvote", post(topic_downvote))
+        .route("/undownvote", post(topic_undownvote))
+        .route("/react", post(topic_react))
+        .route("/unreact", post(topic_unreact))
+        .route("/bookmark", post(topic_bookmark))
+        .route("/unbookmark", post(topic_unbookmark))
+        .route("/bookmark/list", get(topic_bookmark_list))
         .route("/list", get(topic_list))
+        .route("/likers", get(topic_likers))
+        .route("/poll/vote", post(topic_poll_vote))
+        .route("/poll/voters", get(topic_poll_voters))
 }
 
-fn like_key(id: u64) -> String {
-    format!("topiclike:{}", id)
+/// The bookmark sets are dropped after a day unused and loaded again.
+const BOOKMARK_WARM_TTL: u64 = 24 * 3600;
+
+fn bookmark_key(user_id: u64) -> String {
+    format!("userbookmark:{}", user_id)
+}
+
+fn bookmark_warm_key(user_id: u64) -> String {
+    format!("userbookmark:{}:warm", user_id)
+}
+
+/// Loads the bookmarks of a user from MySQL unless the warm marker is there.
+/// The marker stands in for users without bookmarks, whose empty set Redis
+/// does not keep.
+async fn warm_bookmarks<C>(
+    conn: &mut SqlConnection,
+    connr: &mut C,
+    user_id: u64,
+) -> Result<(), ApiError>
+where
+    C: RedisConnectionLike,
+{
+    let exists: bool = redis::cmd("EXISTS")
+        .arg(bookmark_warm_key(user_id))
+        .query_async(connr)
+        .await
+        .map_err(|e| api_errore(ApiErrorCode::InvalidDatabase, &e))?;
+    if exists {
+        return Ok(());
+    }
+
+    let bookmarks = bookmark::get_all_by_user(conn, user_id).await?;
+    let items: Vec<(i64, u64)> = bookmarks
+        .iter()
+        .map(|s| (s.created_at.and_utc().timestamp(), s.topic_id))
+        .collect();
+
+    let key = bookmark_key(user_id);
+    let mut pipe = redis::pipe();
+    pipe.atomic();
+    pipe.cmd("DEL").arg(&key).ignore();
+    if !items.is_empty() {
+        pipe.cmd("ZADD").arg(&key).arg(items).ignore();
+        // outlives the marker, a set gone while the marker is there reads empty
+        pipe.cmd("EXPIRE")
+            .arg(&key)
+            .arg(BOOKMARK_WARM_TTL + 60)
+            .ignore();
+    }
+    pipe.cmd("SET")
+        .arg(bookmark_warm_key(user_id))
+        .arg(1)
+        .arg("EX")
+        .arg(BOOKMARK_WARM_TTL)
+        .ignore();
+    pipe.query_async::<()>(connr)
+        .await
+        .map_err(|e| api_errore(ApiErrorCode::InvalidDatabase, &e))?;
+
+    Ok(())
 }
 
 #[derive(Deserialize)]
-struct TopicCreatePayload {
-    app_id: u64,
-    category: u64,
-    title: String,
-    content: String,
+pub(super) struct TopicPollPayload {
+    options: Vec<String>,
+    #[serde(default)]
+    multiple: bool,
+    #[serde(default)]
+    anonymous: bool,
+    close_at: Option<i64>,
+}
+
+#[derive(Validate, Deserialize)]
+pub(super) struct TopicCreatePayload {
+    pub(super) app_id: u64,
+    pub(super) category: u64,
+    #[validate(length(min = 1, max = 1024))]
+    pub(super) title: String,
+    #[serde(default)]
+    #[validate(custom = "validate_content_format")]
+    pub(super) content_format: i8,
+    #[validate(length(max = 16000))]
+    pub(super) content: String,
+    pub(super) tags: Option<Vec<String>>,
+    pub(super) attachment_ids: Option<Vec<u64>>,
+    pub(super) poll: Option<TopicPollPayload>,
 }
 
 #[derive(Serialize)]
@@ -44,6 +140,186 @@ async fn topic_create(
     claims: UserClaims,
     Json(payload): Json<TopicCreatePayload>,
 ) -> Result<ApiSuccess<TopicCreateResponse>, ApiError> {
+    let topic_id = create_topic(&claims, payload).await?;
+
+    Ok(api_success(TopicCreateResponse { topic_id }))
+}
+
+/// Shared by `topic_create` and publishing a draft.
+pub(super) async fn create_topic(
+    claims: &UserClaims,
+    payload: TopicCreatePayload,
+) -> Result<u64, ApiError> {
+    match payload.validate() {
+        Err(e) => return Err(api_errore(ApiErrorCode::InvalidParams, &e)),
+        _ => {}
+    };
+
+    if payload.app_id != claims.app_id {
+        return Err(api_error(ApiErrorCode::NoPermission));
+    }
+
+    let mut conn = database_connect().await?;
+
+    let user = user::get_by_id(&mut conn, claims.user_id).await?;
+    if payload.app_id != user.app_id {
+        return Err(api_error(ApiErrorCode::NoPermission));
+    }
+    if !user.is_actived() {
+        return Err(api_error(ApiErrorCode::AccountNotActived));
+    }
+
+    let tag_names = tag::collect_names(payload.tags, &payload.content);
+    let poll = match payload.poll {
+        Some(poll) => {
+            let (options, close_at) = poll::check_options(poll.options, poll.close_at)?;
+            let poll = poll::PollModel {
+                app_id: user.app_id,
+                multiple: poll.multiple,
+                anonymous: poll.anonymous,
+                close_at,
+                ..Default::default()
+            };
+            Some((poll, options))
+        }
+        None => None,
+    };
+
+    let mut topic = topic::TopicModel {
+        app_id: user.app_id,
+        user_id: user.id,
+        category: payload.category,
+        title: payload.title,
+        content_format: payload.content_format,
+        content: payload.content,
+        ..Default::default()
+    };
+
+    let topic_id = topic::create(&mut conn, &mut topic).await?;
+    tag::update_topic_tags(&mut conn, topic.app_id, topic_id, tag_names).await?;
+    if let Some((mut poll, options)) = poll {
+        poll.topic_id = topic_id;
+        poll::create(&mut conn, &mut poll, options).await?;
+    }
+    attachment::bind_target(
+        &mut conn,
+        user.id,
+        attachment::TARGET_TOPIC,
+        topic_id,
+        payload.attachment_ids,
+    )
+    .await?;
+    user::update_topic_count(&mut conn, claims.user_id, UpdateCountOp::INCR).await?;
+
+    let topic = topic::get_by_id(&mut conn, topic_id).await?;
+    search::sync_topic(&topic);
+    inbox::notify_topic(&mut conn, &topic).await;
+
+    Ok(topic.id)
+}
+
+#[derive(Validate, Deserialize)]
+struct TopicUpdatePayload {
+    app_id: u64,
+    topic_id: u64,
+    #[validate(length(min = 1, max = 1024))]
+    title: String,
+    #[serde(default)]
+    #[validate(custom = "validate_content_format")]
+    content_format: i8,
+    #[validate(length(max = 16000))]
+    content: String,
+    tags: Option<Vec<String>>,
+}
+
+#[derive(Serialize)]
+struct TopicUpdateResponse {
+    topic: topic::TopicSimple,
+}
+
+async fn topic_update(
+    claims: UserClaims,
+    Json(payload): Json<TopicUpdatePayload>,
+) -> Result<ApiSuccess<TopicUpdateResponse>, ApiError> {
+    match payload.validate() {
+        Err(e) => return Err(api_errore(ApiErrorCode::InvalidParams, &e)),
+        _ => {}
+    };
+
+    if payload.app_id != claims.app_id {
+        return Err(api_error(ApiErrorCode::NoPermission));
+    }
+
+    let mut conn = database_connect().await?;
+
+    let user = user::get_by_id(&mut conn, claims.user_id).await?;
+    if payload.app_id != user.app_id {
+        return Err(api_error(ApiErrorCode::NoPermission));
+    }
+    if !user.is_actived() {
+        return Err(api_error(ApiErrorCode::AccountNotActived));
+    }
+
+    let mut topic = topic::get_by_id(&mut conn, payload.topic_id).await?;
+    if topic.app_id != payload.app_id || topic.user_id != user.id {
+        return Err(api_error(ApiErrorCode::NoPermission));
+    }
+    if !topic.is_actived() {
+        return Err(api_error(ApiErrorCode::TopicNotFound));
+    }
+    if topic.moved_to > 0 {
+        return Err(api_error(ApiErrorCode::TopicMoved));
+    }
+
+    let app = app::get_by_id(&mut conn, topic.app_id).await?;
+    if topic.locked || app.is_auto_locked(topic.refreshed_at) {
+        return Err(api_error(ApiErrorCode::TopicLocked));
+    }
+    if !app.is_editable(topic.created_at) {
+        return Err(api_error(ApiErrorCode::TopicNotEditable));
+    }
+
+    let mut revision = topic_revision::TopicRevisionModel::from_topic(
+        &topic,
+        topic_revision::EDITOR_USER,
+        user.id,
+    );
+    let tag_names = tag::collect_names(payload.tags, &payload.content);
+
+    topic.title = payload.title;
+    topic.content_format = payload.content_format;
+    topic.content = payload.content;
+
+    let mut tx = database_begin(&mut conn).await?;
+    topic_revision::create(&mut tx, &mut revision).await?;
+    topic::update_content(&mut tx, &mut topic).await?;
+    database_commit(tx).await?;
+
+    tag::update_topic_tags(&mut conn, topic.app_id, topic.id, tag_names).await?;
+
+    let topic = topic::get_by_id(&mut conn, topic.id).await?;
+    search::sync_topic(&topic);
+
+    Ok(api_success(TopicUpdateResponse {
+        topic: topic.to_simple(),
+    }))
+}
+
+#[derive(Deserialize)]
+struct TopicDeletePayload {
+    app_id: u64,
+    topic_id: u64,
+}
+
+#[derive(Serialize)]
+struct TopicDeleteResponse {
+    topic_id: u64,
+}
+
+async fn topic_delete(
+    claims: UserClaims,
+    Json(payload): Json<TopicDeletePayload>,
+) -> Result<ApiSuccess<TopicDeleteResponse>, ApiError> {
     if payload.app_id != claims.app_id {
         return Err(api_error(ApiErrorCode::NoPermission));
     }
@@ -58,39 +334,402 @@ async fn topic_create(
         return Err(api_error(ApiErrorCode::AccountNotActived));
     }
 
-    let mut topic = topic::TopicModel {
-        app_id: user.app_id,
-        user_id: user.id,
-        category: payload.category,
-        title: payload.title,
-        content: payload.content,
-        ..Default::default()
-    };
+    let topic = topic::get_by_id(&mut conn, payload.topic_id).await?;
+    if topic.app_id != payload.app_id || topic.user_id != user.id {
+        return Err(api_error(ApiErrorCode::NoPermission));
+    }
+    if topic.is_deleted() {
+        return Err(api_error(ApiErrorCode::TopicNotFound));
+    }
+
+    topic::update_status(
+        &mut conn,
+        topic.id,
+        topic::StatusAction::DELETE,
+        topic::StatusOperator::AUTHOR,
+    )
+    .await?;
+    // placeholders of moved topics were never counted
+    if topic.moved_to == 0 {
+        user::update_topic_count(&mut conn, topic.user_id, UpdateCountOp::DECR).await?;
+    }
+    tag::unlink_topic(&mut conn, topic.id).await?;
+    attachment::purge_target(&mut conn, attachment::TARGET_TOPIC, topic.id).await?;
+    ranking::remove_topic(topic.app_id, topic.id, topic.category).await?;
+
+    let topic = topic::get_by_id(&mut conn, topic.id).await?;
+    search::sync_topic_tree(&mut conn, &topic).await;
+
+    Ok(api_success(TopicDeleteResponse { topic_id: topic.id }))
+}
+
+#[derive(Deserialize)]
+struct TopicLockPayload {
+    app_id: u64,
+    topic_id: u64,
+}
+
+#[derive(Serialize)]
+struct TopicLockResponse {
+    topic_id: u64,
+    locked: bool,
+}
+
+/// Authors lock their own topics when the app allows it, and may only undo
+/// locks of their own, not those of managers or auto-lock.
+async fn update_lock(
+    claims: UserClaims,
+    payload: TopicLockPayload,
+    locked: bool,
+) -> Result<ApiSuccess<TopicLockResponse>, ApiError> {
+    if payload.app_id != claims.app_id {
+        return Err(api_error(ApiErrorCode::NoPermission));
+    }
+
+    let mut conn = database_connect().await?;
+
+    let app = app::get_by_id(&mut conn, payload.app_id).await?;
+    if !app.author_lock {
+        return Err(api_error(ApiErrorCode::NoPermission));
+    }
+
+    let user = user::get_by_id(&mut conn, claims.user_id).await?;
+    if payload.app_id != user.app_id {
+        return Err(api_error(ApiErrorCode::NoPermission));
+    }
+    if !user.is_actived() {
+        return Err(api_error(ApiErrorCode::AccountNotActived));
+    }
+
+    let topic = topic::get_by_id(&mut conn, payload.topic_id).await?;
+    if topic.app_id != payload.app_id || topic.user_id != user.id {
+        return Err(api_error(ApiErrorCode::NoPermission));
+    }
+    if !topic.is_actived() {
+        return Err(api_error(ApiErrorCode::TopicNotFound));
+    }
+    if topic.moved_to > 0 {
+        return Err(api_error(ApiErrorCode::TopicMoved));
+    }
+
+    if locked {
+        topic::lock(&mut conn, topic.id, topic::LOCKED_BY_AUTHOR).await?;
+    } else if topic.locked
+        && (topic.locked_by != topic::LOCKED_BY_AUTHOR
+            || !topic::unlock(&mut conn, topic.id, Some(topic::LOCKED_BY_AUTHOR)).await?)
+    {
+        return Err(api_error(ApiErrorCode::NoPermission));
+    }
+
+    Ok(api_success(TopicLockResponse {
+        topic_id: topic.id,
+        locked,
+    }))
+}
+
+async fn topic_lock(
+    claims: UserClaims,
+    Json(payload): Json<TopicLockPayload>,
+) -> Result<ApiSuccess<TopicLockResponse>, ApiError> {
+    update_lock(claims, payload, true).await
+}
+
+async fn topic_unlock(
+    claims: UserClaims,
+    Json(payload): Json<TopicLockPayload>,
+) -> Result<ApiSuccess<TopicLockResponse>, ApiError> {
+    update_lock(claims, payload, false).await
+}
+
+#[derive(Deserialize)]
+struct TopicAcceptPayload {
+    app_id: u64,
+    topic_id: u64,
+    reply_id: u64,
+}
+
+#[derive(Serialize)]
+struct TopicAcceptResponse {
+    topic_id: u64,
+    accepted_reply_id: u64,
+}
+
+/// Marks a reply as the accepted answer, reply_id 0 clears it.
+async fn topic_accept(
+    claims: UserClaims,
+    Json(payload): Json<TopicAcceptPayload>,
+) -> Result<ApiSuccess<TopicAcceptResponse>, ApiError> {
+    if payload.app_id != claims.app_id {
+        return Err(api_error(ApiErrorCode::NoPermission));
+    }
+
+    let mut conn = database_connect().await?;
+
+    let topic = topic::get_by_id(&mut conn, payload.topic_id).await?;
+    if topic.app_id != payload.app_id || topic.user_id != claims.user_id {
+        return Err(api_error(ApiErrorCode::NoPermission));
+    }
+    if !topic.is_actived() {
+        return Err(api_error(ApiErrorCode::TopicNotFound));
+    }
+    if topic.moved_to > 0 {
+        return Err(api_error(ApiErrorCode::TopicMoved));
+    }
+
+    if payload.reply_id > 0 {
+        let app = app::get_by_id(&mut conn, topic.app_id).await?;
+        if !app.is_qa_category(topic.category) {
+            return Err(api_error(ApiErrorCode::TopicNotQa));
+        }
+
+        let reply = reply::get_by_id(&mut conn, payload.reply_id).await?;
+        if reply.topic_id != topic.id || !reply.is_actived() {
+            return Err(api_error(ApiErrorCode::ReplyNotFound));
+        }
+    }
+
+    topic::update_accepted_reply(&mut conn, topic.id, payload.reply_id).await?;
+
+    Ok(api_success(TopicAcceptResponse {
+        topic_id: topic.id,
+        accepted_reply_id: payload.reply_id,
+    }))
+}
+
+#[derive(Deserialize)]
+struct TopicLikePayload {
+    app_id: u64,
+    topic_id: u64,
+}
+
+#[derive(Serialize)]
+struct TopicLikeResponse {
+    affect: u64,
+    like_count: u64,
+    score: i64,
+}
+
+async fn topic_like(
+    claims: UserClaims,
+    Json(payload): Json<TopicLikePayload>,
+) -> Result<ApiSuccess<TopicLikeResponse>, ApiError> {
+    let (affect, topic) = update_reaction(
+        &claims,
+        payload.app_id,
+        payload.topic_id,
+        reaction::DEFAULT_REACTION,
+        UpdateCountOp::INCR,
+    )
+    .await?;
+
+    Ok(api_success(TopicLikeResponse {
+        affect,
+        like_count: topic.like_count,
+        score: topic.score,
+    }))
+}
+
+async fn topic_unlike(
+    claims: UserClaims,
+    Json(payload): Json<TopicLikePayload>,
+) -> Result<ApiSuccess<TopicLikeResponse>, ApiError> {
+    let (affect, topic) = update_reaction(
+        &claims,
+        payload.app_id,
+        payload.topic_id,
+        reaction::DEFAULT_REACTION,
+        UpdateCountOp::DECR,
+    )
+    .await?;
+
+    Ok(api_success(TopicLikeResponse {
+        affect,
+        like_count: topic.like_count,
+        score: topic.score,
+    }))
+}
+
+async fn topic_downvote(
+    claims: UserClaims,
+    Json(payload): Json<TopicLikePayload>,
+) -> Result<ApiSuccess<TopicLikeResponse>, ApiError> {
+    let (affect, topic) = update_reaction(
+        &claims,
+        payload.app_id,
+        payload.topic_id,
+        reaction::DOWNVOTE_REACTION,
+        UpdateCountOp::INCR,
+    )
+    .await?;
+
+    Ok(api_success(TopicLikeResponse {
+        affect,
+        like_count: topic.like_count,
+        score: topic.score,
+    }))
+}
+
+async fn topic_undownvote(
+    claims: UserClaims,
+    Json(payload): Json<TopicLikePayload>,
+) -> Result<ApiSuccess<TopicLikeResponse>, ApiError> {
+    let (affect, topic) = update_reaction(
+        &claims,
+        payload.app_id,
+        payload.topic_id,
+        reaction::DOWNVOTE_REACTION,
+        UpdateCountOp::DECR,
+    )
+    .await?;
+
+    Ok(api_success(TopicLikeResponse {
+        affect,
+        like_count: topic.like_count,
+        score: topic.score,
+    }))
+}
+
+#[derive(Deserialize)]
+struct TopicReactPayload {
+    app_id: u64,
+    topic_id: u64,
+    reaction: String,
+}
+
+#[derive(Serialize)]
+struct TopicReactResponse {
+    affect: u64,
+    reaction_counts: HashMap<String, u64>,
+}
+
+async fn topic_react(
+    claims: UserClaims,
+    Json(payload): Json<TopicReactPayload>,
+) -> Result<ApiSuccess<TopicReactResponse>, ApiError> {
+    let (affect, topic) = update_reaction(
+        &claims,
+        payload.app_id,
+        payload.topic_id,
+        &payload.reaction,
+        UpdateCountOp::INCR,
+    )
+    .await?;
+
+    Ok(api_success(TopicReactResponse {
+        affect,
+        reaction_counts: topic.reaction_map(),
+    }))
+}
+
+async fn topic_unreact(
+    claims: UserClaims,
+    Json(payload): Json<TopicReactPayload>,
+) -> Result<ApiSuccess<TopicReactResponse>, ApiError> {
+    let (affect, topic) = update_reaction(
+        &claims,
+        payload.app_id,
+        payload.topic_id,
+        &payload.reaction,
+        UpdateCountOp::DECR,
+    )
+    .await?;
+
+    Ok(api_success(TopicReactResponse {
+        affect,
+        reaction_counts: topic.reaction_map(),
+    }))
+}
+
+/// Adds or removes a reaction of the user, returns whether anything changed
+/// along with the topic holding the updated counts.
+async fn update_reaction(
+    claims: &UserClaims,
+    app_id: u64,
+    topic_id: u64,
+    name: &str,
+    op: UpdateCountOp,
+) -> Result<(u64, topic::TopicModel), ApiError> {
+    if app_id != claims.app_id {
+        return Err(api_error(ApiErrorCode::NoPermission));
+    }
+
+    let mut conn = database_connect().await?;
+
+    let user = user::get_by_id(&mut conn, claims.user_id).await?;
+    if app_id != user.app_id {
+        return Err(api_error(ApiErrorCode::NoPermission));
+    }
+    if !user.is_actived() {
+        return Err(api_error(ApiErrorCode::AccountNotActived));
+    }
+
+    let topic = topic::get_by_id(&mut conn, topic_id).await?;
+    if topic.app_id != app_id {
+        return Err(api_error(ApiErrorCode::NoPermission));
+    }
+    if !topic.is_actived() {
+        return Err(api_error(ApiErrorCode::TopicNotFound));
+    }
+
+    // a reaction taken off the app, or downvotes turned off, can still be
+    // removed by those who left it
+    let app = app::get_by_id(&mut conn, app_id).await?;
+    if op == UpdateCountOp::INCR && !app.is_reaction_allowed(name) {
+        return Err(api_error(ApiErrorCode::ReactionNotAllowed));
+    }
+
+    let changed = match op {
+        UpdateCountOp::INCR => {
+            reaction::add(
+                &mut conn,
+                topic.app_id,
+                reaction::TARGET_TOPIC,
+                topic.id,
+                user.id,
+                name,
+            )
+            .await?
+        }
+        _ => reaction::remove(&mut conn, reaction::TARGET_TOPIC, topic.id, user.id, name).await?,
+    };
+    if !changed {
+        return Ok((0, topic));
+    }
+
+    let added = op == UpdateCountOp::INCR;
+    topic::update_reaction_count(&mut conn, topic.id, name, op).await?;
 
-    let topic_id = topic::create(&mut conn, &mut topic).await?;
-    user::update_topic_count(&mut conn, claims.user_id, UpdateCountOp::INCR).await?;
+    // likes and downvotes exclude each other
+    if let Some(other) = reaction::opposite(name).filter(|_| added) {
+        if reaction::remove(&mut conn, reaction::TARGET_TOPIC, topic.id, user.id, other).await? {
+            topic::update_reaction_count(&mut conn, topic.id, other, UpdateCountOp::DECR).await?;
+        }
+    }
+    if added && name == reaction::DEFAULT_REACTION {
+        inbox::notify_like(&mut conn, &topic, None, user.id).await;
+    }
 
-    let topic = topic::get_by_id(&mut conn, topic_id).await?;
+    let topic = topic::get_by_id(&mut conn, topic.id).await?;
 
-    Ok(api_success(TopicCreateResponse { topic_id: topic.id }))
+    Ok((1, topic))
 }
 
 #[derive(Deserialize)]
-struct TopicLikePayload {
+struct TopicBookmarkPayload {
     app_id: u64,
     topic_id: u64,
 }
 
 #[derive(Serialize)]
-struct TopicLikeResponse {
+struct TopicBookmarkResponse {
     affect: u64,
-    like_count: u64,
+    bookmark_count: u64,
 }
 
-async fn topic_like(
+async fn topic_bookmark(
     claims: UserClaims,
-    Json(payload): Json<TopicLikePayload>,
-) -> Result<ApiSuccess<TopicLikeResponse>, ApiError> {
+    Json(payload): Json<TopicBookmarkPayload>,
+) -> Result<ApiSuccess<TopicBookmarkResponse>, ApiError> {
     if payload.app_id != claims.app_id {
         return Err(api_error(ApiErrorCode::NoPermission));
     }
@@ -114,79 +753,153 @@ async fn topic_like(
     }
 
     let mut connr = redis_connect().await?;
+    warm_bookmarks(&mut conn, &mut *connr, user.id).await?;
 
-    let affect: u64 = redis::cmd("ZADD")
-        .arg(like_key(topic.id))
-        .arg("NX")
-        .arg(timestamp())
-        .arg(user.id)
-        .query_async(&mut *connr)
-        .await
-        .map_err(|e| api_errore(ApiErrorCode::InvalidDatabase, &e))?;
-
+    let affect = bookmark::create(&mut conn, topic.app_id, user.id, topic.id).await? as u64;
     if affect > 0 {
-        topic::update_like_count(&mut conn, topic.id, UpdateCountOp::INCR).await?;
+        topic::update_bookmark_count(&mut conn, topic.id, UpdateCountOp::INCR).await?;
+
+        redis::cmd("ZADD")
+            .arg(bookmark_key(user.id))
+            .arg(timestamp())
+            .arg(topic.id)
+            .query_async::<()>(&mut *connr)
+            .await
+            .map_err(|e| api_errore(ApiErrorCode::InvalidDatabase, &e))?;
     }
 
-    Ok(api_success(TopicLikeResponse {
+    Ok(api_success(TopicBookmarkResponse {
         affect,
-        like_count: topic.like_count + affect,
+        bookmark_count: topic.bookmark_count + affect,
     }))
 }
 
-async fn topic_unlike(
+async fn topic_unbookmark(
     claims: UserClaims,
-    Json(payload): Json<TopicLikePayload>,
-) -> Result<ApiSuccess<TopicLikeResponse>, ApiError> {
+    Json(payload): Json<TopicBookmarkPayload>,
+) -> Result<ApiSuccess<TopicBookmarkResponse>, ApiError> {
     if payload.app_id != claims.app_id {
         return Err(api_error(ApiErrorCode::NoPermission));
     }
 
     let mut conn = database_connect().await?;
 
-    let user = user::get_by_id(&mut conn, claims.user_id).await?;
-    if payload.app_id != user.app_id {
+    let topic = topic::get_by_id(&mut conn, payload.topic_id).await?;
+    if topic.app_id != payload.app_id {
         return Err(api_error(ApiErrorCode::NoPermission));
     }
-    if !user.is_actived() {
-        return Err(api_error(ApiErrorCode::AccountNotActived));
+
+    let affect = bookmark::delete(&mut conn, claims.user_id, topic.id).await? as u64;
+    if affect > 0 {
+        topic::update_bookmark_count(&mut conn, topic.id, UpdateCountOp::DECR).await?;
     }
 
-    let topic = topic::get_by_id(&mut conn, payload.topic_id).await?;
-    if topic.app_id != payload.app_id {
+    let mut connr = redis_connect().await?;
+
+    redis::cmd("ZREM")
+        .arg(bookmark_key(claims.user_id))
+        .arg(topic.id)
+        .query_async::<()>(&mut *connr)
+        .await
+        .map_err(|e| api_errore(ApiErrorCode::InvalidDatabase, &e))?;
+
+    Ok(api_success(TopicBookmarkResponse {
+        affect,
+        bookmark_count: topic.bookmark_count.saturating_sub(affect),
+    }))
+}
+
+#[derive(Validate, Deserialize)]
+struct TopicBookmarkListPayload {
+    app_id: u64,
+    cursor: u32,
+    #[validate(custom = "validate_page_count")]
+    count: u32,
+}
+
+async fn topic_bookmark_list(
+    claims: UserClaims,
+    Query(payload): Query<TopicBookmarkListPayload>,
+) -> Result<ApiSuccess<TopicListResponse>, ApiError> {
+    match payload.validate() {
+        Err(e) => return Err(api_errore(ApiErrorCode::InvalidParams, &e)),
+        _ => {}
+    };
+
+    if payload.app_id != claims.app_id {
         return Err(api_error(ApiErrorCode::NoPermission));
     }
-    if !topic.is_actived() {
-        return Err(api_error(ApiErrorCode::TopicNotFound));
-    }
+
+    let mut conn = database_connect().await?;
+
+    let app = app::get_by_id(&mut conn, payload.app_id).await?;
 
     let mut connr = redis_connect().await?;
+    warm_bookmarks(&mut conn, &mut *connr, claims.user_id).await?;
 
-    let affect: u64 = redis::cmd("ZREM")
-        .arg(like_key(topic.id))
-        .arg(user.id)
+    let topic_ids: Vec<u64> = redis::cmd("ZREVRANGE")
+        .arg(bookmark_key(claims.user_id))
+        .arg(payload.cursor)
+        .arg(payload.cursor as i64 + payload.count as i64 - 1)
         .query_async(&mut *connr)
         .await
         .map_err(|e| api_errore(ApiErrorCode::InvalidDatabase, &e))?;
+    let total = bookmark::count_visible(&mut conn, payload.app_id, claims.user_id).await?;
 
-    if affect > 0 {
-        topic::update_like_count(&mut conn, topic.id, UpdateCountOp::DECR).await?;
-    }
+    // bookmarked topics that were hidden or deleted since are skipped
+    let topics: Vec<topic::TopicModel> = topic::get_list_by_ids(&mut conn, &topic_ids)
+        .await?
+        .into_iter()
+        .filter(|s| s.app_id == payload.app_id && s.is_actived())
+        .collect();
 
-    Ok(api_success(TopicLikeResponse {
-        affect,
-        like_count: topic.like_count - affect,
-    }))
+    let user_map =
+        user::get_simple_map_by_ids(&mut conn, topics.iter().map(|s| s.user_id).collect()).await?;
+
+    let myself_map = if topics.is_empty() {
+        ArcDataMap::new()
+    } else {
+        fetch_myself(
+            &mut *connr,
+            claims.user_id,
+            topics.iter().map(|s| s.id).collect(),
+            &app.user_reaction_list(),
+            HashMap::new(),
+        )
+        .await?
+    };
+
+    let topics = topics
+        .iter()
+        .map(|s| TopicListItem {
+            user: user_map.get(s.user_id),
+            topic: s.to_preview(),
+            myself: myself_map.opt(s.id),
+        })
+        .collect();
+
+    Ok(api_success(TopicListResponse { total, topics }))
 }
 
 #[derive(Serialize)]
 struct MySelfData {
     is_liked: bool,
+    is_downvoted: bool,
+    reactions: Vec<String>,
+    is_bookmarked: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    poll_votes: Option<Vec<u64>>,
 }
 
 impl Default for MySelfData {
     fn default() -> Self {
-        Self { is_liked: false }
+        Self {
+            is_liked: false,
+            is_downvoted: false,
+            reactions: Vec::new(),
+            is_bookmarked: false,
+            poll_votes: None,
+        }
     }
 }
 
@@ -194,32 +907,50 @@ async fn fetch_myself<C>(
     conn: &mut C,
     user_id: u64,
     topic_ids: Vec<u64>,
+    reactions: &Vec<String>,
+    mut poll_votes: HashMap<u64, Vec<u64>>,
 ) -> Result<ArcDataMap<u64, MySelfData>, ApiError>
 where
     C: RedisConnectionLike,
 {
+    let mut reaction_map = reaction::fetch_user_reactions(
+        &mut *conn,
+        reaction::TARGET_TOPIC,
+        &topic_ids,
+        user_id,
+        reactions,
+    )
+    .await?;
+
     let mut pipe = redis::pipe();
     for id in topic_ids.iter() {
-        pipe.cmd("ZSCORE").arg(like_key(*id)).arg(user_id);
+        pipe.cmd("ZSCORE").arg(bookmark_key(user_id)).arg(*id);
     }
     let scores: Vec<Option<u64>> = pipe
         .query_async(conn)
         .await
         .map_err(|e| api_errore(ApiErrorCode::InvalidDatabase, &e))?;
 
-    let mut like_ids = HashSet::new();
+    let mut bookmark_ids = HashSet::new();
     for (i, o) in scores.iter().enumerate() {
         if o.is_some() {
-            like_ids.insert(topic_ids.get(i).unwrap());
+            bookmark_ids.insert(topic_ids.get(i).unwrap());
         }
     }
 
     let mut out = ArcDataMap::new();
     for id in topic_ids.iter() {
+        let mut reactions = reaction_map.remove(id).unwrap_or_default();
+        let is_downvoted = reactions.iter().any(|s| s == reaction::DOWNVOTE_REACTION);
+        reactions.retain(|s| s != reaction::DOWNVOTE_REACTION);
         out.insert(
             *id,
             MySelfData {
-                is_liked: like_ids.contains(id),
+                is_liked: reactions.iter().any(|s| s == reaction::DEFAULT_REACTION),
+                is_downvoted,
+                reactions,
+                is_bookmarked: bookmark_ids.contains(id),
+                poll_votes: poll_votes.remove(id),
             },
         );
     }
@@ -237,11 +968,16 @@ struct TopicDetailPayload {
 struct TopicDetailResponse {
     user: user::UserSimple,
     topic: topic::TopicSimple,
+    tags: Vec<tag::TagSimple>,
+    attachments: Vec<attachment::AttachmentSimple>,
+    poll: Option<poll::PollSimple>,
+    liked_by: reaction::ReactorSummary,
     myself: Option<Arc<MySelfData>>,
 }
 
 async fn topic_detail(
     claims: Option<UserClaims>,
+    ClientIp(client_ip): ClientIp,
     Query(payload): Query<TopicDetailPayload>,
 ) -> Result<ApiSuccess<TopicDetailResponse>, ApiError> {
     let claims = claims.unwrap_or_default();
@@ -260,13 +996,50 @@ async fn topic_detail(
     }
 
     let user = user::get_by_id(&mut conn, topic.user_id).await?;
+    let tags = tag::get_simple_list_by_topic(&mut conn, topic.id).await?;
+    let attachments =
+        attachment::get_simple_list_by_target(&mut conn, attachment::TARGET_TOPIC, topic.id)
+            .await?;
+    let poll = poll::get_simple_by_topic(&mut conn, topic.id).await?;
+    let liked_by = reaction::fetch_summary(
+        &mut conn,
+        reaction::TARGET_TOPIC,
+        topic.id,
+        reaction::DEFAULT_REACTION,
+        reaction::SUMMARY_USERS,
+    )
+    .await?;
+
+    let viewer = if claims.user_id == 0 {
+        format!("ip:{}", client_ip)
+    } else {
+        format!("u:{}", claims.user_id)
+    };
+    view::record(topic.id, &viewer).await;
 
     let myself = if claims.user_id == 0 {
         None
     } else {
+        let mut poll_votes = HashMap::new();
+        if let Some(poll) = &poll {
+            poll_votes.insert(
+                topic.id,
+                poll::get_user_choices(&mut conn, poll.id, claims.user_id).await?,
+            );
+        }
+
         let mut connr = redis_connect().await?;
+        warm_bookmarks(&mut conn, &mut *connr, claims.user_id).await?;
 
-        let myself_map = fetch_myself(&mut *connr, claims.user_id, vec![topic.id]).await?;
+        let app = app::get_by_id(&mut conn, topic.app_id).await?;
+        let myself_map = fetch_myself(
+            &mut *connr,
+            claims.user_id,
+            vec![topic.id],
+            &app.user_reaction_list(),
+            poll_votes,
+        )
+        .await?;
 
         myself_map.opt(topic.id)
     };
@@ -274,6 +1047,10 @@ async fn topic_detail(
     Ok(api_success(TopicDetailResponse {
         user: user.to_simple(),
         topic: topic.to_simple(),
+        tags,
+        attachments,
+        poll,
+        liked_by,
         myself,
     }))
 }
@@ -282,7 +1059,11 @@ async fn topic_detail(
 struct TopicListPayload {
     app_id: u64,
     category: u64,
+    tag: Option<String>,
+    solved: Option<bool>,
     order_by: topic::VisibleOrderBy,
+    #[serde(default)]
+    window: topic::VisibleTopWindow,
     cursor: u32,
     #[validate(custom = "validate_page_count")]
     count: u32,
@@ -317,18 +1098,51 @@ async fn topic_list(
 
     let mut conn = database_connect().await?;
 
-    app::get_by_id(&mut conn, payload.app_id).await?;
+    let app = app::get_by_id(&mut conn, payload.app_id).await?;
 
-    let (total, topics) = topic::fetch_pagging(
-        &mut conn,
-        payload.app_id,
-        payload.category,
-        topic::VisibleStyle::NORMAL,
-        payload.order_by,
-        payload.cursor,
-        payload.count,
-    )
-    .await?;
+    let tag_id = match &payload.tag {
+        Some(name) => {
+            tag::get_actived_by_name(&mut conn, payload.app_id, name)
+                .await?
+                .id
+        }
+        None => 0,
+    };
+
+    let hot = if payload.order_by == topic::VisibleOrderBy::HOT
+        && tag_id == 0
+        && payload.solved.is_none()
+    {
+        ranking::fetch_hot_pagging(
+            &mut conn,
+            payload.app_id,
+            payload.category,
+            payload.cursor,
+            payload.count,
+        )
+        .await?
+    } else {
+        None
+    };
+
+    let (total, topics) = match hot {
+        Some(hot) => hot,
+        None => {
+            topic::fetch_pagging(
+                &mut conn,
+                payload.app_id,
+                payload.category,
+                tag_id,
+                payload.solved,
+                topic::VisibleStyle::NORMAL,
+                payload.order_by,
+                payload.window,
+                payload.cursor,
+                payload.count,
+            )
+            .await?
+        }
+    };
 
     let user_map =
         user::get_simple_map_by_ids(&mut conn, topics.iter().map(|s| s.user_id).collect()).await?;
@@ -337,11 +1151,14 @@ async fn topic_list(
         ArcDataMap::new()
     } else {
         let mut connr = redis_connect().await?;
+        warm_bookmarks(&mut conn, &mut *connr, claims.user_id).await?;
 
         fetch_myself(
             &mut *connr,
             claims.user_id,
             topics.iter().map(|s| s.id).collect(),
+            &app.user_reaction_list(),
+            HashMap::new(),
         )
         .await?
     };
@@ -357,7 +1174,7 @@ async fn topic_list(
 
             TopicListItem {
                 user: user_map.get(s.user_id),
-                topic: s.to_simple(),
+                topic: s.to_preview(),
                 myself,
             }
         })
@@ -365,3 +1182,201 @@ async fn topic_list(
 
     Ok(api_success(TopicListResponse { total, topics }))
 }
+
+#[derive(Deserialize)]
+struct TopicPollVotePayload {
+    app_id: u64,
+    topic_id: u64,
+    option_ids: Vec<u64>,
+}
+
+#[derive(Serialize)]
+struct TopicPollVoteResponse {
+    poll: poll::PollSimple,
+    poll_votes: Vec<u64>,
+}
+
+async fn topic_poll_vote(
+    claims: UserClaims,
+    Json(payload): Json<TopicPollVotePayload>,
+) -> Result<ApiSuccess<TopicPollVoteResponse>, ApiError> {
+    if payload.app_id != claims.app_id {
+        return Err(api_error(ApiErrorCode::NoPermission));
+    }
+
+    let mut conn = database_connect().await?;
+
+    let user = user::get_by_id(&mut conn, claims.user_id).await?;
+    if payload.app_id != user.app_id {
+        return Err(api_error(ApiErrorCode::NoPermission));
+    }
+    if !user.is_actived() {
+        return Err(api_error(ApiErrorCode::AccountNotActived));
+    }
+
+    let topic = topic::get_by_id(&mut conn, payload.topic_id).await?;
+    if topic.app_id != payload.app_id {
+        return Err(api_error(ApiErrorCode::NoPermission));
+    }
+    if !topic.is_actived() {
+        return Err(api_error(ApiErrorCode::TopicNotFound));
+    }
+
+    let poll = poll::get_by_topic(&mut conn, topic.id).await?;
+    if poll.is_closed() {
+        return Err(api_error(ApiErrorCode::PollClosed));
+    }
+
+    let mut option_ids = payload.option_ids;
+    option_ids.sort();
+    option_ids.dedup();
+    if option_ids.is_empty() || (!poll.multiple && option_ids.len() > 1) {
+        return Err(api_error2(
+            ApiErrorCode::InvalidParams,
+            "option_ids does not match the poll",
+        ));
+    }
+
+    let options = poll::get_options(&mut conn, poll.id).await?;
+    if option_ids
+        .iter()
+        .any(|id| !options.iter().any(|s| s.id == *id))
+    {
+        return Err(api_error2(
+            ApiErrorCode::InvalidParams,
+            "option_ids does not match the poll",
+        ));
+    }
+
+    if !poll::create_votes(&mut conn, poll.id, user.id, &option_ids).await? {
+        return Err(api_error(ApiErrorCode::PollAlreadyVoted));
+    }
+
+    let poll = poll::get_by_topic(&mut conn, topic.id).await?;
+    let options = poll::get_options(&mut conn, poll.id).await?;
+
+    Ok(api_success(TopicPollVoteResponse {
+        poll: poll.to_simple(options),
+        poll_votes: option_ids,
+    }))
+}
+
+#[derive(Validate, Deserialize)]
+struct TopicPollVotersPayload {
+    app_id: u64,
+    topic_id: u64,
+    option_id: u64,
+    cursor: u32,
+    #[validate(custom = "validate_page_count")]
+    count: u32,
+}
+
+#[derive(Serialize)]
+struct TopicPollVotersResponse {
+    total: u32,
+    users: Vec<Arc<user::UserSimple>>,
+}
+
+async fn topic_poll_voters(
+    claims: Option<UserClaims>,
+    Query(payload): Query<TopicPollVotersPayload>,
+) -> Result<ApiSuccess<TopicPollVotersResponse>, ApiError> {
+    match payload.validate() {
+        Err(e) => return Err(api_errore(ApiErrorCode::InvalidParams, &e)),
+        _ => {}
+    };
+
+    let claims = claims.unwrap_or_default();
+    if claims.app_id != 0 && payload.app_id != claims.app_id {
+        return Err(api_error(ApiErrorCode::NoPermission));
+    }
+
+    let mut conn = database_connect().await?;
+
+    let topic = topic::get_by_id(&mut conn, payload.topic_id).await?;
+    if topic.app_id != payload.app_id {
+        return Err(api_error(ApiErrorCode::NoPermission));
+    }
+    if !topic.is_actived() {
+        return Err(api_error(ApiErrorCode::TopicNotFound));
+    }
+
+    let poll = poll::get_by_topic(&mut conn, topic.id).await?;
+    if poll.anonymous {
+        return Err(api_error(ApiErrorCode::NoPermission));
+    }
+
+    let options = poll::get_options(&mut conn, poll.id).await?;
+    if !options.iter().any(|s| s.id == payload.option_id) {
+        return Err(api_error(ApiErrorCode::PollNotFound));
+    }
+
+    let (total, user_ids) =
+        poll::fetch_voters(&mut conn, payload.option_id, payload.cursor, payload.count).await?;
+
+    let user_map = user::get_simple_map_by_ids(&mut conn, user_ids.clone()).await?;
+    let users = user_ids.into_iter().map(|id| user_map.get(id)).collect();
+
+    Ok(api_success(TopicPollVotersResponse { total, users }))
+}
+
+#[derive(Validate, Deserialize)]
+struct TopicLikersPayload {
+    app_id: u64,
+    topic_id: u64,
+    reaction: Option<String>,
+    cursor: u32,
+    #[validate(custom = "validate_page_count")]
+    count: u32,
+}
+
+#[derive(Serialize)]
+struct TopicLikersResponse {
+    total: u32,
+    users: Vec<reaction::ReactorItem>,
+}
+
+/// Users who liked the topic, or reacted with `reaction`, newest first.
+async fn topic_likers(
+    claims: Option<UserClaims>,
+    Query(payload): Query<TopicLikersPayload>,
+) -> Result<ApiSuccess<TopicLikersResponse>, ApiError> {
+    match payload.validate() {
+        Err(e) => return Err(api_errore(ApiErrorCode::InvalidParams, &e)),
+        _ => {}
+    };
+
+    let claims = claims.unwrap_or_default();
+    if claims.app_id != 0 && payload.app_id != claims.app_id {
+        return Err(api_error(ApiErrorCode::NoPermission));
+    }
+
+    let name = payload
+        .reaction
+        .unwrap_or(String::from(reaction::DEFAULT_REACTION));
+    if !reaction::is_valid_name(&name) {
+        return Err(api_error(ApiErrorCode::InvalidParams));
+    }
+
+    let mut conn = database_connect().await?;
+
+    let topic = topic::get_by_id(&mut conn, payload.topic_id).await?;
+    if payload.app_id != topic.app_id {
+        return Err(api_error(ApiErrorCode::NoPermission));
+    }
+    if !topic.is_actived() {
+        return Err(api_error(ApiErrorCode::TopicNotFound));
+    }
+
+    let (total, users) = reaction::fetch_reactors(
+        &mut conn,
+        reaction::TARGET_TOPIC,
+        topic.id,
+        &name,
+        payload.cursor,
+        payload.count,
+    )
+    .await?;
+
+    Ok(api_success(TopicLikersResponse { total, users }))
+}
@@ -1,9 +1,11 @@
 use axum::{
+    extract::{DefaultBodyLimit, Multipart},
     routing::{get, post},
     Router,
 };
 
 use super::base::*;
+use crate::shared::avatar;
 use crate::shared::data::*;
 use crate::shared::hash::*;
 use crate::shared::model::*;
@@ -19,6 +21,10 @@ pub fn setup_routers() -> Router {
         .route("/login/sync", post(user_sync_login))
         .route("/detail", get(user_detail))
         .route("/update/profile", post(user_update_profile))
+        .route(
+            "/update/avatar",
+            post(user_update_avatar).layer(DefaultBodyLimit::max(avatar::MAX_AVATAR_SIZE)),
+        )
 }
 
 #[derive(Validate, Deserialize)]
@@ -137,3 +143,50 @@ async fn user_update_profile(
 
     Ok(api_success(UserDetailResponse { user }))
 }
+
+#[derive(Serialize)]
+struct UserUpdateAvatarResponse {
+    user: user::UserModel,
+    thumbnails: Vec<avatar::AvatarThumbnail>,
+}
+
+async fn user_update_avatar(
+    claims: UserClaims,
+    mut multipart: Multipart,
+) -> Result<ApiSuccess<UserUpdateAvatarResponse>, ApiError> {
+    let mut data = Vec::new();
+    while let Some(field) = multipart
+        .next_field()
+        .await
+        .map_err(|e| api_errore(ApiErrorCode::InvalidParams, &e))?
+    {
+        if field.name() == Some("file") {
+            data = field
+                .bytes()
+                .await
+                .map_err(|e| api_errore(ApiErrorCode::InvalidParams, &e))?
+                .to_vec();
+        }
+    }
+    if data.is_empty() {
+        return Err(api_error2(ApiErrorCode::InvalidParams, "file is required"));
+    }
+
+    let mut conn = database_connect().await?;
+
+    let mut user = user::get_by_id(&mut conn, claims.user_id).await?;
+    if !user.is_actived() {
+        return Err(api_error(ApiErrorCode::AccountNotActived));
+    }
+
+    let thumbnails = avatar::upload(user.app_id, data).await?;
+
+    let display_name = user.display_name.clone();
+    let avatar_url = thumbnails.first().map(|s| s.url.clone());
+    let gender = user.gender;
+    if user.try_update_profile(display_name, avatar_url, gender) {
+        user::update_profile(&mut conn, &mut user).await?;
+    }
+
+    Ok(api_success(UserUpdateAvatarResponse { user, thumbnails }))
+}
@@ -0,0 +1,97 @@
+use std::io::Cursor;
+
+use image::{imageops::FilterType, DynamicImage, ImageDecoder, ImageFormat, ImageReader};
+
+use super::storage::*;
+use super::web::*;
+
+pub const MAX_AVATAR_SIZE: usize = 10 * 1024 * 1024;
+pub const THUMBNAIL_SIZES: [u32; 3] = [48, 96, 240];
+
+const MAX_AVATAR_PIXELS: u64 = 40_000_000;
+
+#[derive(Debug, Serialize)]
+pub struct AvatarThumbnail {
+    pub size: u32,
+    pub url: String,
+}
+
+/// Decodes an uploaded image and renders square thumbnails for each of
+/// `THUMBNAIL_SIZES`. Re-encoding drops EXIF and any other metadata, the
+/// orientation tag is applied to the pixels first.
+fn render_thumbnails(data: &[u8]) -> Result<(Vec<(u32, Vec<u8>)>, bool), String> {
+    let reader = ImageReader::new(Cursor::new(data))
+        .with_guessed_format()
+        .map_err(|e| e.to_string())?;
+    let mut decoder = reader.into_decoder().map_err(|e| e.to_string())?;
+
+    let (width, height) = decoder.dimensions();
+    if width == 0 || height == 0 || width as u64 * height as u64 > MAX_AVATAR_PIXELS {
+        return Err("image dimensions out of range".to_string());
+    }
+
+    let orientation = decoder.orientation().map_err(|e| e.to_string())?;
+    let mut image = DynamicImage::from_decoder(decoder).map_err(|e| e.to_string())?;
+    image.apply_orientation(orientation);
+
+    let side = image.width().min(image.height());
+    let image = image.crop_imm(
+        (image.width() - side) / 2,
+        (image.height() - side) / 2,
+        side,
+        side,
+    );
+
+    let has_alpha = image.color().has_alpha();
+    let mut out = Vec::new();
+    for size in THUMBNAIL_SIZES {
+        let thumbnail = image.resize_exact(size, size, FilterType::Lanczos3);
+
+        let mut buf = Cursor::new(Vec::new());
+        if has_alpha {
+            thumbnail
+                .to_rgba8()
+                .write_to(&mut buf, ImageFormat::Png)
+                .map_err(|e| e.to_string())?;
+        } else {
+            thumbnail
+                .to_rgb8()
+                .write_to(&mut buf, ImageFormat::Jpeg)
+                .map_err(|e| e.to_string())?;
+        }
+        out.push((size, buf.into_inner()));
+    }
+
+    Ok((out, has_alpha))
+}
+
+/// Stores the thumbnails of an avatar upload, the largest one is returned
+/// first so it can be used as `avatar_url`.
+pub async fn upload(app_id: u64, data: Vec<u8>) -> Result<Vec<AvatarThumbnail>, ApiError> {
+    let (thumbnails, has_alpha) = tokio::task::spawn_blocking(move || render_thumbnails(&data))
+        .await
+        .map_err(|e| api_errore(ApiErrorCode::Unexpected, &e))?
+        .map_err(|e| api_error2(ApiErrorCode::InvalidAvatar, &e))?;
+
+    let (extension, mime_type) = if has_alpha {
+        ("png", "image/png")
+    } else {
+        ("jpg", "image/jpeg")
+    };
+    let base = build_key(app_id, extension);
+    let (stem, _) = base.rsplit_once('.').unwrap_or((&base, ""));
+
+    let mut out = Vec::new();
+    for (size, data) in thumbnails {
+        let key = format!("{}_{}.{}", stem, size, extension);
+        storage().put(&key, data, mime_type).await?;
+
+        out.push(AvatarThumbnail {
+            size,
+            url: storage().url(&key),
+        });
+    }
+    out.reverse();
+
+    Ok(out)
+}
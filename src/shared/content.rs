@@ -0,0 +1,160 @@
+use ammonia::UrlRelative;
+use pulldown_cmark::{html, Event, Options, Parser, TagEnd};
+
+use super::base::*;
+
+pub const FORMAT_PLAIN: i8 = 0;
+pub const FORMAT_MARKDOWN: i8 = 1;
+
+const EXCERPT_CHARS: usize = 200;
+
+static SANITIZER: Lazy<ammonia::Builder<'static>> = Lazy::new(|| {
+    let mut builder = ammonia::Builder::empty();
+    builder
+        .add_tags([
+            "p",
+            "br",
+            "hr",
+            "strong",
+            "em",
+            "del",
+            "code",
+            "pre",
+            "blockquote",
+            "ul",
+            "ol",
+            "li",
+            "a",
+            "h1",
+            "h2",
+            "h3",
+            "h4",
+            "h5",
+            "h6",
+        ])
+        .add_tag_attributes("a", ["href"])
+        .add_url_schemes(["http", "https", "mailto"])
+        .url_relative(UrlRelative::Deny)
+        .link_rel(Some("nofollow noopener noreferrer"));
+    builder
+});
+
+/// Renders the content into sanitized HTML and a plain-text excerpt.
+///
+/// Markdown is limited to the CommonMark core plus strikethrough, raw HTML
+/// is escaped and anything outside the allowed tags is stripped.
+pub fn render(format: i8, content: &str) -> (String, String) {
+    if format != FORMAT_MARKDOWN {
+        return (render_plain(content), build_excerpt(content));
+    }
+
+    let events: Vec<Event> = Parser::new_ext(content, Options::ENABLE_STRIKETHROUGH)
+        .map(|e| match e {
+            Event::Html(s) | Event::InlineHtml(s) => Event::Text(s),
+            _ => e,
+        })
+        .collect();
+
+    let mut text = String::new();
+    for e in events.iter() {
+        match e {
+            Event::Text(s) | Event::Code(s) => text.push_str(s),
+            Event::SoftBreak | Event::HardBreak | Event::Rule => text.push(' '),
+            Event::End(TagEnd::Paragraph | TagEnd::Heading(_) | TagEnd::Item) => text.push(' '),
+            Event::End(TagEnd::CodeBlock | TagEnd::BlockQuote(_)) => text.push(' '),
+            _ => {}
+        }
+    }
+
+    let mut out = String::new();
+    html::push_html(&mut out, events.into_iter());
+
+    (SANITIZER.clean(&out).to_string(), build_excerpt(&text))
+}
+
+fn escape_html(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+fn render_plain(content: &str) -> String {
+    let mut out = String::new();
+    let content = content.replace("\r\n", "\n");
+    for block in content.split("\n\n") {
+        let block = block.trim();
+        if block.is_empty() {
+            continue;
+        }
+        out.push_str("<p>");
+        out.push_str(&escape_html(block).replace('\n', "<br>"));
+        out.push_str("</p>");
+    }
+    out
+}
+
+fn build_excerpt(text: &str) -> String {
+    let words: Vec<&str> = text.split_whitespace().collect();
+    let text = words.join(" ");
+
+    let mut out: String = text.chars().take(EXCERPT_CHARS).collect();
+    if out.len() < text.len() {
+        out.push('…');
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn render_escapes_raw_html() {
+        let (html, excerpt) = render(
+            FORMAT_MARKDOWN,
+            "hi <script>alert(1)</script>\n\n<img src=x onerror=alert(1)>",
+        );
+        assert!(!html.contains("<script"));
+        assert!(!html.contains("<img"));
+        assert!(html.contains("&lt;script&gt;"));
+        assert!(!excerpt.contains('\n'));
+
+        let (html, _) = render(FORMAT_PLAIN, "a <b>\nc & 'd'");
+        assert_eq!(html, "<p>a &lt;b&gt;<br>c &amp; &#39;d&#39;</p>");
+    }
+
+    #[test]
+    fn render_drops_unsafe_links() {
+        let (html, _) = render(FORMAT_MARKDOWN, "[x](javascript:alert(1)) [y](/relative)");
+        assert!(!html.contains("javascript:"));
+        assert!(!html.contains("href=\"/relative\""));
+
+        let (html, _) = render(FORMAT_MARKDOWN, "[ok](https://example.com)");
+        assert!(html.contains("href=\"https://example.com\""));
+        assert!(html.contains("rel=\"nofollow noopener noreferrer\""));
+    }
+
+    #[test]
+    fn render_caps_excerpt() {
+        let (_, excerpt) = render(FORMAT_MARKDOWN, "# Title\n\n**bold**   text");
+        assert_eq!(excerpt, "Title bold text");
+
+        let content = "话".repeat(EXCERPT_CHARS + 10);
+        let (_, excerpt) = render(FORMAT_PLAIN, &content);
+        assert_eq!(excerpt.chars().count(), EXCERPT_CHARS + 1);
+        assert!(excerpt.ends_with('…'));
+
+        let content = "a".repeat(EXCERPT_CHARS);
+        let (_, excerpt) = render(FORMAT_PLAIN, &content);
+        assert_eq!(excerpt, content);
+    }
+}
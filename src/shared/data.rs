@@ -19,6 +19,16 @@ pub fn init() {
 
 pub type SqlDateTime = chrono::NaiveDateTime;
 pub type SqlConnection = sqlx::pool::PoolConnection<sqlx::MySql>;
+/// What pooled connections and transactions both deref to, taken by model
+/// functions that may run inside a transaction.
+pub type SqlRawConnection = sqlx::MySqlConnection;
+pub type SqlTransaction<'a> = sqlx::Transaction<'a, sqlx::MySql>;
+
+pub fn sql_datetime(ts: i64) -> SqlDateTime {
+    chrono::DateTime::from_timestamp(ts, 0)
+        .unwrap_or_default()
+        .naive_utc()
+}
 
 static MYSQL_POOL: Lazy<MySqlPool> = Lazy::new(|| {
     let connection_str = std::env::var("MYSQL_URL").expect("MYSQL_URL must be set");
@@ -41,6 +51,18 @@ pub async fn database_connect() -> Result<SqlConnection, ApiError> {
         .map_err(|e| api_errore(ApiErrorCode::InvalidDatabase, &e))
 }
 
+pub async fn database_begin(conn: &mut SqlConnection) -> Result<SqlTransaction<'_>, ApiError> {
+    sqlx::Connection::begin(&mut **conn)
+        .await
+        .map_err(|e| api_errore(ApiErrorCode::InvalidDatabase, &e))
+}
+
+pub async fn database_commit(tx: SqlTransaction<'_>) -> Result<(), ApiError> {
+    tx.commit()
+        .await
+        .map_err(|e| api_errore(ApiErrorCode::InvalidDatabase, &e))
+}
+
 pub use redis::aio::ConnectionLike as RedisConnectionLike;
 pub type RedisConnection = bb8::PooledConnection<'static, RedisConnectionManager>;
 
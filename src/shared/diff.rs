@@ -0,0 +1,23 @@
+use similar::{ChangeTag, TextDiff};
+
+use super::web::*;
+
+#[derive(Debug, Serialize)]
+pub struct DiffLine {
+    pub tag: &'static str,
+    pub text: String,
+}
+
+pub fn diff_lines(old: &str, new: &str) -> Vec<DiffLine> {
+    TextDiff::from_lines(old, new)
+        .iter_all_changes()
+        .map(|c| DiffLine {
+            tag: match c.tag() {
+                ChangeTag::Equal => "equal",
+                ChangeTag::Insert => "insert",
+                ChangeTag::Delete => "delete",
+            },
+            text: c.to_string(),
+        })
+        .collect()
+}
@@ -0,0 +1,169 @@
+use super::data::*;
+use super::model::*;
+use super::web::*;
+
+/// Tells the users mentioned in the content, except those in `told`.
+async fn notify_mentions(
+    conn: &mut SqlConnection,
+    topic: &topic::TopicModel,
+    reply_id: u64,
+    content: &str,
+    actor_id: u64,
+    told: Vec<u64>,
+) -> Result<(), ApiError> {
+    let names = user::extract_mentions(content);
+    for user_id in user::get_ids_by_mentions(conn, topic.app_id, &names).await? {
+        if told.contains(&user_id) {
+            continue;
+        }
+
+        let notification = notification::NotificationModel {
+            app_id: topic.app_id,
+            user_id,
+            kind: notification::KIND_MENTIONED,
+            topic_id: topic.id,
+            reply_id,
+            actor_id,
+            content: topic.title.clone(),
+            ..Default::default()
+        };
+        notification::create(conn, &notification).await?;
+    }
+
+    Ok(())
+}
+
+/// Notifications never fail the request that caused them, errors are only
+/// logged.
+fn log_failure(name: &str, res: Result<(), ApiError>) {
+    if let Err(e) = res {
+        tracing::warn!("{} failed: {}", name, e);
+    }
+}
+
+/// Tells the users mentioned in a topic once it is visible.
+pub async fn notify_topic(conn: &mut SqlConnection, topic: &topic::TopicModel) {
+    log_failure("notify_topic", send_topic(conn, topic).await);
+}
+
+async fn send_topic(conn: &mut SqlConnection, topic: &topic::TopicModel) -> Result<(), ApiError> {
+    notify_mentions(
+        conn,
+        topic,
+        0,
+        &topic.content,
+        topic.user_id,
+        vec![topic.user_id],
+    )
+    .await
+}
+
+/// Tells the author of the replied reply, the topic author and the users
+/// mentioned about a new reply, each once. Nobody hears about their own reply.
+pub async fn notify_reply(
+    conn: &mut SqlConnection,
+    topic: &topic::TopicModel,
+    reply: &reply::ReplyModel,
+) {
+    log_failure("notify_reply", send_reply(conn, topic, reply).await);
+}
+
+async fn send_reply(
+    conn: &mut SqlConnection,
+    topic: &topic::TopicModel,
+    reply: &reply::ReplyModel,
+) -> Result<(), ApiError> {
+    let mut told = vec![reply.user_id];
+
+    if reply.parent_reply_id > 0 {
+        let parent = reply::get_by_id(conn, reply.parent_reply_id).await?;
+        if !told.contains(&parent.user_id) {
+            let notification = notification::NotificationModel {
+                app_id: topic.app_id,
+                user_id: parent.user_id,
+                kind: notification::KIND_REPLY_REPLIED,
+                topic_id: topic.id,
+                reply_id: reply.id,
+                actor_id: reply.user_id,
+                content: topic.title.clone(),
+                ..Default::default()
+            };
+            notification::create(conn, &notification).await?;
+            told.push(parent.user_id);
+        }
+    }
+
+    if !told.contains(&topic.user_id) {
+        let notification = notification::NotificationModel {
+            app_id: topic.app_id,
+            user_id: topic.user_id,
+            kind: notification::KIND_TOPIC_REPLIED,
+            topic_id: topic.id,
+            reply_id: reply.id,
+            actor_id: reply.user_id,
+            content: topic.title.clone(),
+            ..Default::default()
+        };
+        notification::create_or_aggregate(conn, &notification).await?;
+        told.push(topic.user_id);
+    }
+
+    notify_mentions(conn, topic, reply.id, &reply.content, reply.user_id, told).await
+}
+
+/// Tells the author about a like on their topic, or on their reply when
+/// `reply` is given.
+pub async fn notify_like(
+    conn: &mut SqlConnection,
+    topic: &topic::TopicModel,
+    reply: Option<&reply::ReplyModel>,
+    actor_id: u64,
+) {
+    log_failure("notify_like", send_like(conn, topic, reply, actor_id).await);
+}
+
+async fn send_like(
+    conn: &mut SqlConnection,
+    topic: &topic::TopicModel,
+    reply: Option<&reply::ReplyModel>,
+    actor_id: u64,
+) -> Result<(), ApiError> {
+    let (user_id, kind, reply_id) = match reply {
+        Some(reply) => (reply.user_id, notification::KIND_REPLY_LIKED, reply.id),
+        None => (topic.user_id, notification::KIND_TOPIC_LIKED, 0),
+    };
+    if user_id == actor_id {
+        return Ok(());
+    }
+
+    let notification = notification::NotificationModel {
+        app_id: topic.app_id,
+        user_id,
+        kind,
+        topic_id: topic.id,
+        reply_id,
+        actor_id,
+        content: topic.title.clone(),
+        ..Default::default()
+    };
+    notification::create_or_aggregate(conn, &notification).await
+}
+
+/// Tells the author their topic was moved to another category.
+pub async fn notify_moved(conn: &mut SqlConnection, topic: &topic::TopicModel) {
+    log_failure("notify_moved", send_moved(conn, topic).await);
+}
+
+async fn send_moved(conn: &mut SqlConnection, topic: &topic::TopicModel) -> Result<(), ApiError> {
+    let notification = notification::NotificationModel {
+        app_id: topic.app_id,
+        user_id: topic.user_id,
+        kind: notification::KIND_TOPIC_MOVED,
+        topic_id: topic.id,
+        content: topic.title.clone(),
+        ..Default::default()
+    };
+    notification::create(conn, &notification).await?;
+
+    Ok(())
+}
@@ -1,12 +1,24 @@
+pub mod avatar;
 pub mod base;
+pub mod content;
 pub mod data;
+pub mod diff;
 pub mod hash;
+pub mod inbox;
 pub mod jwt;
 pub mod model;
+pub mod ranking;
+pub mod reaction;
+pub mod scheduler;
+pub mod search;
+pub mod storage;
+pub mod view;
 pub mod web;
 
 pub fn init() {
     jwt::init();
     data::init();
+    search::init();
+    storage::init();
     web::init();
 }
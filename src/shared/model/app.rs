@@ -1,7 +1,11 @@
 use crate::shared::base::*;
 use crate::shared::data::*;
+use crate::shared::reaction::{DEFAULT_REACTION, DOWNVOTE_REACTION};
 use crate::shared::web::*;
 
+const DEFAULT_UPLOAD_MAX_SIZE: u32 = 5 * 1024 * 1024;
+const DEFAULT_UPLOAD_MIME_TYPES: &'static str = "image/jpeg,image/png,image/gif,image/webp";
+
 #[derive(Debug, Serialize, sqlx::FromRow)]
 pub struct AppModel {
     pub id: u64,
@@ -9,6 +13,14 @@ pub struct AppModel {
     pub app_secret: String,
     pub name: String,
     pub icon_url: Option<String>,
+    pub edit_window: u32,
+    pub upload_max_size: u32,
+    pub upload_mime_types: String,
+    pub author_lock: bool,
+    pub auto_lock_days: u32,
+    pub reaction_types: String,
+    pub downvote_enabled: bool,
+    pub qa_categories: String,
     pub created_at: SqlDateTime,
 }
 
@@ -18,6 +30,14 @@ pub struct AppSimple {
     pub app_key: String,
     pub name: String,
     pub icon_url: Option<String>,
+    pub edit_window: u32,
+    pub upload_max_size: u32,
+    pub upload_mime_types: String,
+    pub author_lock: bool,
+    pub auto_lock_days: u32,
+    pub reaction_types: String,
+    pub downvote_enabled: bool,
+    pub qa_categories: String,
 }
 
 impl AppModel {
@@ -27,7 +47,72 @@ impl AppModel {
             app_key: self.app_key.clone(),
             name: self.name.clone(),
             icon_url: self.icon_url.clone(),
+            edit_window: self.edit_window,
+            upload_max_size: self.upload_max_size,
+            upload_mime_types: self.upload_mime_types.clone(),
+            author_lock: self.author_lock,
+            auto_lock_days: self.auto_lock_days,
+            reaction_types: self.reaction_types.clone(),
+            downvote_enabled: self.downvote_enabled,
+            qa_categories: self.qa_categories.clone(),
+        }
+    }
+
+    pub fn is_editable(self: &Self, created_at: SqlDateTime) -> bool {
+        if self.edit_window == 0 {
+            return false;
+        }
+
+        return created_at.and_utc().timestamp() + self.edit_window as i64 >= timestamp();
+    }
+
+    pub fn is_auto_locked(self: &Self, refreshed_at: SqlDateTime) -> bool {
+        if self.auto_lock_days == 0 {
+            return false;
+        }
+
+        return refreshed_at.and_utc().timestamp() + self.auto_lock_days as i64 * 86400
+            < timestamp();
+    }
+
+    /// Reaction types of the app, the default reaction always comes first.
+    pub fn reaction_list(self: &Self) -> Vec<String> {
+        let mut out = vec![String::from(DEFAULT_REACTION)];
+        for s in self.reaction_types.split(',').map(|s| s.trim()) {
+            if !s.is_empty() && !out.iter().any(|o| o == s) {
+                out.push(String::from(s));
+            }
+        }
+        out
+    }
+
+    /// Reactions kept for each user, the reaction types plus downvotes when
+    /// the app enables them.
+    pub fn user_reaction_list(self: &Self) -> Vec<String> {
+        let mut out = self.reaction_list();
+        if self.downvote_enabled {
+            out.push(String::from(DOWNVOTE_REACTION));
         }
+        out
+    }
+
+    pub fn is_reaction_allowed(self: &Self, reaction: &str) -> bool {
+        self.user_reaction_list().iter().any(|s| s == reaction)
+    }
+
+    /// Whether answers can be accepted in the category, `0` in
+    /// `qa_categories` stands for every category.
+    pub fn is_qa_category(self: &Self, category: u64) -> bool {
+        self.qa_categories
+            .split(',')
+            .filter_map(|s| s.trim().parse::<u64>().ok())
+            .any(|c| c == 0 || c == category)
+    }
+
+    pub fn is_upload_allowed(self: &Self, mime_type: &str) -> bool {
+        self.upload_mime_types
+            .split(',')
+            .any(|s| s.trim().eq_ignore_ascii_case(mime_type))
     }
 }
 
@@ -39,6 +124,14 @@ impl Default for AppModel {
             app_secret: String::new(),
             name: String::new(),
             icon_url: None,
+            edit_window: 0,
+            upload_max_size: DEFAULT_UPLOAD_MAX_SIZE,
+            upload_mime_types: String::from(DEFAULT_UPLOAD_MIME_TYPES),
+            author_lock: false,
+            auto_lock_days: 0,
+            reaction_types: String::from(DEFAULT_REACTION),
+            downvote_enabled: false,
+            qa_categories: String::new(),
             created_at: SqlDateTime::MIN,
         }
     }
@@ -95,6 +188,26 @@ pub async fn create(conn: &mut SqlConnection, app: AppModel) -> Result<u64, ApiE
     Ok(res.last_insert_id())
 }
 
+pub async fn update_setting(conn: &mut SqlConnection, app: &AppModel) -> Result<(), ApiError> {
+    sqlx::query(
+        "update dg_apps set edit_window=?,upload_max_size=?,upload_mime_types=?,author_lock=?,auto_lock_days=?,reaction_types=?,downvote_enabled=?,qa_categories=? where id=?",
+    )
+    .bind(app.edit_window)
+    .bind(app.upload_max_size)
+    .bind(&app.upload_mime_types)
+    .bind(app.author_lock)
+    .bind(app.auto_lock_days)
+    .bind(&app.reaction_types)
+    .bind(app.downvote_enabled)
+    .bind(&app.qa_categories)
+    .bind(app.id)
+    .execute(conn)
+    .await
+    .map_err(|e| api_errore(ApiErrorCode::InvalidDatabase, &e))?;
+
+    Ok(())
+}
+
 pub async fn fetch_pagging(
     conn: &mut SqlConnection,
     cursor: u32,
@@ -114,3 +227,12 @@ pub async fn fetch_pagging(
 
     Ok((total.0 as u32, res))
 }
+
+pub async fn get_all_ids(conn: &mut SqlConnection) -> Result<Vec<u64>, ApiError> {
+    let res: Vec<(u64,)> = sqlx::query_as("select id from dg_apps order by id")
+        .fetch_all(conn)
+        .await
+        .map_err(|e| api_errore(ApiErrorCode::InvalidDatabase, &e))?;
+
+    Ok(res.into_iter().map(|s| s.0).collect())
+}
@@ -0,0 +1,227 @@
+use crate::shared::base::*;
+use crate::shared::data::*;
+use crate::shared::storage::*;
+use crate::shared::web::*;
+
+pub const TARGET_NONE: i8 = 0;
+pub const TARGET_TOPIC: i8 = 1;
+pub const TARGET_REPLY: i8 = 2;
+
+pub const MAX_TARGET_ATTACHMENTS: usize = 9;
+pub const MAX_UPLOAD_SIZE: usize = 32 * 1024 * 1024;
+pub const UNBOUND_TTL: u32 = 24 * 3600;
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct AttachmentModel {
+    pub id: u64,
+    pub app_id: u64,
+    pub user_id: u64,
+    pub target_type: i8,
+    pub target_id: u64,
+    pub storage_key: String,
+    pub file_name: String,
+    pub mime_type: String,
+    pub size: u32,
+    pub created_at: SqlDateTime,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AttachmentSimple {
+    pub id: u64,
+    pub url: String,
+    pub file_name: String,
+    pub mime_type: String,
+    pub size: u32,
+}
+
+impl AttachmentModel {
+    pub fn to_simple(self: &Self) -> AttachmentSimple {
+        AttachmentSimple {
+            id: self.id,
+            url: storage().url(&self.storage_key),
+            file_name: self.file_name.clone(),
+            mime_type: self.mime_type.clone(),
+            size: self.size,
+        }
+    }
+}
+
+impl Default for AttachmentModel {
+    fn default() -> AttachmentModel {
+        AttachmentModel {
+            id: 0,
+            app_id: 0,
+            user_id: 0,
+            target_type: TARGET_NONE,
+            target_id: 0,
+            storage_key: String::new(),
+            file_name: String::new(),
+            mime_type: String::new(),
+            size: 0,
+            created_at: SqlDateTime::MIN,
+        }
+    }
+}
+
+pub async fn create(
+    conn: &mut SqlConnection,
+    attachment: &mut AttachmentModel,
+) -> Result<u64, ApiError> {
+    let res = sqlx::query(
+        "insert into dg_attachments(app_id,user_id,target_type,target_id,storage_key,file_name,mime_type,size) values(?,?,?,?,?,?,?,?)",
+    )
+    .bind(attachment.app_id)
+    .bind(attachment.user_id)
+    .bind(attachment.target_type)
+    .bind(attachment.target_id)
+    .bind(&attachment.storage_key)
+    .bind(&attachment.file_name)
+    .bind(&attachment.mime_type)
+    .bind(attachment.size)
+    .execute(conn)
+    .await
+    .map_err(|e| api_errore(ApiErrorCode::InvalidDatabase, &e))?;
+
+    Ok(res.last_insert_id())
+}
+
+/// Attaches the user's unbound uploads to a topic or reply, ids that are
+/// not the user's or already bound are ignored.
+pub async fn bind_target(
+    conn: &mut SqlConnection,
+    user_id: u64,
+    target_type: i8,
+    target_id: u64,
+    ids: Option<Vec<u64>>,
+) -> Result<(), ApiError> {
+    let ids = ids.unwrap_or_default();
+    if ids.is_empty() {
+        return Ok(());
+    }
+    if ids.len() > MAX_TARGET_ATTACHMENTS {
+        return Err(api_error2(
+            ApiErrorCode::InvalidParams,
+            "too many attachments",
+        ));
+    }
+
+    let ids_str = ids
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join(",");
+    sqlx::query(&format!(
+        "update dg_attachments set target_type=?,target_id=? where user_id=? and target_id=0 and id in ({})",
+        ids_str
+    ))
+    .bind(target_type)
+    .bind(target_id)
+    .bind(user_id)
+    .execute(conn)
+    .await
+    .map_err(|e| api_errore(ApiErrorCode::InvalidDatabase, &e))?;
+
+    Ok(())
+}
+
+pub async fn get_simple_list_by_target(
+    conn: &mut SqlConnection,
+    target_type: i8,
+    target_id: u64,
+) -> Result<Vec<AttachmentSimple>, ApiError> {
+    let res = sqlx::query_as::<_, AttachmentModel>(
+        "select * from dg_attachments where target_type=? and target_id=? order by id",
+    )
+    .bind(target_type)
+    .bind(target_id)
+    .fetch_all(conn)
+    .await
+    .map_err(|e| api_errore(ApiErrorCode::InvalidDatabase, &e))?;
+
+    Ok(res.iter().map(|s| s.to_simple()).collect())
+}
+
+pub async fn get_simple_map_by_targets(
+    conn: &mut SqlConnection,
+    target_type: i8,
+    target_ids: Vec<u64>,
+) -> Result<ArcDataMap<u64, Vec<AttachmentSimple>>, ApiError> {
+    let mut out = ArcDataMap::new();
+    if target_ids.len() < 1 {
+        return Ok(out);
+    }
+
+    let ids_str = target_ids
+        .iter()
+        .map(ToString::to_string)
+        .collect::<HashSet<_>>()
+        .into_iter()
+        .collect::<Vec<_>>()
+        .join(",");
+    let res = sqlx::query_as::<_, AttachmentModel>(&format!(
+        "select * from dg_attachments where target_type=? and target_id in ({}) order by id",
+        ids_str
+    ))
+    .bind(target_type)
+    .fetch_all(conn)
+    .await
+    .map_err(|e| api_errore(ApiErrorCode::InvalidDatabase, &e))?;
+
+    let mut groups: HashMap<u64, Vec<AttachmentSimple>> = HashMap::new();
+    for o in res {
+        groups.entry(o.target_id).or_default().push(o.to_simple());
+    }
+    for (k, v) in groups {
+        out.insert(k, v);
+    }
+
+    Ok(out)
+}
+
+async fn purge(
+    conn: &mut SqlConnection,
+    attachments: Vec<AttachmentModel>,
+) -> Result<u32, ApiError> {
+    for o in attachments.iter() {
+        storage().delete(&o.storage_key).await?;
+
+        sqlx::query("delete from dg_attachments where id=?")
+            .bind(o.id)
+            .execute(conn.as_mut())
+            .await
+            .map_err(|e| api_errore(ApiErrorCode::InvalidDatabase, &e))?;
+    }
+
+    Ok(attachments.len() as u32)
+}
+
+/// Removes the files of a deleted topic or reply.
+pub async fn purge_target(
+    conn: &mut SqlConnection,
+    target_type: i8,
+    target_id: u64,
+) -> Result<u32, ApiError> {
+    let res = sqlx::query_as::<_, AttachmentModel>(
+        "select * from dg_attachments where target_type=? and target_id=?",
+    )
+    .bind(target_type)
+    .bind(target_id)
+    .fetch_all(conn.as_mut())
+    .await
+    .map_err(|e| api_errore(ApiErrorCode::InvalidDatabase, &e))?;
+
+    purge(conn, res).await
+}
+
+/// Removes uploads that were never attached to anything within `ttl` seconds.
+pub async fn purge_unbound(conn: &mut SqlConnection, ttl: u32) -> Result<u32, ApiError> {
+    let res = sqlx::query_as::<_, AttachmentModel>(
+        "select * from dg_attachments where target_id=0 and created_at<DATE_SUB(NOW(),INTERVAL ? SECOND) limit 1000",
+    )
+    .bind(ttl)
+    .fetch_all(conn.as_mut())
+    .await
+    .map_err(|e| api_errore(ApiErrorCode::InvalidDatabase, &e))?;
+
+    purge(conn, res).await
+}
@@ -0,0 +1,73 @@
+use crate::shared::data::*;
+use crate::shared::web::*;
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct BookmarkModel {
+    pub user_id: u64,
+    pub topic_id: u64,
+    pub app_id: u64,
+    pub created_at: SqlDateTime,
+}
+
+/// Returns false when the topic was already bookmarked.
+pub async fn create(
+    conn: &mut SqlConnection,
+    app_id: u64,
+    user_id: u64,
+    topic_id: u64,
+) -> Result<bool, ApiError> {
+    let res = sqlx::query("insert ignore into dg_bookmarks(user_id,topic_id,app_id) values(?,?,?)")
+        .bind(user_id)
+        .bind(topic_id)
+        .bind(app_id)
+        .execute(conn)
+        .await
+        .map_err(|e| api_errore(ApiErrorCode::InvalidDatabase, &e))?;
+
+    Ok(res.rows_affected() > 0)
+}
+
+/// Returns false when the topic was not bookmarked.
+pub async fn delete(
+    conn: &mut SqlConnection,
+    user_id: u64,
+    topic_id: u64,
+) -> Result<bool, ApiError> {
+    let res = sqlx::query("delete from dg_bookmarks where user_id=? and topic_id=?")
+        .bind(user_id)
+        .bind(topic_id)
+        .execute(conn)
+        .await
+        .map_err(|e| api_errore(ApiErrorCode::InvalidDatabase, &e))?;
+
+    Ok(res.rows_affected() > 0)
+}
+
+pub async fn get_all_by_user(
+    conn: &mut SqlConnection,
+    user_id: u64,
+) -> Result<Vec<BookmarkModel>, ApiError> {
+    sqlx::query_as::<_, BookmarkModel>("select * from dg_bookmarks where user_id=?")
+        .bind(user_id)
+        .fetch_all(conn)
+        .await
+        .map_err(|e| api_errore(ApiErrorCode::InvalidDatabase, &e))
+}
+
+/// Bookmarks of the user whose topics are still visible.
+pub async fn count_visible(
+    conn: &mut SqlConnection,
+    app_id: u64,
+    user_id: u64,
+) -> Result<u32, ApiError> {
+    let total: (i64,) = sqlx::query_as(
+        "select count(*) from dg_bookmarks b join dg_topics t on t.id=b.topic_id where b.user_id=? and b.app_id=? and t.topped>=0",
+    )
+    .bind(user_id)
+    .bind(app_id)
+    .fetch_one(conn)
+    .await
+    .map_err(|e| api_errore(ApiErrorCode::InvalidDatabase, &e))?;
+
+    Ok(total.0 as u32)
+}
@@ -0,0 +1,227 @@
+use crate::shared::content;
+use crate::shared::data::*;
+use crate::shared::web::*;
+
+pub const TARGET_TOPIC: i8 = 1;
+pub const TARGET_REPLY: i8 = 2;
+
+pub const MAX_USER_DRAFTS: u32 = 50;
+/// Drafts untouched for this many days are removed.
+pub const EXPIRE_DAYS: u32 = 30;
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct DraftModel {
+    pub id: u64,
+    pub app_id: u64,
+    pub user_id: u64,
+    pub target_type: i8,
+    pub topic_id: u64,
+    pub parent_reply_id: u64,
+    pub quote_reply_id: u64,
+    pub category: u64,
+    pub title: String,
+    pub content_format: i8,
+    pub content: String,
+    pub tags: String,
+    pub created_at: SqlDateTime,
+    pub updated_at: SqlDateTime,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DraftSimple {
+    pub id: u64,
+    pub target_type: i8,
+    pub topic_id: u64,
+    pub parent_reply_id: u64,
+    pub quote_reply_id: u64,
+    pub category: u64,
+    pub title: String,
+    pub content_format: i8,
+    pub content: String,
+    pub tags: Vec<String>,
+    pub created_at: SqlDateTime,
+    pub updated_at: SqlDateTime,
+}
+
+impl DraftModel {
+    pub fn to_simple(self: &Self) -> DraftSimple {
+        DraftSimple {
+            id: self.id,
+            target_type: self.target_type,
+            topic_id: self.topic_id,
+            parent_reply_id: self.parent_reply_id,
+            quote_reply_id: self.quote_reply_id,
+            category: self.category,
+            title: self.title.clone(),
+            content_format: self.content_format,
+            content: self.content.clone(),
+            tags: self.tag_list(),
+            created_at: self.created_at,
+            updated_at: self.updated_at,
+        }
+    }
+
+    pub fn tag_list(self: &Self) -> Vec<String> {
+        self.tags
+            .split(',')
+            .filter(|s| !s.is_empty())
+            .map(String::from)
+            .collect()
+    }
+}
+
+impl Default for DraftModel {
+    fn default() -> DraftModel {
+        DraftModel {
+            id: 0,
+            app_id: 0,
+            user_id: 0,
+            target_type: TARGET_TOPIC,
+            topic_id: 0,
+            parent_reply_id: 0,
+            quote_reply_id: 0,
+            category: 0,
+            title: String::new(),
+            content_format: content::FORMAT_PLAIN,
+            content: String::new(),
+            tags: String::new(),
+            created_at: SqlDateTime::MIN,
+            updated_at: SqlDateTime::MIN,
+        }
+    }
+}
+
+pub async fn get_by_id(conn: &mut SqlConnection, id: u64) -> Result<DraftModel, ApiError> {
+    let res = sqlx::query_as::<_, DraftModel>("select * from dg_drafts where id=?")
+        .bind(id)
+        .fetch_optional(conn)
+        .await
+        .map_err(|e| api_errore(ApiErrorCode::InvalidDatabase, &e))?;
+
+    if res.is_none() {
+        return Err(api_error(ApiErrorCode::DraftNotFound));
+    }
+
+    Ok(res.unwrap())
+}
+
+pub async fn count_by_user(conn: &mut SqlConnection, user_id: u64) -> Result<u32, ApiError> {
+    let total: (i64,) = sqlx::query_as("select count(*) from dg_drafts where user_id=?")
+        .bind(user_id)
+        .fetch_one(conn)
+        .await
+        .map_err(|e| api_errore(ApiErrorCode::InvalidDatabase, &e))?;
+
+    Ok(total.0 as u32)
+}
+
+pub async fn create(conn: &mut SqlConnection, draft: &DraftModel) -> Result<u64, ApiError> {
+    let res = sqlx::query(
+        "insert into dg_drafts(app_id,user_id,target_type,topic_id,parent_reply_id,quote_reply_id,category,title,content_format,content,tags,updated_at) values(?,?,?,?,?,?,?,?,?,?,?,NOW())",
+    )
+    .bind(draft.app_id)
+    .bind(draft.user_id)
+    .bind(draft.target_type)
+    .bind(draft.topic_id)
+    .bind(draft.parent_reply_id)
+    .bind(draft.quote_reply_id)
+    .bind(draft.category)
+    .bind(&draft.title)
+    .bind(draft.content_format)
+    .bind(&draft.content)
+    .bind(&draft.tags)
+    .execute(conn)
+    .await
+    .map_err(|e| api_errore(ApiErrorCode::InvalidDatabase, &e))?;
+
+    Ok(res.last_insert_id())
+}
+
+pub async fn update(conn: &mut SqlConnection, draft: &DraftModel) -> Result<(), ApiError> {
+    sqlx::query(
+        "update dg_drafts set category=?,title=?,content_format=?,content=?,tags=?,updated_at=NOW() where id=?",
+    )
+    .bind(draft.category)
+    .bind(&draft.title)
+    .bind(draft.content_format)
+    .bind(&draft.content)
+    .bind(&draft.tags)
+    .bind(draft.id)
+    .execute(conn)
+    .await
+    .map_err(|e| api_errore(ApiErrorCode::InvalidDatabase, &e))?;
+
+    Ok(())
+}
+
+/// Returns false when the draft was gone already.
+pub async fn delete(conn: &mut SqlConnection, id: u64) -> Result<bool, ApiError> {
+    let res = sqlx::query("delete from dg_drafts where id=?")
+        .bind(id)
+        .execute(conn)
+        .await
+        .map_err(|e| api_errore(ApiErrorCode::InvalidDatabase, &e))?;
+
+    Ok(res.rows_affected() > 0)
+}
+
+/// Puts a deleted draft back as it was, id and times included.
+pub async fn restore(conn: &mut SqlConnection, draft: &DraftModel) -> Result<(), ApiError> {
+    sqlx::query(
+        "insert ignore into dg_drafts(id,app_id,user_id,target_type,topic_id,parent_reply_id,quote_reply_id,category,title,content_format,content,tags,created_at,updated_at) values(?,?,?,?,?,?,?,?,?,?,?,?,?,?)",
+    )
+    .bind(draft.id)
+    .bind(draft.app_id)
+    .bind(draft.user_id)
+    .bind(draft.target_type)
+    .bind(draft.topic_id)
+    .bind(draft.parent_reply_id)
+    .bind(draft.quote_reply_id)
+    .bind(draft.category)
+    .bind(&draft.title)
+    .bind(draft.content_format)
+    .bind(&draft.content)
+    .bind(&draft.tags)
+    .bind(draft.created_at)
+    .bind(draft.updated_at)
+    .execute(conn)
+    .await
+    .map_err(|e| api_errore(ApiErrorCode::InvalidDatabase, &e))?;
+
+    Ok(())
+}
+
+pub async fn purge_expired(conn: &mut SqlConnection) -> Result<u64, ApiError> {
+    let res = sqlx::query("delete from dg_drafts where updated_at<DATE_SUB(NOW(),INTERVAL ? DAY)")
+        .bind(EXPIRE_DAYS)
+        .execute(conn)
+        .await
+        .map_err(|e| api_errore(ApiErrorCode::InvalidDatabase, &e))?;
+
+    Ok(res.rows_affected())
+}
+
+pub async fn fetch_pagging(
+    conn: &mut SqlConnection,
+    user_id: u64,
+    cursor: u32,
+    count: u32,
+) -> Result<(u32, Vec<DraftModel>), ApiError> {
+    let res = sqlx::query_as::<_, DraftModel>(
+        "select * from dg_drafts where user_id=? order by updated_at desc limit ?,?",
+    )
+    .bind(user_id)
+    .bind(cursor)
+    .bind(count)
+    .fetch_all(conn.as_mut())
+    .await
+    .map_err(|e| api_errore(ApiErrorCode::InvalidDatabase, &e))?;
+
+    let total: (i64,) = sqlx::query_as("select count(*) from dg_drafts where user_id=?")
+        .bind(user_id)
+        .fetch_one(conn.as_mut())
+        .await
+        .map_err(|e| api_errore(ApiErrorCode::InvalidDatabase, &e))?;
+
+    Ok((total.0 as u32, res))
+}
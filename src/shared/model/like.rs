@@ -0,0 +1,106 @@
+use crate::shared::data::*;
+use crate::shared::web::*;
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct LikeModel {
+    pub id: u64,
+    pub app_id: u64,
+    pub target_type: i8,
+    pub target_id: u64,
+    pub user_id: u64,
+    pub reaction: String,
+    pub created_at: SqlDateTime,
+}
+
+/// Returns false when the user already reacted so.
+pub async fn create(
+    conn: &mut SqlConnection,
+    app_id: u64,
+    target_type: i8,
+    target_id: u64,
+    user_id: u64,
+    reaction: &str,
+    created_at: SqlDateTime,
+) -> Result<bool, ApiError> {
+    let res = sqlx::query(
+        "insert ignore into dg_likes(app_id,target_type,target_id,user_id,reaction,created_at) values(?,?,?,?,?,?)",
+    )
+    .bind(app_id)
+    .bind(target_type)
+    .bind(target_id)
+    .bind(user_id)
+    .bind(reaction)
+    .bind(created_at)
+    .execute(conn)
+    .await
+    .map_err(|e| api_errore(ApiErrorCode::InvalidDatabase, &e))?;
+
+    Ok(res.rows_affected() > 0)
+}
+
+/// Inserts many reactions at once, those already stored are skipped.
+pub async fn create_batch(
+    conn: &mut SqlConnection,
+    likes: &Vec<LikeModel>,
+) -> Result<(), ApiError> {
+    if likes.len() < 1 {
+        return Ok(());
+    }
+
+    let sql = format!(
+        "insert ignore into dg_likes(app_id,target_type,target_id,user_id,reaction,created_at) values {}",
+        vec!["(?,?,?,?,?,?)"; likes.len()].join(",")
+    );
+    let mut query = sqlx::query(&sql);
+    for o in likes.iter() {
+        query = query
+            .bind(o.app_id)
+            .bind(o.target_type)
+            .bind(o.target_id)
+            .bind(o.user_id)
+            .bind(&o.reaction)
+            .bind(o.created_at);
+    }
+    query
+        .execute(conn)
+        .await
+        .map_err(|e| api_errore(ApiErrorCode::InvalidDatabase, &e))?;
+
+    Ok(())
+}
+
+/// Returns false when the user had not reacted so.
+pub async fn delete(
+    conn: &mut SqlConnection,
+    target_type: i8,
+    target_id: u64,
+    user_id: u64,
+    reaction: &str,
+) -> Result<bool, ApiError> {
+    let res = sqlx::query(
+        "delete from dg_likes where target_type=? and target_id=? and reaction=? and user_id=?",
+    )
+    .bind(target_type)
+    .bind(target_id)
+    .bind(reaction)
+    .bind(user_id)
+    .execute(conn)
+    .await
+    .map_err(|e| api_errore(ApiErrorCode::InvalidDatabase, &e))?;
+
+    Ok(res.rows_affected() > 0)
+}
+
+/// Walks the whole table by id, for rebuilding the Redis sets.
+pub async fn fetch_after(
+    conn: &mut SqlConnection,
+    last_id: u64,
+    count: u32,
+) -> Result<Vec<LikeModel>, ApiError> {
+    sqlx::query_as::<_, LikeModel>("select * from dg_likes where id>? order by id limit ?")
+        .bind(last_id)
+        .bind(count)
+        .fetch_all(conn)
+        .await
+        .map_err(|e| api_errore(ApiErrorCode::InvalidDatabase, &e))
+}
@@ -0,0 +1,26 @@
+use crate::shared::data::*;
+use crate::shared::web::*;
+
+/// Likes of the Redis-only era copied into `dg_likes`.
+pub const LIKES_MIGRATED: &'static str = "likes_migrated";
+
+pub async fn is_applied(conn: &mut SqlConnection, version: &str) -> Result<bool, ApiError> {
+    let res: Option<(String,)> =
+        sqlx::query_as("select version from dg_migrations where version=?")
+            .bind(version)
+            .fetch_optional(conn)
+            .await
+            .map_err(|e| api_errore(ApiErrorCode::InvalidDatabase, &e))?;
+
+    Ok(res.is_some())
+}
+
+pub async fn create(conn: &mut SqlConnection, version: &str) -> Result<(), ApiError> {
+    sqlx::query("insert ignore into dg_migrations(version) values(?)")
+        .bind(version)
+        .execute(conn)
+        .await
+        .map_err(|e| api_errore(ApiErrorCode::InvalidDatabase, &e))?;
+
+    Ok(())
+}
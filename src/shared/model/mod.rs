@@ -1,5 +1,15 @@
 pub mod app;
+pub mod attachment;
+pub mod bookmark;
+pub mod draft;
+pub mod like;
 pub mod manager;
+pub mod migration;
+pub mod notification;
+pub mod poll;
 pub mod reply;
+pub mod reply_revision;
+pub mod tag;
 pub mod topic;
+pub mod topic_revision;
 pub mod user;
@@ -0,0 +1,263 @@
+use crate::shared::data::*;
+use crate::shared::web::*;
+
+pub const KIND_TOPIC_MOVED: i8 = 1;
+pub const KIND_TOPIC_REPLIED: i8 = 2;
+pub const KIND_REPLY_REPLIED: i8 = 3;
+pub const KIND_TOPIC_LIKED: i8 = 4;
+pub const KIND_REPLY_LIKED: i8 = 5;
+pub const KIND_MENTIONED: i8 = 6;
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct NotificationModel {
+    pub id: u64,
+    pub app_id: u64,
+    pub user_id: u64,
+    pub kind: i8,
+    pub topic_id: u64,
+    pub reply_id: u64,
+    pub actor_id: u64,
+    pub actor_count: u32,
+    pub content: String,
+    pub is_read: bool,
+    pub created_at: SqlDateTime,
+    pub updated_at: SqlDateTime,
+}
+
+#[derive(Debug, Serialize)]
+pub struct NotificationSimple {
+    pub id: u64,
+    pub kind: i8,
+    pub topic_id: u64,
+    pub reply_id: u64,
+    pub actor_id: u64,
+    pub actor_count: u32,
+    pub content: String,
+    pub is_read: bool,
+    pub created_at: SqlDateTime,
+    pub updated_at: SqlDateTime,
+}
+
+impl NotificationModel {
+    pub fn to_simple(self: &Self) -> NotificationSimple {
+        NotificationSimple {
+            id: self.id,
+            kind: self.kind,
+            topic_id: self.topic_id,
+            reply_id: self.reply_id,
+            actor_id: self.actor_id,
+            actor_count: self.actor_count,
+            content: self.content.clone(),
+            is_read: self.is_read,
+            created_at: self.created_at,
+            updated_at: self.updated_at,
+        }
+    }
+}
+
+impl Default for NotificationModel {
+    fn default() -> NotificationModel {
+        NotificationModel {
+            id: 0,
+            app_id: 0,
+            user_id: 0,
+            kind: 0,
+            topic_id: 0,
+            reply_id: 0,
+            actor_id: 0,
+            actor_count: 1,
+            content: String::new(),
+            is_read: false,
+            created_at: SqlDateTime::MIN,
+            updated_at: SqlDateTime::MIN,
+        }
+    }
+}
+
+pub async fn create(
+    conn: &mut SqlRawConnection,
+    notification: &NotificationModel,
+) -> Result<u64, ApiError> {
+    let res = sqlx::query(
+        "insert into dg_notifications(app_id,user_id,kind,topic_id,reply_id,actor_id,content) values(?,?,?,?,?,?,?)",
+    )
+    .bind(notification.app_id)
+    .bind(notification.user_id)
+    .bind(notification.kind)
+    .bind(notification.topic_id)
+    .bind(notification.reply_id)
+    .bind(notification.actor_id)
+    .bind(&notification.content)
+    .execute(conn)
+    .await
+    .map_err(|e| api_errore(ApiErrorCode::InvalidDatabase, &e))?;
+
+    Ok(res.last_insert_id())
+}
+
+/// Key shared by the unread notifications folded together. Replies to a
+/// topic are folded by topic, likes by their target.
+fn group_key(notification: &NotificationModel) -> String {
+    match notification.kind {
+        KIND_TOPIC_REPLIED => format!("{}:{}", notification.kind, notification.topic_id),
+        _ => format!(
+            "{}:{}:{}",
+            notification.kind, notification.topic_id, notification.reply_id
+        ),
+    }
+}
+
+/// Folds the event into the unread notification of the same kind and target,
+/// so repeated events read as "5 people liked your topic", and keeps the
+/// latest actor and reply. `actor_count` counts the distinct actors recorded
+/// in `dg_notification_actors`. Creates the notification when there is none
+/// to fold into.
+pub async fn create_or_aggregate(
+    conn: &mut SqlConnection,
+    notification: &NotificationModel,
+) -> Result<(), ApiError> {
+    // the unique (user_id,group_key) decides between insert and fold, and
+    // LAST_INSERT_ID gives the id of the row either way
+    let res = sqlx::query(
+        "insert into dg_notifications(app_id,user_id,kind,topic_id,reply_id,actor_id,content,group_key) values(?,?,?,?,?,?,?,?) on duplicate key update id=LAST_INSERT_ID(id),actor_id=VALUES(actor_id),reply_id=VALUES(reply_id),content=VALUES(content),updated_at=NOW()",
+    )
+    .bind(notification.app_id)
+    .bind(notification.user_id)
+    .bind(notification.kind)
+    .bind(notification.topic_id)
+    .bind(notification.reply_id)
+    .bind(notification.actor_id)
+    .bind(&notification.content)
+    .bind(group_key(notification))
+    .execute(conn.as_mut())
+    .await
+    .map_err(|e| api_errore(ApiErrorCode::InvalidDatabase, &e))?;
+    let id = res.last_insert_id();
+
+    let res = sqlx::query(
+        "insert ignore into dg_notification_actors(notification_id,actor_id) values(?,?)",
+    )
+    .bind(id)
+    .bind(notification.actor_id)
+    .execute(conn.as_mut())
+    .await
+    .map_err(|e| api_errore(ApiErrorCode::InvalidDatabase, &e))?;
+    if res.rows_affected() == 0 {
+        return Ok(());
+    }
+
+    sqlx::query(
+        "update dg_notifications set actor_count=(select count(*) from dg_notification_actors where notification_id=?) where id=?",
+    )
+    .bind(id)
+    .bind(id)
+    .execute(conn.as_mut())
+    .await
+    .map_err(|e| api_errore(ApiErrorCode::InvalidDatabase, &e))?;
+
+    Ok(())
+}
+
+pub async fn count_unread(conn: &mut SqlConnection, user_id: u64) -> Result<u32, ApiError> {
+    let total: (i64,) =
+        sqlx::query_as("select count(*) from dg_notifications where user_id=? and is_read=0")
+            .bind(user_id)
+            .fetch_one(conn)
+            .await
+            .map_err(|e| api_errore(ApiErrorCode::InvalidDatabase, &e))?;
+
+    Ok(total.0 as u32)
+}
+
+/// Marks the given notifications of the user read, returns how many were unread.
+pub async fn mark_read(
+    conn: &mut SqlConnection,
+    user_id: u64,
+    ids: Vec<u64>,
+) -> Result<u64, ApiError> {
+    if ids.len() < 1 {
+        return Ok(0);
+    }
+
+    let ids_str = ids
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join(",");
+    // read notifications leave their group, the next event starts a new one
+    let res = sqlx::query(&format!(
+        "update dg_notifications set is_read=1,group_key=NULL where user_id=? and is_read=0 and id in ({})",
+        ids_str
+    ))
+    .bind(user_id)
+    .execute(conn.as_mut())
+    .await
+    .map_err(|e| api_errore(ApiErrorCode::InvalidDatabase, &e))?;
+
+    sqlx::query(&format!(
+        "delete a from dg_notification_actors a join dg_notifications n on n.id=a.notification_id where n.user_id=? and n.id in ({})",
+        ids_str
+    ))
+    .bind(user_id)
+    .execute(conn.as_mut())
+    .await
+    .map_err(|e| api_errore(ApiErrorCode::InvalidDatabase, &e))?;
+
+    Ok(res.rows_affected())
+}
+
+pub async fn mark_all_read(conn: &mut SqlConnection, user_id: u64) -> Result<u64, ApiError> {
+    let res = sqlx::query(
+        "update dg_notifications set is_read=1,group_key=NULL where user_id=? and is_read=0",
+    )
+    .bind(user_id)
+    .execute(conn.as_mut())
+    .await
+    .map_err(|e| api_errore(ApiErrorCode::InvalidDatabase, &e))?;
+
+    sqlx::query(
+        "delete a from dg_notification_actors a join dg_notifications n on n.id=a.notification_id where n.user_id=?",
+    )
+    .bind(user_id)
+    .execute(conn.as_mut())
+    .await
+    .map_err(|e| api_errore(ApiErrorCode::InvalidDatabase, &e))?;
+
+    Ok(res.rows_affected())
+}
+
+/// Latest activity first.
+pub async fn fetch_pagging(
+    conn: &mut SqlConnection,
+    user_id: u64,
+    unread: bool,
+    cursor: u32,
+    count: u32,
+) -> Result<(u32, Vec<NotificationModel>), ApiError> {
+    let part_sql = match unread {
+        true => " and is_read=0",
+        _ => "",
+    };
+
+    let res = sqlx::query_as::<_, NotificationModel>(&format!(
+        "select * from dg_notifications where user_id=?{} order by updated_at desc,id desc limit ?,?",
+        part_sql
+    ))
+    .bind(user_id)
+    .bind(cursor)
+    .bind(count)
+    .fetch_all(conn.as_mut())
+    .await
+    .map_err(|e| api_errore(ApiErrorCode::InvalidDatabase, &e))?;
+
+    let total: (i64,) = sqlx::query_as(&format!(
+        "select count(*) from dg_notifications where user_id=?{}",
+        part_sql
+    ))
+    .bind(user_id)
+    .fetch_one(conn.as_mut())
+    .await
+    .map_err(|e| api_errore(ApiErrorCode::InvalidDatabase, &e))?;
+
+    Ok((total.0 as u32, res))
+}
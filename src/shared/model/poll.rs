@@ -0,0 +1,290 @@
+use crate::shared::base::*;
+use crate::shared::data::*;
+use crate::shared::web::*;
+
+pub const MIN_OPTIONS: usize = 2;
+pub const MAX_OPTIONS: usize = 10;
+const MAX_OPTION_CHARS: usize = 100;
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct PollModel {
+    pub id: u64,
+    pub app_id: u64,
+    pub topic_id: u64,
+    pub multiple: bool,
+    pub anonymous: bool,
+    pub voter_count: u64,
+    pub close_at: Option<SqlDateTime>,
+    pub created_at: SqlDateTime,
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct PollOptionModel {
+    pub id: u64,
+    pub poll_id: u64,
+    pub position: u32,
+    pub title: String,
+    pub vote_count: u64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PollOptionSimple {
+    pub id: u64,
+    pub title: String,
+    pub vote_count: u64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PollSimple {
+    pub id: u64,
+    pub multiple: bool,
+    pub anonymous: bool,
+    pub voter_count: u64,
+    pub close_at: Option<SqlDateTime>,
+    pub is_closed: bool,
+    pub options: Vec<PollOptionSimple>,
+}
+
+impl PollModel {
+    pub fn to_simple(self: &Self, options: Vec<PollOptionModel>) -> PollSimple {
+        PollSimple {
+            id: self.id,
+            multiple: self.multiple,
+            anonymous: self.anonymous,
+            voter_count: self.voter_count,
+            close_at: self.close_at,
+            is_closed: self.is_closed(),
+            options: options
+                .into_iter()
+                .map(|s| PollOptionSimple {
+                    id: s.id,
+                    title: s.title,
+                    vote_count: s.vote_count,
+                })
+                .collect(),
+        }
+    }
+
+    pub fn is_closed(self: &Self) -> bool {
+        self.close_at
+            .map_or(false, |s| s.and_utc().timestamp() <= timestamp())
+    }
+}
+
+impl Default for PollModel {
+    fn default() -> PollModel {
+        PollModel {
+            id: 0,
+            app_id: 0,
+            topic_id: 0,
+            multiple: false,
+            anonymous: false,
+            voter_count: 0,
+            close_at: None,
+            created_at: SqlDateTime::MIN,
+        }
+    }
+}
+
+/// Trims the option titles and checks their count and length, `close_at`
+/// is a unix timestamp that must lie in the future.
+pub fn check_options(
+    options: Vec<String>,
+    close_at: Option<i64>,
+) -> Result<(Vec<String>, Option<SqlDateTime>), ApiError> {
+    let options: Vec<String> = options.iter().map(|s| s.trim().to_string()).collect();
+    if options.len() < MIN_OPTIONS || options.len() > MAX_OPTIONS {
+        return Err(api_error2(
+            ApiErrorCode::InvalidParams,
+            "poll options must be between 2 to 10",
+        ));
+    }
+    if options
+        .iter()
+        .any(|s| s.is_empty() || s.chars().count() > MAX_OPTION_CHARS)
+    {
+        return Err(api_error2(
+            ApiErrorCode::InvalidParams,
+            "poll option must be 1 to 100 characters",
+        ));
+    }
+
+    let close_at = match close_at {
+        Some(ts) if ts <= timestamp() => {
+            return Err(api_error2(
+                ApiErrorCode::InvalidParams,
+                "poll close_at must be in the future",
+            ))
+        }
+        Some(ts) => chrono::DateTime::from_timestamp(ts, 0).map(|s| s.naive_utc()),
+        None => None,
+    };
+
+    Ok((options, close_at))
+}
+
+pub async fn get_by_topic(conn: &mut SqlConnection, topic_id: u64) -> Result<PollModel, ApiError> {
+    let res = sqlx::query_as::<_, PollModel>("select * from dg_polls where topic_id=?")
+        .bind(topic_id)
+        .fetch_optional(conn)
+        .await
+        .map_err(|e| api_errore(ApiErrorCode::InvalidDatabase, &e))?;
+
+    if res.is_none() {
+        return Err(api_error(ApiErrorCode::PollNotFound));
+    }
+
+    Ok(res.unwrap())
+}
+
+pub async fn get_options(
+    conn: &mut SqlConnection,
+    poll_id: u64,
+) -> Result<Vec<PollOptionModel>, ApiError> {
+    sqlx::query_as::<_, PollOptionModel>(
+        "select * from dg_poll_options where poll_id=? order by position",
+    )
+    .bind(poll_id)
+    .fetch_all(conn)
+    .await
+    .map_err(|e| api_errore(ApiErrorCode::InvalidDatabase, &e))
+}
+
+pub async fn get_simple_by_topic(
+    conn: &mut SqlConnection,
+    topic_id: u64,
+) -> Result<Option<PollSimple>, ApiError> {
+    let poll = sqlx::query_as::<_, PollModel>("select * from dg_polls where topic_id=?")
+        .bind(topic_id)
+        .fetch_optional(conn.as_mut())
+        .await
+        .map_err(|e| api_errore(ApiErrorCode::InvalidDatabase, &e))?;
+
+    let poll = match poll {
+        Some(poll) => poll,
+        None => return Ok(None),
+    };
+    let options = get_options(conn, poll.id).await?;
+
+    Ok(Some(poll.to_simple(options)))
+}
+
+pub async fn create(
+    conn: &mut SqlConnection,
+    poll: &mut PollModel,
+    options: Vec<String>,
+) -> Result<u64, ApiError> {
+    let res = sqlx::query(
+        "insert into dg_polls(app_id,topic_id,multiple,anonymous,close_at) values(?,?,?,?,?)",
+    )
+    .bind(poll.app_id)
+    .bind(poll.topic_id)
+    .bind(poll.multiple)
+    .bind(poll.anonymous)
+    .bind(poll.close_at)
+    .execute(conn.as_mut())
+    .await
+    .map_err(|e| api_errore(ApiErrorCode::InvalidDatabase, &e))?;
+
+    let poll_id = res.last_insert_id();
+    for (i, title) in options.iter().enumerate() {
+        sqlx::query("insert into dg_poll_options(poll_id,position,title) values(?,?,?)")
+            .bind(poll_id)
+            .bind(i as u32)
+            .bind(title)
+            .execute(conn.as_mut())
+            .await
+            .map_err(|e| api_errore(ApiErrorCode::InvalidDatabase, &e))?;
+    }
+
+    Ok(poll_id)
+}
+
+/// Records the choices of a voter in one transaction, returns false without
+/// recording anything when the user has voted on this poll before.
+pub async fn create_votes(
+    conn: &mut SqlConnection,
+    poll_id: u64,
+    user_id: u64,
+    option_ids: &Vec<u64>,
+) -> Result<bool, ApiError> {
+    let mut tx = database_begin(conn).await?;
+
+    let res = sqlx::query("insert ignore into dg_poll_voters(poll_id,user_id) values(?,?)")
+        .bind(poll_id)
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| api_errore(ApiErrorCode::InvalidDatabase, &e))?;
+    if res.rows_affected() == 0 {
+        return Ok(false);
+    }
+
+    for option_id in option_ids.iter() {
+        sqlx::query("insert into dg_poll_votes(poll_id,option_id,user_id) values(?,?,?)")
+            .bind(poll_id)
+            .bind(option_id)
+            .bind(user_id)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| api_errore(ApiErrorCode::InvalidDatabase, &e))?;
+
+        sqlx::query("update dg_poll_options set vote_count=vote_count+1 where id=?")
+            .bind(option_id)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| api_errore(ApiErrorCode::InvalidDatabase, &e))?;
+    }
+
+    sqlx::query("update dg_polls set voter_count=voter_count+1 where id=?")
+        .bind(poll_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| api_errore(ApiErrorCode::InvalidDatabase, &e))?;
+
+    database_commit(tx).await?;
+
+    Ok(true)
+}
+
+pub async fn get_user_choices(
+    conn: &mut SqlConnection,
+    poll_id: u64,
+    user_id: u64,
+) -> Result<Vec<u64>, ApiError> {
+    let res: Vec<(u64,)> = sqlx::query_as(
+        "select option_id from dg_poll_votes where poll_id=? and user_id=? order by option_id",
+    )
+    .bind(poll_id)
+    .bind(user_id)
+    .fetch_all(conn)
+    .await
+    .map_err(|e| api_errore(ApiErrorCode::InvalidDatabase, &e))?;
+
+    Ok(res.into_iter().map(|s| s.0).collect())
+}
+
+pub async fn fetch_voters(
+    conn: &mut SqlConnection,
+    option_id: u64,
+    cursor: u32,
+    count: u32,
+) -> Result<(u32, Vec<u64>), ApiError> {
+    let voters: Vec<(u64,)> = sqlx::query_as(
+        "select user_id from dg_poll_votes where option_id=? order by created_at desc limit ?,?",
+    )
+    .bind(option_id)
+    .bind(cursor)
+    .bind(count)
+    .fetch_all(conn.as_mut())
+    .await
+    .map_err(|e| api_errore(ApiErrorCode::InvalidDatabase, &e))?;
+
+    let total: (i64,) = sqlx::query_as("select count(*) from dg_poll_votes where option_id=?")
+        .bind(option_id)
+        .fetch_one(conn)
+        .await
+        .map_err(|e| api_errore(ApiErrorCode::InvalidDatabase, &e))?;
+
+    Ok((total.0 as u32, voters.into_iter().map(|s| s.0).collect()))
+}
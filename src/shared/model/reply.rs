@@ -1,9 +1,18 @@
 use crate::shared::base::*;
+use crate::shared::content;
 use crate::shared::data::*;
+use crate::shared::reaction::{DEFAULT_REACTION, DOWNVOTE_REACTION, TARGET_REPLY};
 use crate::shared::web::*;
 
+use super::topic::TopicModel;
+use super::user::UserSimple;
+
 const STATUS_HIDDEN: i64 = -1;
 const STATUS_DELETE: i64 = -2;
+const STATUS_DELETE_AUTHOR: i64 = -3;
+
+/// Children listed along with each top-level reply of a thread.
+pub const INLINE_CHILDREN: u32 = 3;
 
 #[derive(PartialEq, Eq, Deserialize)]
 #[serde(rename_all = "lowercase")]
@@ -12,6 +21,48 @@ pub enum VisibleStyle {
     NORMAL = 1,
 }
 
+#[derive(PartialEq, Eq, Deserialize, Copy, Clone)]
+#[serde(rename_all = "lowercase")]
+pub enum VisibleOrderBy {
+    NEWEST = 0,
+    OLDEST = 1,
+    LIKED = 2,
+    AUTHOR = 3,
+    SCORE = 4,
+}
+
+impl Default for VisibleOrderBy {
+    fn default() -> Self {
+        VisibleOrderBy::NEWEST
+    }
+}
+
+impl VisibleOrderBy {
+    /// The accepted answer comes first, then pinned replies, then the chosen
+    /// order. AUTHOR puts the replies of the topic author on top. Hidden and
+    /// deleted replies listed to managers or as tombstones keep their place
+    /// in the chosen order.
+    fn sql(self: &Self, topic: &TopicModel) -> String {
+        let mut out = String::new();
+        if topic.accepted_reply_id > 0 {
+            out.push_str(&format!("id={} desc,", topic.accepted_reply_id));
+        }
+        out.push_str("greatest(topped,0) desc,");
+
+        let part_sql = match self {
+            VisibleOrderBy::NEWEST => "created_at desc,id desc".to_string(),
+            VisibleOrderBy::OLDEST => "created_at,id".to_string(),
+            VisibleOrderBy::LIKED => "like_count desc,id desc".to_string(),
+            VisibleOrderBy::SCORE => "score desc,id desc".to_string(),
+            VisibleOrderBy::AUTHOR => {
+                format!("user_id={} desc,created_at desc,id desc", topic.user_id)
+            }
+        };
+        out.push_str(&part_sql);
+        out
+    }
+}
+
 #[derive(PartialEq, Eq, Deserialize, Copy, Clone)]
 #[serde(rename_all = "lowercase")]
 pub enum StatusAction {
@@ -21,26 +72,51 @@ pub enum StatusAction {
     DELETE = 3,
 }
 
+#[derive(PartialEq, Eq, Copy, Clone)]
+pub enum StatusOperator {
+    MANAGER = 0,
+    AUTHOR = 1,
+}
+
 #[derive(Debug, Serialize, sqlx::FromRow)]
 pub struct ReplyModel {
     pub id: u64,
     pub app_id: u64,
     pub topic_id: u64,
     pub user_id: u64,
+    pub parent_reply_id: u64,
+    pub root_reply_id: u64,
+    pub quote_reply_id: u64,
+    pub content_format: i8,
     pub content: String,
+    pub content_html: String,
+    pub excerpt: String,
     pub topped: i64,
     pub created_at: SqlDateTime,
     pub like_count: u64,
+    pub score: i64,
+    pub reaction_counts: String,
+    pub child_count: u64,
+    pub edited_at: Option<SqlDateTime>,
 }
 
-#[derive(Debug, Serialize, sqlx::FromRow)]
+#[derive(Debug, Serialize)]
 pub struct ReplySimple {
     pub id: u64,
     pub user_id: u64,
+    pub parent_reply_id: u64,
+    pub root_reply_id: u64,
+    pub quote_reply_id: u64,
+    pub content_format: i8,
     pub content: String,
+    pub content_html: String,
     pub topped: i64,
     pub created_at: SqlDateTime,
     pub like_count: u64,
+    pub score: i64,
+    pub reaction_counts: HashMap<String, u64>,
+    pub child_count: u64,
+    pub edited_at: Option<SqlDateTime>,
 }
 
 impl ReplyModel {
@@ -48,13 +124,36 @@ impl ReplyModel {
         ReplySimple {
             id: self.id,
             user_id: self.user_id,
+            parent_reply_id: self.parent_reply_id,
+            root_reply_id: self.root_reply_id,
+            quote_reply_id: self.quote_reply_id,
+            content_format: self.content_format,
             content: self.content.clone(),
+            content_html: self.content_html.clone(),
             topped: self.topped,
             created_at: self.created_at,
             like_count: self.like_count,
+            score: self.score,
+            reaction_counts: self.reaction_map(),
+            child_count: self.child_count,
+            edited_at: self.edited_at,
+        }
+    }
+
+    /// Stands in for a hidden or deleted reply whose children are still
+    /// listed, without its content.
+    pub fn to_tombstone(self: &Self) -> ReplySimple {
+        ReplySimple {
+            content: String::new(),
+            content_html: String::new(),
+            ..self.to_simple()
         }
     }
 
+    pub fn reaction_map(self: &Self) -> HashMap<String, u64> {
+        serde_json::from_str(&self.reaction_counts).unwrap_or_default()
+    }
+
     pub fn is_actived(self: &Self) -> bool {
         return self.topped >= 0;
     }
@@ -62,6 +161,15 @@ impl ReplyModel {
     pub fn is_deleted(self: &Self) -> bool {
         return self.topped <= STATUS_DELETE;
     }
+
+    /// Top-level reply of the thread this reply belongs to.
+    pub fn root_id(self: &Self) -> u64 {
+        if self.root_reply_id > 0 {
+            return self.root_reply_id;
+        }
+
+        return self.id;
+    }
 }
 
 impl Default for ReplyModel {
@@ -71,10 +179,20 @@ impl Default for ReplyModel {
             app_id: 0,
             topic_id: 0,
             user_id: 0,
+            parent_reply_id: 0,
+            root_reply_id: 0,
+            quote_reply_id: 0,
+            content_format: content::FORMAT_PLAIN,
             content: String::new(),
+            content_html: String::new(),
+            excerpt: String::new(),
             topped: 0,
             created_at: SqlDateTime::MIN,
             like_count: 0,
+            score: 0,
+            reaction_counts: String::from("{}"),
+            child_count: 0,
+            edited_at: None,
         }
     }
 }
@@ -93,14 +211,115 @@ pub async fn get_by_id(conn: &mut SqlConnection, id: u64) -> Result<ReplyModel,
     Ok(res.unwrap())
 }
 
+pub async fn get_map_by_ids(
+    conn: &mut SqlConnection,
+    ids: Vec<u64>,
+) -> Result<ArcDataMap<u64, ReplyModel>, ApiError> {
+    let mut out = ArcDataMap::new();
+    if ids.len() < 1 {
+        return Ok(out);
+    }
+
+    let ids_str = ids
+        .iter()
+        .map(ToString::to_string)
+        .collect::<HashSet<_>>()
+        .into_iter()
+        .collect::<Vec<_>>()
+        .join(",");
+    let res = sqlx::query_as::<_, ReplyModel>(&format!(
+        "select * from dg_replies where id in ({})",
+        ids_str
+    ))
+    .fetch_all(conn)
+    .await
+    .map_err(|e| api_errore(ApiErrorCode::InvalidDatabase, &e))?;
+
+    for o in res {
+        out.insert(o.id, o);
+    }
+
+    Ok(out)
+}
+
+/// Replies quoted by `replies`, along with the ids of the authors of both,
+/// for hydrating a reply list.
+pub async fn get_quote_map(
+    conn: &mut SqlConnection,
+    replies: &Vec<ReplyModel>,
+) -> Result<(ArcDataMap<u64, ReplyModel>, Vec<u64>), ApiError> {
+    let quote_map = get_map_by_ids(
+        conn,
+        replies
+            .iter()
+            .filter(|s| s.quote_reply_id > 0)
+            .map(|s| s.quote_reply_id)
+            .collect(),
+    )
+    .await?;
+
+    let mut user_ids: Vec<u64> = replies.iter().map(|s| s.user_id).collect();
+    user_ids.extend(
+        replies
+            .iter()
+            .filter_map(|s| quote_map.opt(s.quote_reply_id))
+            .map(|s| s.user_id),
+    );
+
+    Ok((quote_map, user_ids))
+}
+
+#[derive(Debug, Serialize)]
+pub struct ReplyQuoteItem {
+    pub reply_id: u64,
+    pub is_available: bool,
+    pub user: Option<Arc<UserSimple>>,
+    pub excerpt: String,
+}
+
+/// A quote of a hidden or deleted reply keeps only its id.
+pub fn build_quote(
+    reply_id: u64,
+    quote_map: &ArcDataMap<u64, ReplyModel>,
+    user_map: &ArcDataMap<u64, UserSimple>,
+) -> Option<ReplyQuoteItem> {
+    if reply_id == 0 {
+        return None;
+    }
+
+    let quoted = quote_map.opt(reply_id).filter(|s| s.is_actived());
+    Some(match quoted {
+        Some(q) => ReplyQuoteItem {
+            reply_id,
+            is_available: true,
+            user: Some(user_map.get(q.user_id)),
+            excerpt: q.excerpt.clone(),
+        },
+        None => ReplyQuoteItem {
+            reply_id,
+            is_available: false,
+            user: None,
+            excerpt: String::new(),
+        },
+    })
+}
+
 pub async fn create(conn: &mut SqlConnection, reply: &mut ReplyModel) -> Result<u64, ApiError> {
+    (reply.content_html, reply.excerpt) = content::render(reply.content_format, &reply.content);
+
     let res = sqlx::query(
-        "insert into dg_replies(app_id,topic_id,user_id,content,topped) values(?,?,?,?,0)",
+        "insert into dg_replies(app_id,topic_id,user_id,parent_reply_id,root_reply_id,quote_reply_id,content_format,content,content_html,excerpt,topped) values(?,?,?,?,?,?,?,?,?,?,0)",
     )
     .bind(reply.app_id)
     .bind(reply.topic_id)
     .bind(reply.user_id)
+    .bind(reply.parent_reply_id)
+    .bind(reply.root_reply_id)
+    .bind(reply.quote_reply_id)
+    .bind(reply.content_format)
     .bind(&reply.content)
+    .bind(&reply.content_html)
+    .bind(&reply.excerpt)
     .execute(conn)
     .await
     .map_err(|e| api_errore(ApiErrorCode::InvalidDatabase, &e))?;
@@ -108,10 +327,55 @@ pub async fn create(conn: &mut SqlConnection, reply: &mut ReplyModel) -> Result<
     Ok(res.last_insert_id())
 }
 
+pub async fn update_content(
+    conn: &mut SqlConnection,
+    reply: &mut ReplyModel,
+) -> Result<(), ApiError> {
+    (reply.content_html, reply.excerpt) = content::render(reply.content_format, &reply.content);
+
+    sqlx::query(
+        "update dg_replies set content_format=?,content=?,content_html=?,excerpt=?,edited_at=NOW() where id=?",
+    )
+    .bind(reply.content_format)
+    .bind(&reply.content)
+    .bind(&reply.content_html)
+    .bind(&reply.excerpt)
+    .bind(reply.id)
+    .execute(conn)
+    .await
+    .map_err(|e| api_errore(ApiErrorCode::InvalidDatabase, &e))?;
+
+    Ok(())
+}
+
+/// Marks the reply deleted, returns false when it already was so callers
+/// only adjust the counters once.
+pub async fn delete(
+    conn: &mut SqlConnection,
+    id: u64,
+    operator: StatusOperator,
+) -> Result<bool, ApiError> {
+    let topped = match operator {
+        StatusOperator::AUTHOR => STATUS_DELETE_AUTHOR,
+        _ => STATUS_DELETE,
+    };
+
+    let res = sqlx::query("update dg_replies set topped=? where id=? and topped>?")
+        .bind(topped)
+        .bind(id)
+        .bind(STATUS_DELETE)
+        .execute(conn)
+        .await
+        .map_err(|e| api_errore(ApiErrorCode::InvalidDatabase, &e))?;
+
+    Ok(res.rows_affected() > 0)
+}
+
 pub async fn update_status(
     conn: &mut SqlConnection,
     id: u64,
     action: StatusAction,
+    operator: StatusOperator,
 ) -> Result<(), ApiError> {
     let mut topped: i64 = 0;
     if action == StatusAction::MOVEUP {
@@ -119,7 +383,10 @@ pub async fn update_status(
     } else if action == StatusAction::HIDDEN {
         topped = STATUS_HIDDEN;
     } else if action == StatusAction::DELETE {
-        topped = STATUS_DELETE;
+        topped = match operator {
+            StatusOperator::AUTHOR => STATUS_DELETE_AUTHOR,
+            _ => STATUS_DELETE,
+        };
     }
 
     sqlx::query("update dg_replies set topped=? where id=?")
@@ -132,20 +399,94 @@ pub async fn update_status(
     Ok(())
 }
 
-pub async fn update_like_count(
+/// Adds `op` to the count of the reaction, the default reaction also keeps
+/// `like_count` up to date for the rankings. Likes and downvotes move `score`.
+pub async fn update_reaction_count(
     conn: &mut SqlConnection,
     id: u64,
+    reaction: &str,
     op: UpdateCountOp,
 ) -> Result<(), ApiError> {
     let mut sql = String::new();
-    sql.push_str("update dg_replies set like_count=like_count");
+    sql.push_str(
+        "update dg_replies set updated_at=updated_at,reaction_counts=JSON_SET(reaction_counts,?,GREATEST(CAST(COALESCE(JSON_EXTRACT(reaction_counts,?),0) AS SIGNED)+?,0))",
+    );
+
+    let delta = match op {
+        UpdateCountOp::INCR => 1,
+        _ => -1,
+    };
+    if reaction == DEFAULT_REACTION {
+        sql.push_str(match op {
+            UpdateCountOp::INCR => ",like_count=like_count+1,score=score+1",
+            _ => ",like_count=like_count-1,score=score-1",
+        });
+    } else if reaction == DOWNVOTE_REACTION {
+        sql.push_str(match op {
+            UpdateCountOp::INCR => ",score=score-1",
+            _ => ",score=score+1",
+        });
+    }
+    sql.push_str(" where id=?");
+
+    let path = format!("$.\"{}\"", reaction);
+    sqlx::query(&sql)
+        .bind(&path)
+        .bind(&path)
+        .bind(delta)
+        .bind(id)
+        .execute(conn)
+        .await
+        .map_err(|e| api_errore(ApiErrorCode::InvalidDatabase, &e))?;
+
+    Ok(())
+}
+
+/// Seeds `reaction_counts` from the likes counted before reactions existed.
+pub async fn init_reaction_counts(conn: &mut SqlConnection) -> Result<(), ApiError> {
+    sqlx::query(
+        "update dg_replies set updated_at=updated_at,reaction_counts=JSON_OBJECT(?,like_count) where like_count>0 and reaction_counts='{}'",
+    )
+    .bind(DEFAULT_REACTION)
+    .execute(conn)
+    .await
+    .map_err(|e| api_errore(ApiErrorCode::InvalidDatabase, &e))?;
+
+    Ok(())
+}
+
+/// Repairs `like_count` and `score` drifted from the likes and downvotes
+/// stored in `dg_likes`, returns how many replies were off.
+pub async fn reconcile_like_count(conn: &mut SqlConnection) -> Result<u64, ApiError> {
+    let res = sqlx::query(
+        "update dg_replies r left join (select target_id,sum(reaction=?) as likes,sum(reaction=?) as downvotes from dg_likes where target_type=? and reaction in (?,?) group by target_id) l on l.target_id=r.id set r.updated_at=r.updated_at,r.like_count=IFNULL(l.likes,0),r.score=IFNULL(l.likes,0)-IFNULL(l.downvotes,0),r.reaction_counts=JSON_SET(r.reaction_counts,?,IFNULL(l.likes,0)) where r.like_count<>IFNULL(l.likes,0) or r.score<>IFNULL(l.likes,0)-IFNULL(l.downvotes,0)",
+    )
+    .bind(DEFAULT_REACTION)
+    .bind(DOWNVOTE_REACTION)
+    .bind(TARGET_REPLY)
+    .bind(DEFAULT_REACTION)
+    .bind(DOWNVOTE_REACTION)
+    .bind(format!("$.\"{}\"", DEFAULT_REACTION))
+    .execute(conn)
+    .await
+    .map_err(|e| api_errore(ApiErrorCode::InvalidDatabase, &e))?;
+
+    Ok(res.rows_affected())
+}
+
+pub async fn update_child_count(
+    conn: &mut SqlConnection,
+    id: u64,
+    op: UpdateCountOp,
+) -> Result<(), ApiError> {
+    let mut sql = String::new();
+    sql.push_str("update dg_replies set updated_at=updated_at,child_count=child_count");
 
     let part_sql = match op {
-        UpdateCountOp::INCR => "+1",
-        _ => "-1",
+        UpdateCountOp::INCR => "+1 where id=?",
+        _ => "-1 where id=? and child_count>0",
     };
     sql.push_str(part_sql);
-    sql.push_str(" where id=?");
 
     sqlx::query(&sql)
         .bind(id)
@@ -158,8 +499,9 @@ pub async fn update_like_count(
 
 pub async fn fetch_pagging(
     conn: &mut SqlConnection,
-    topic_id: u64,
+    topic: &TopicModel,
     style: VisibleStyle,
+    order_by: VisibleOrderBy,
     cursor: u32,
     count: u32,
 ) -> Result<(u32, Vec<ReplyModel>), ApiError> {
@@ -169,7 +511,7 @@ pub async fn fetch_pagging(
 
     fetch_sql.push_str("select * from dg_replies where topic_id=?");
     count_sql.push_str("select count(*) from dg_replies where topic_id=?");
-    part_binds.push(topic_id);
+    part_binds.push(topic.id);
 
     let part_sql = match style {
         VisibleStyle::NORMAL => " and topped>=0",
@@ -178,7 +520,9 @@ pub async fn fetch_pagging(
     fetch_sql.push_str(part_sql);
     count_sql.push_str(part_sql);
 
-    fetch_sql.push_str(" order by created_at desc limit ?,?");
+    fetch_sql.push_str(" order by ");
+    fetch_sql.push_str(&order_by.sql(topic));
+    fetch_sql.push_str(" limit ?,?");
 
     let mut query = sqlx::query_as::<_, ReplyModel>(&fetch_sql);
     for v in part_binds.iter() {
@@ -202,3 +546,206 @@ pub async fn fetch_pagging(
 
     Ok((total.0 as u32, replies))
 }
+
+/// Top-level replies of a topic. Hidden or deleted ones stay listed while
+/// they have visible children, so the threads below them remain reachable.
+pub async fn fetch_root_pagging(
+    conn: &mut SqlConnection,
+    topic: &TopicModel,
+    order_by: VisibleOrderBy,
+    cursor: u32,
+    count: u32,
+) -> Result<(u32, Vec<ReplyModel>), ApiError> {
+    let replies = sqlx::query_as::<_, ReplyModel>(&format!(
+        "select * from dg_replies where topic_id=? and parent_reply_id=0 and (topped>=0 or child_count>0) order by {} limit ?,?",
+        order_by.sql(topic)
+    ))
+    .bind(topic.id)
+    .bind(cursor)
+    .bind(count)
+    .fetch_all(conn.as_mut())
+    .await
+    .map_err(|e| api_errore(ApiErrorCode::InvalidDatabase, &e))?;
+
+    let total: (i64,) = sqlx::query_as(
+        "select count(*) from dg_replies where topic_id=? and parent_reply_id=0 and (topped>=0 or child_count>0)",
+    )
+    .bind(topic.id)
+    .fetch_one(conn.as_mut())
+    .await
+    .map_err(|e| api_errore(ApiErrorCode::InvalidDatabase, &e))?;
+
+    Ok((total.0 as u32, replies))
+}
+
+/// Zero based position of a visible reply among the visible replies of its
+/// topic in the given order, None when the reply is not listed.
+pub async fn get_position(
+    conn: &mut SqlConnection,
+    topic: &TopicModel,
+    reply: &ReplyModel,
+    order_by: VisibleOrderBy,
+) -> Result<Option<u32>, ApiError> {
+    let res: Option<(u64,)> = sqlx::query_as(&format!(
+        "select rn from (select id,row_number() over (order by {}) as rn from dg_replies where topic_id=? and topped>=0) t where id=?",
+        order_by.sql(topic)
+    ))
+    .bind(reply.topic_id)
+    .bind(reply.id)
+    .fetch_optional(conn)
+    .await
+    .map_err(|e| api_errore(ApiErrorCode::InvalidDatabase, &e))?;
+
+    Ok(res.map(|s| s.0 as u32 - 1))
+}
+
+/// Direct children of a reply, oldest first so the conversation reads in order.
+pub async fn fetch_children_pagging(
+    conn: &mut SqlConnection,
+    parent_reply_id: u64,
+    cursor: u32,
+    count: u32,
+) -> Result<(u32, Vec<ReplyModel>), ApiError> {
+    let replies = sqlx::query_as::<_, ReplyModel>(
+        "select * from dg_replies where parent_reply_id=? and topped>=0 order by created_at,id limit ?,?",
+    )
+    .bind(parent_reply_id)
+    .bind(cursor)
+    .bind(count)
+    .fetch_all(conn.as_mut())
+    .await
+    .map_err(|e| api_errore(ApiErrorCode::InvalidDatabase, &e))?;
+
+    let total: (i64,) =
+        sqlx::query_as("select count(*) from dg_replies where parent_reply_id=? and topped>=0")
+            .bind(parent_reply_id)
+            .fetch_one(conn.as_mut())
+            .await
+            .map_err(|e| api_errore(ApiErrorCode::InvalidDatabase, &e))?;
+
+    Ok((total.0 as u32, replies))
+}
+
+/// First `limit` visible children of each given reply, in one query.
+pub async fn fetch_first_children(
+    conn: &mut SqlConnection,
+    parent_ids: Vec<u64>,
+    limit: u32,
+) -> Result<Vec<ReplyModel>, ApiError> {
+    if parent_ids.len() < 1 {
+        return Ok(Vec::new());
+    }
+
+    let ids_str = parent_ids
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join(",");
+    let res = sqlx::query_as::<_, ReplyModel>(&format!(
+        "select * from (select *,row_number() over (partition by parent_reply_id order by created_at,id) as rn from dg_replies where parent_reply_id in ({}) and topped>=0) t where rn<=? order by created_at,id",
+        ids_str
+    ))
+    .bind(limit)
+    .fetch_all(conn)
+    .await
+    .map_err(|e| api_errore(ApiErrorCode::InvalidDatabase, &e))?;
+
+    Ok(res)
+}
+
+pub async fn fetch_actived_by_topic(
+    conn: &mut SqlConnection,
+    topic_id: u64,
+    last_id: u64,
+    count: u32,
+) -> Result<Vec<ReplyModel>, ApiError> {
+    let res = sqlx::query_as::<_, ReplyModel>(
+        "select * from dg_replies where topic_id=? and id>? and topped>=0 order by id limit ?",
+    )
+    .bind(topic_id)
+    .bind(last_id)
+    .bind(count)
+    .fetch_all(conn)
+    .await
+    .map_err(|e| api_errore(ApiErrorCode::InvalidDatabase, &e))?;
+
+    Ok(res)
+}
+
+/// Replies stored before the rendered HTML was kept, as `(id, content_format, content)`.
+pub async fn fetch_unrendered_after(
+    conn: &mut SqlConnection,
+    last_id: u64,
+    count: u32,
+) -> Result<Vec<(u64, i8, String)>, ApiError> {
+    let res = sqlx::query_as(
+        "select id,content_format,content from dg_replies where id>? and content_html='' and content<>'' order by id limit ?",
+    )
+    .bind(last_id)
+    .bind(count)
+    .fetch_all(conn)
+    .await
+    .map_err(|e| api_errore(ApiErrorCode::InvalidDatabase, &e))?;
+
+    Ok(res)
+}
+
+pub async fn update_rendered(
+    conn: &mut SqlConnection,
+    id: u64,
+    content_html: &str,
+    excerpt: &str,
+) -> Result<(), ApiError> {
+    sqlx::query("update dg_replies set content_html=?,excerpt=? where id=?")
+        .bind(content_html)
+        .bind(excerpt)
+        .bind(id)
+        .execute(conn)
+        .await
+        .map_err(|e| api_errore(ApiErrorCode::InvalidDatabase, &e))?;
+
+    Ok(())
+}
+
+/// Ids of the rows changed after `(updated_at, id)`, in that order, leaving
+/// out those changed within the last `settle` seconds.
+pub async fn fetch_changed_after(
+    conn: &mut SqlConnection,
+    updated_at: SqlDateTime,
+    last_id: u64,
+    settle: u32,
+    count: u32,
+) -> Result<Vec<(u64, SqlDateTime)>, ApiError> {
+    let res = sqlx::query_as(
+        "select id,updated_at from dg_replies where (updated_at>? or (updated_at=? and id>?)) and updated_at<DATE_SUB(NOW(),INTERVAL ? SECOND) order by updated_at,id limit ?",
+    )
+    .bind(updated_at)
+    .bind(updated_at)
+    .bind(last_id)
+    .bind(settle)
+    .bind(count)
+    .fetch_all(conn)
+    .await
+    .map_err(|e| api_errore(ApiErrorCode::InvalidDatabase, &e))?;
+
+    Ok(res)
+}
+
+pub async fn fetch_actived_after(
+    conn: &mut SqlConnection,
+    app_id: u64,
+    last_id: u64,
+    count: u32,
+) -> Result<Vec<ReplyModel>, ApiError> {
+    let res = sqlx::query_as::<_, ReplyModel>(
+        "select * from dg_replies where app_id=? and id>? and topped>=0 order by id limit ?",
+    )
+    .bind(app_id)
+    .bind(last_id)
+    .bind(count)
+    .fetch_all(conn)
+    .await
+    .map_err(|e| api_errore(ApiErrorCode::InvalidDatabase, &e))?;
+
+    Ok(res)
+}
@@ -0,0 +1,126 @@
+use crate::shared::content;
+use crate::shared::data::*;
+use crate::shared::web::*;
+
+use super::reply::ReplyModel;
+
+pub const EDITOR_USER: i8 = 0;
+pub const EDITOR_MANAGER: i8 = 1;
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct ReplyRevisionModel {
+    pub id: u64,
+    pub app_id: u64,
+    pub reply_id: u64,
+    pub editor_type: i8,
+    pub editor_id: u64,
+    pub content_format: i8,
+    pub content: String,
+    pub created_at: SqlDateTime,
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct ReplyRevisionSimple {
+    pub id: u64,
+    pub editor_type: i8,
+    pub editor_id: u64,
+    pub created_at: SqlDateTime,
+}
+
+impl ReplyRevisionModel {
+    pub fn from_reply(reply: &ReplyModel, editor_type: i8, editor_id: u64) -> ReplyRevisionModel {
+        ReplyRevisionModel {
+            app_id: reply.app_id,
+            reply_id: reply.id,
+            editor_type,
+            editor_id,
+            content_format: reply.content_format,
+            content: reply.content.clone(),
+            ..Default::default()
+        }
+    }
+
+    pub fn to_simple(self: &Self) -> ReplyRevisionSimple {
+        ReplyRevisionSimple {
+            id: self.id,
+            editor_type: self.editor_type,
+            editor_id: self.editor_id,
+            created_at: self.created_at,
+        }
+    }
+}
+
+impl Default for ReplyRevisionModel {
+    fn default() -> ReplyRevisionModel {
+        ReplyRevisionModel {
+            id: 0,
+            app_id: 0,
+            reply_id: 0,
+            editor_type: EDITOR_USER,
+            editor_id: 0,
+            content_format: content::FORMAT_PLAIN,
+            content: String::new(),
+            created_at: SqlDateTime::MIN,
+        }
+    }
+}
+
+pub async fn get_by_id(conn: &mut SqlConnection, id: u64) -> Result<ReplyRevisionModel, ApiError> {
+    let res =
+        sqlx::query_as::<_, ReplyRevisionModel>("select * from dg_reply_revisions where id=?")
+            .bind(id)
+            .fetch_optional(conn)
+            .await
+            .map_err(|e| api_errore(ApiErrorCode::InvalidDatabase, &e))?;
+
+    if res.is_none() {
+        return Err(api_error(ApiErrorCode::RevisionNotFound));
+    }
+
+    Ok(res.unwrap())
+}
+
+pub async fn create(
+    conn: &mut SqlConnection,
+    revision: &mut ReplyRevisionModel,
+) -> Result<u64, ApiError> {
+    let res = sqlx::query(
+        "insert into dg_reply_revisions(app_id,reply_id,editor_type,editor_id,content_format,content) values(?,?,?,?,?,?)",
+    )
+    .bind(revision.app_id)
+    .bind(revision.reply_id)
+    .bind(revision.editor_type)
+    .bind(revision.editor_id)
+    .bind(revision.content_format)
+    .bind(&revision.content)
+    .execute(conn)
+    .await
+    .map_err(|e| api_errore(ApiErrorCode::InvalidDatabase, &e))?;
+
+    Ok(res.last_insert_id())
+}
+
+pub async fn fetch_pagging(
+    conn: &mut SqlConnection,
+    reply_id: u64,
+    cursor: u32,
+    count: u32,
+) -> Result<(u32, Vec<ReplyRevisionModel>), ApiError> {
+    let res = sqlx::query_as::<_, ReplyRevisionModel>(
+        "select * from dg_reply_revisions where reply_id=? order by id desc limit ?,?",
+    )
+    .bind(reply_id)
+    .bind(cursor)
+    .bind(count)
+    .fetch_all(conn.as_mut())
+    .await
+    .map_err(|e| api_errore(ApiErrorCode::InvalidDatabase, &e))?;
+
+    let total: (i64,) = sqlx::query_as("select count(*) from dg_reply_revisions where reply_id=?")
+        .bind(reply_id)
+        .fetch_one(conn.as_mut())
+        .await
+        .map_err(|e| api_errore(ApiErrorCode::InvalidDatabase, &e))?;
+
+    Ok((total.0 as u32, res))
+}
@@ -0,0 +1,463 @@
+use crate::shared::base::*;
+use crate::shared::data::*;
+use crate::shared::web::*;
+
+pub const MAX_TOPIC_TAGS: usize = 5;
+const MAX_NAME_CHARS: usize = 32;
+
+pub const STATUS_NORMAL: i8 = 0;
+pub const STATUS_BANNED: i8 = 1;
+
+#[derive(PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum VisibleStyle {
+    ALL = 0,
+    NORMAL = 1,
+}
+
+#[derive(PartialEq, Eq, Deserialize, Copy, Clone)]
+#[serde(rename_all = "lowercase")]
+pub enum StatusAction {
+    RESET = 0,
+    BAN = 1,
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct TagModel {
+    pub id: u64,
+    pub app_id: u64,
+    pub name: String,
+    pub status: i8,
+    pub merged_to: u64,
+    pub topic_count: u64,
+    pub created_at: SqlDateTime,
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct TagSimple {
+    pub id: u64,
+    pub name: String,
+    pub topic_count: u64,
+}
+
+impl TagModel {
+    pub fn to_simple(self: &Self) -> TagSimple {
+        TagSimple {
+            id: self.id,
+            name: self.name.clone(),
+            topic_count: self.topic_count,
+        }
+    }
+
+    pub fn is_actived(self: &Self) -> bool {
+        return self.status == STATUS_NORMAL && self.merged_to == 0;
+    }
+}
+
+impl Default for TagModel {
+    fn default() -> TagModel {
+        TagModel {
+            id: 0,
+            app_id: 0,
+            name: String::new(),
+            status: STATUS_NORMAL,
+            merged_to: 0,
+            topic_count: 0,
+            created_at: SqlDateTime::MIN,
+        }
+    }
+}
+
+fn is_name_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '-'
+}
+
+pub fn normalize_name(name: &str) -> Option<String> {
+    let out: String = name
+        .trim()
+        .trim_start_matches('#')
+        .chars()
+        .filter(|c| is_name_char(*c))
+        .flat_map(char::to_lowercase)
+        .take(MAX_NAME_CHARS)
+        .collect();
+
+    if out.is_empty() {
+        return None;
+    }
+
+    Some(out)
+}
+
+fn can_precede_hashtag(c: char) -> bool {
+    !(c.is_ascii_alphanumeric() || c == '_' || c == '/' || c == '&' || c == '#')
+}
+
+/// Finds `#hashtag` words in the content. A `#` glued to an ASCII word or a
+/// path (like an URL fragment) is skipped, CJK text needs no space before it
+/// and a closing `#` as in `#话题#` is consumed.
+pub fn extract_hashtags(content: &str) -> Vec<String> {
+    let mut out = Vec::new();
+    let mut prev: Option<char> = None;
+    let mut chars = content.char_indices().peekable();
+
+    while let Some((i, c)) = chars.next() {
+        if c != '#' || !prev.map_or(true, can_precede_hashtag) {
+            prev = Some(c);
+            continue;
+        }
+
+        let start = i + 1;
+        let mut end = start;
+        while let Some((j, n)) = chars.peek() {
+            if !is_name_char(*n) {
+                break;
+            }
+            end = j + n.len_utf8();
+            chars.next();
+        }
+        if end > start {
+            out.push(String::from(&content[start..end]));
+        }
+        if let Some((_, '#')) = chars.peek() {
+            chars.next();
+        }
+        prev = Some('#');
+    }
+
+    out
+}
+
+/// Explicit tags go first, then hashtags from the content, deduplicated
+/// after normalizing and capped at `MAX_TOPIC_TAGS`.
+pub fn collect_names(tags: Option<Vec<String>>, content: &str) -> Vec<String> {
+    let mut out: Vec<String> = Vec::new();
+    for name in tags
+        .unwrap_or_default()
+        .iter()
+        .chain(extract_hashtags(content).iter())
+    {
+        if out.len() >= MAX_TOPIC_TAGS {
+            break;
+        }
+        if let Some(name) = normalize_name(name) {
+            if !out.contains(&name) {
+                out.push(name);
+            }
+        }
+    }
+
+    out
+}
+
+pub async fn get_by_id(conn: &mut SqlConnection, id: u64) -> Result<TagModel, ApiError> {
+    let res = sqlx::query_as::<_, TagModel>("select * from dg_tags where id=?")
+        .bind(id)
+        .fetch_optional(conn)
+        .await
+        .map_err(|e| api_errore(ApiErrorCode::InvalidDatabase, &e))?;
+
+    if res.is_none() {
+        return Err(api_error(ApiErrorCode::TagNotFound));
+    }
+
+    Ok(res.unwrap())
+}
+
+pub async fn get_by_name(
+    conn: &mut SqlConnection,
+    app_id: u64,
+    name: &str,
+) -> Result<Option<TagModel>, ApiError> {
+    let res = sqlx::query_as::<_, TagModel>("select * from dg_tags where app_id=? and name=?")
+        .bind(app_id)
+        .bind(name)
+        .fetch_optional(conn)
+        .await
+        .map_err(|e| api_errore(ApiErrorCode::InvalidDatabase, &e))?;
+
+    Ok(res)
+}
+
+/// Returns the tag a topic should be linked to under this name, following a
+/// merge; `None` when the tag is banned.
+pub async fn get_or_create(
+    conn: &mut SqlConnection,
+    app_id: u64,
+    name: &str,
+) -> Result<Option<TagModel>, ApiError> {
+    sqlx::query("insert ignore into dg_tags(app_id,name,status) values(?,?,?)")
+        .bind(app_id)
+        .bind(name)
+        .bind(STATUS_NORMAL)
+        .execute(conn.as_mut())
+        .await
+        .map_err(|e| api_errore(ApiErrorCode::InvalidDatabase, &e))?;
+
+    let mut tag = get_by_name(conn, app_id, name).await?;
+    if let Some(o) = &tag {
+        if o.merged_to > 0 {
+            tag = Some(get_by_id(conn, o.merged_to).await?);
+        }
+    }
+
+    Ok(tag.filter(|o| o.status == STATUS_NORMAL))
+}
+
+/// Looks a tag up by a name typed by a user, following a merge.
+pub async fn get_actived_by_name(
+    conn: &mut SqlConnection,
+    app_id: u64,
+    name: &str,
+) -> Result<TagModel, ApiError> {
+    let name = normalize_name(name).ok_or(api_error(ApiErrorCode::TagNotFound))?;
+
+    let mut tag = get_by_name(conn, app_id, &name)
+        .await?
+        .ok_or(api_error(ApiErrorCode::TagNotFound))?;
+    if tag.merged_to > 0 {
+        tag = get_by_id(conn, tag.merged_to).await?;
+    }
+    if !tag.is_actived() {
+        return Err(api_error(ApiErrorCode::TagNotFound));
+    }
+
+    Ok(tag)
+}
+
+pub async fn get_simple_list_by_topic(
+    conn: &mut SqlConnection,
+    topic_id: u64,
+) -> Result<Vec<TagSimple>, ApiError> {
+    let res = sqlx::query_as::<_, TagSimple>(
+        "select t.id,t.name,t.topic_count from dg_topic_tags tt join dg_tags t on t.id=tt.tag_id where tt.topic_id=? and t.status=? order by tt.created_at",
+    )
+    .bind(topic_id)
+    .bind(STATUS_NORMAL)
+    .fetch_all(conn)
+    .await
+    .map_err(|e| api_errore(ApiErrorCode::InvalidDatabase, &e))?;
+
+    Ok(res)
+}
+
+/// Replaces the tags of the topic with the given names, creating tags that
+/// do not exist yet.
+pub async fn update_topic_tags(
+    conn: &mut SqlConnection,
+    app_id: u64,
+    topic_id: u64,
+    names: Vec<String>,
+) -> Result<(), ApiError> {
+    unlink_topic(conn, topic_id).await?;
+
+    let mut tag_ids = HashSet::new();
+    for name in names.iter() {
+        if let Some(tag) = get_or_create(conn, app_id, name).await? {
+            tag_ids.insert(tag.id);
+        }
+    }
+
+    for tag_id in tag_ids {
+        sqlx::query("insert into dg_topic_tags(topic_id,tag_id,app_id) values(?,?,?)")
+            .bind(topic_id)
+            .bind(tag_id)
+            .bind(app_id)
+            .execute(conn.as_mut())
+            .await
+            .map_err(|e| api_errore(ApiErrorCode::InvalidDatabase, &e))?;
+
+        update_topic_count(conn, tag_id, UpdateCountOp::INCR).await?;
+    }
+
+    Ok(())
+}
+
+pub async fn unlink_topic(conn: &mut SqlConnection, topic_id: u64) -> Result<(), ApiError> {
+    let tag_ids: Vec<(u64,)> = sqlx::query_as("select tag_id from dg_topic_tags where topic_id=?")
+        .bind(topic_id)
+        .fetch_all(conn.as_mut())
+        .await
+        .map_err(|e| api_errore(ApiErrorCode::InvalidDatabase, &e))?;
+
+    sqlx::query("delete from dg_topic_tags where topic_id=?")
+        .bind(topic_id)
+        .execute(conn.as_mut())
+        .await
+        .map_err(|e| api_errore(ApiErrorCode::InvalidDatabase, &e))?;
+
+    for (tag_id,) in tag_ids {
+        update_topic_count(conn, tag_id, UpdateCountOp::DECR).await?;
+    }
+
+    Ok(())
+}
+
+pub async fn update_topic_count(
+    conn: &mut SqlConnection,
+    id: u64,
+    op: UpdateCountOp,
+) -> Result<(), ApiError> {
+    let mut sql = String::new();
+    sql.push_str("update dg_tags set topic_count=topic_count");
+
+    let part_sql = match op {
+        UpdateCountOp::INCR => "+1",
+        _ => "-1",
+    };
+    sql.push_str(part_sql);
+    sql.push_str(" where id=?");
+
+    sqlx::query(&sql)
+        .bind(id)
+        .execute(conn)
+        .await
+        .map_err(|e| api_errore(ApiErrorCode::InvalidDatabase, &e))?;
+
+    Ok(())
+}
+
+pub async fn update_status(
+    conn: &mut SqlConnection,
+    id: u64,
+    action: StatusAction,
+) -> Result<(), ApiError> {
+    let status = match action {
+        StatusAction::BAN => STATUS_BANNED,
+        _ => STATUS_NORMAL,
+    };
+
+    sqlx::query("update dg_tags set status=? where id=?")
+        .bind(status)
+        .bind(id)
+        .execute(conn)
+        .await
+        .map_err(|e| api_errore(ApiErrorCode::InvalidDatabase, &e))?;
+
+    Ok(())
+}
+
+/// Moves every topic of `from` onto `to`, later lookups of the old name are
+/// redirected through `merged_to`. All or nothing, so no topic loses a tag.
+pub async fn merge(
+    conn: &mut SqlConnection,
+    from: &TagModel,
+    to: &TagModel,
+) -> Result<(), ApiError> {
+    let mut tx = database_begin(conn).await?;
+
+    sqlx::query(
+        "insert ignore into dg_topic_tags(topic_id,tag_id,app_id,created_at) select topic_id,?,app_id,created_at from dg_topic_tags where tag_id=?",
+    )
+    .bind(to.id)
+    .bind(from.id)
+    .execute(&mut *tx)
+    .await
+    .map_err(|e| api_errore(ApiErrorCode::InvalidDatabase, &e))?;
+
+    sqlx::query("delete from dg_topic_tags where tag_id=?")
+        .bind(from.id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| api_errore(ApiErrorCode::InvalidDatabase, &e))?;
+
+    sqlx::query("update dg_tags set merged_to=?,topic_count=0 where id=? or merged_to=?")
+        .bind(to.id)
+        .bind(from.id)
+        .bind(from.id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| api_errore(ApiErrorCode::InvalidDatabase, &e))?;
+
+    sqlx::query(
+        "update dg_tags set topic_count=(select count(*) from dg_topic_tags where tag_id=?) where id=?",
+    )
+    .bind(to.id)
+    .bind(to.id)
+    .execute(&mut *tx)
+    .await
+    .map_err(|e| api_errore(ApiErrorCode::InvalidDatabase, &e))?;
+
+    database_commit(tx).await
+}
+
+pub async fn fetch_pagging(
+    conn: &mut SqlConnection,
+    app_id: u64,
+    style: VisibleStyle,
+    cursor: u32,
+    count: u32,
+) -> Result<(u32, Vec<TagModel>), ApiError> {
+    let mut fetch_sql = String::new();
+    let mut count_sql = String::new();
+
+    fetch_sql.push_str("select * from dg_tags where app_id=?");
+    count_sql.push_str("select count(*) from dg_tags where app_id=?");
+
+    let part_sql = match style {
+        VisibleStyle::NORMAL => " and status=0 and merged_to=0",
+        _ => "",
+    };
+    fetch_sql.push_str(part_sql);
+    count_sql.push_str(part_sql);
+
+    fetch_sql.push_str(" order by topic_count desc,id desc limit ?,?");
+
+    let tags = sqlx::query_as::<_, TagModel>(&fetch_sql)
+        .bind(app_id)
+        .bind(cursor)
+        .bind(count)
+        .fetch_all(conn.as_mut())
+        .await
+        .map_err(|e| api_errore(ApiErrorCode::InvalidDatabase, &e))?;
+
+    let total: (i64,) = sqlx::query_as(&count_sql)
+        .bind(app_id)
+        .fetch_one(conn.as_mut())
+        .await
+        .map_err(|e| api_errore(ApiErrorCode::InvalidDatabase, &e))?;
+
+    Ok((total.0 as u32, tags))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn extract_hashtags_splits_words() {
+        assert_eq!(
+            extract_hashtags("hello #rust and #Web-dev, #snake_case!"),
+            vec!["rust", "Web-dev", "snake_case"]
+        );
+        assert!(extract_hashtags("## # spaced").is_empty());
+    }
+
+    #[test]
+    fn extract_hashtags_skips_glued_and_paths() {
+        assert!(extract_hashtags("a#b C#").is_empty());
+        assert!(extract_hashtags("see https://example.com/#frag or a/#b").is_empty());
+        assert!(extract_hashtags("it&#39;s").is_empty());
+    }
+
+    #[test]
+    fn extract_hashtags_handles_cjk() {
+        assert_eq!(extract_hashtags("今天#话题#真好"), vec!["话题"]);
+        assert_eq!(extract_hashtags("学习#Rust编程"), vec!["Rust编程"]);
+        assert_eq!(extract_hashtags("#话题#其他#标签"), vec!["话题", "标签"]);
+    }
+
+    #[test]
+    fn collect_names_normalizes_and_caps() {
+        assert_eq!(
+            collect_names(Some(vec![String::from(" #Rust ")]), "#rust #Go"),
+            vec!["rust", "go"]
+        );
+
+        let content = (0..MAX_TOPIC_TAGS + 3)
+            .map(|i| format!("#t{}", i))
+            .collect::<Vec<_>>()
+            .join(" ");
+        assert_eq!(collect_names(None, &content).len(), MAX_TOPIC_TAGS);
+    }
+}
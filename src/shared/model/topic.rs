@@ -1,9 +1,16 @@
 use crate::shared::base::*;
+use crate::shared::content;
 use crate::shared::data::*;
+use crate::shared::reaction::{DEFAULT_REACTION, DOWNVOTE_REACTION, TARGET_TOPIC};
 use crate::shared::web::*;
 
-const STATUS_HIDDEN: i64 = -1;
+pub const STATUS_HIDDEN: i64 = -1;
 const STATUS_DELETE: i64 = -2;
+const STATUS_DELETE_AUTHOR: i64 = -3;
+
+pub const LOCKED_BY_AUTHOR: i8 = 1;
+pub const LOCKED_BY_MANAGER: i8 = 2;
+pub const LOCKED_BY_AUTO: i8 = 3;
 
 #[derive(PartialEq, Eq, Deserialize)]
 #[serde(rename_all = "lowercase")]
@@ -17,8 +24,41 @@ pub enum VisibleStyle {
 pub enum VisibleOrderBy {
     CREATE = 0,
     REFRESH = 1,
+    HOT = 2,
+    TOP = 3,
+    VIEW = 4,
+    SCORE = 5,
+}
+
+#[derive(PartialEq, Eq, Deserialize, Copy, Clone)]
+#[serde(rename_all = "lowercase")]
+pub enum VisibleTopWindow {
+    DAY = 0,
+    WEEK = 1,
+    MONTH = 2,
+}
+
+impl Default for VisibleTopWindow {
+    fn default() -> Self {
+        VisibleTopWindow::WEEK
+    }
+}
+
+impl VisibleTopWindow {
+    pub fn seconds(self: &Self) -> u32 {
+        match self {
+            VisibleTopWindow::DAY => 24 * 3600,
+            VisibleTopWindow::WEEK => 7 * 24 * 3600,
+            VisibleTopWindow::MONTH => 30 * 24 * 3600,
+        }
+    }
 }
 
+/// Hacker News style gravity, kept in sync with `ranking::hot_score`. The
+/// current UTC time is bound to `?` so both agree on the age of a topic.
+pub const HOT_SCORE_SQL: &'static str =
+    "(like_count+reply_count*2)/POW(GREATEST(TIMESTAMPDIFF(HOUR,created_at,?),0)+2,1.5)";
+
 #[derive(PartialEq, Eq, Deserialize, Copy, Clone)]
 #[serde(rename_all = "lowercase")]
 pub enum StatusAction {
@@ -28,6 +68,12 @@ pub enum StatusAction {
     DELETE = 3,
 }
 
+#[derive(PartialEq, Eq, Copy, Clone)]
+pub enum StatusOperator {
+    MANAGER = 0,
+    AUTHOR = 1,
+}
+
 #[derive(Debug, Serialize, sqlx::FromRow)]
 pub struct TopicModel {
     pub id: u64,
@@ -35,26 +81,56 @@ pub struct TopicModel {
     pub user_id: u64,
     pub category: u64,
     pub title: String,
+    pub content_format: i8,
     pub content: String,
+    pub content_html: String,
+    pub excerpt: String,
     pub topped: i64,
     pub created_at: SqlDateTime,
     pub refreshed_at: SqlDateTime,
     pub like_count: u64,
+    pub score: i64,
     pub reply_count: u64,
+    pub view_count: u64,
+    pub bookmark_count: u64,
+    pub locked: bool,
+    pub locked_by: i8,
+    pub moved_to: u64,
+    pub accepted_reply_id: u64,
+    pub reaction_counts: String,
+    pub edited_at: Option<SqlDateTime>,
+    pub publish_at: Option<SqlDateTime>,
+    pub topped_until: Option<SqlDateTime>,
 }
 
-#[derive(Debug, Serialize, sqlx::FromRow)]
+#[derive(Debug, Serialize)]
 pub struct TopicSimple {
     pub id: u64,
     pub user_id: u64,
     pub category: u64,
     pub title: String,
-    pub content: String,
+    pub content_format: i8,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content_html: Option<String>,
+    pub excerpt: String,
     pub topped: i64,
     pub created_at: SqlDateTime,
     pub refreshed_at: SqlDateTime,
     pub like_count: u64,
+    pub score: i64,
     pub reply_count: u64,
+    pub view_count: u64,
+    pub bookmark_count: u64,
+    pub locked: bool,
+    pub locked_by: i8,
+    pub moved_to: u64,
+    pub accepted_reply_id: u64,
+    pub reaction_counts: HashMap<String, u64>,
+    pub edited_at: Option<SqlDateTime>,
+    pub publish_at: Option<SqlDateTime>,
+    pub topped_until: Option<SqlDateTime>,
 }
 
 impl TopicModel {
@@ -64,15 +140,42 @@ impl TopicModel {
             user_id: self.user_id,
             category: self.category,
             title: self.title.clone(),
-            content: self.content.clone(),
+            content_format: self.content_format,
+            content: Some(self.content.clone()),
+            content_html: Some(self.content_html.clone()),
+            excerpt: self.excerpt.clone(),
             topped: self.topped,
             created_at: self.created_at,
             refreshed_at: self.refreshed_at,
             like_count: self.like_count,
+            score: self.score,
+            reaction_counts: self.reaction_map(),
             reply_count: self.reply_count,
+            view_count: self.view_count,
+            bookmark_count: self.bookmark_count,
+            locked: self.locked,
+            locked_by: self.locked_by,
+            moved_to: self.moved_to,
+            accepted_reply_id: self.accepted_reply_id,
+            edited_at: self.edited_at,
+            publish_at: self.publish_at,
+            topped_until: self.topped_until,
+        }
+    }
+
+    /// Same as `to_simple` without the full content, for lists.
+    pub fn to_preview(self: &Self) -> TopicSimple {
+        TopicSimple {
+            content: None,
+            content_html: None,
+            ..self.to_simple()
         }
     }
 
+    pub fn reaction_map(self: &Self) -> HashMap<String, u64> {
+        serde_json::from_str(&self.reaction_counts).unwrap_or_default()
+    }
+
     pub fn is_actived(self: &Self) -> bool {
         return self.topped >= 0;
     }
@@ -90,12 +193,26 @@ impl Default for TopicModel {
             user_id: 0,
             category: 0,
             title: String::new(),
+            content_format: content::FORMAT_PLAIN,
             content: String::new(),
+            content_html: String::new(),
+            excerpt: String::new(),
             topped: 0,
             created_at: SqlDateTime::MIN,
             refreshed_at: SqlDateTime::MIN,
             like_count: 0,
+            score: 0,
+            reaction_counts: String::from("{}"),
             reply_count: 0,
+            view_count: 0,
+            bookmark_count: 0,
+            locked: false,
+            locked_by: 0,
+            moved_to: 0,
+            accepted_reply_id: 0,
+            edited_at: None,
+            publish_at: None,
+            topped_until: None,
         }
     }
 }
@@ -114,15 +231,53 @@ pub async fn get_by_id(conn: &mut SqlConnection, id: u64) -> Result<TopicModel,
     Ok(res.unwrap())
 }
 
+pub async fn get_map_by_ids(
+    conn: &mut SqlConnection,
+    ids: Vec<u64>,
+) -> Result<ArcDataMap<u64, TopicModel>, ApiError> {
+    let mut out = ArcDataMap::new();
+    if ids.len() < 1 {
+        return Ok(out);
+    }
+
+    let ids_str = ids
+        .iter()
+        .map(ToString::to_string)
+        .collect::<HashSet<_>>()
+        .into_iter()
+        .collect::<Vec<_>>()
+        .join(",");
+    let res = sqlx::query_as::<_, TopicModel>(&format!(
+        "select * from dg_topics where id in ({})",
+        ids_str
+    ))
+    .fetch_all(conn)
+    .await
+    .map_err(|e| api_errore(ApiErrorCode::InvalidDatabase, &e))?;
+
+    for o in res {
+        out.insert(o.id, o);
+    }
+
+    Ok(out)
+}
+
 pub async fn create(conn: &mut SqlConnection, topic: &mut TopicModel) -> Result<u64, ApiError> {
+    (topic.content_html, topic.excerpt) = content::render(topic.content_format, &topic.content);
+
     let res = sqlx::query(
-        "insert into dg_topics(app_id,user_id,category,title,content,topped,refreshed_at) values(?,?,?,?,?,0,NOW())",
+        "insert into dg_topics(app_id,user_id,category,title,content_format,content,content_html,excerpt,topped,publish_at,refreshed_at) values(?,?,?,?,?,?,?,?,?,?,NOW())",
     )
     .bind(topic.app_id)
     .bind(topic.user_id)
     .bind(topic.category)
     .bind(&topic.title)
+    .bind(topic.content_format)
     .bind(&topic.content)
+    .bind(&topic.content_html)
+    .bind(&topic.excerpt)
+    .bind(topic.topped)
+    .bind(topic.publish_at)
     .execute(conn)
     .await
     .map_err(|e| api_errore(ApiErrorCode::InvalidDatabase, &e))?;
@@ -130,13 +285,83 @@ pub async fn create(conn: &mut SqlConnection, topic: &mut TopicModel) -> Result<
     Ok(res.last_insert_id())
 }
 
+pub async fn update_category(
+    conn: &mut SqlRawConnection,
+    id: u64,
+    category: u64,
+) -> Result<(), ApiError> {
+    sqlx::query("update dg_topics set category=? where id=?")
+        .bind(category)
+        .bind(id)
+        .execute(&mut *conn)
+        .await
+        .map_err(|e| api_errore(ApiErrorCode::InvalidDatabase, &e))?;
+
+    // the replies are indexed under the category of their topic
+    sqlx::query("update dg_replies set updated_at=NOW() where topic_id=?")
+        .bind(id)
+        .execute(&mut *conn)
+        .await
+        .map_err(|e| api_errore(ApiErrorCode::InvalidDatabase, &e))?;
+
+    Ok(())
+}
+
+/// Leaves a locked, empty topic in `category` that points at the moved one,
+/// dated like the original so it keeps its place in the old listing.
+pub async fn create_placeholder(
+    conn: &mut SqlRawConnection,
+    topic: &TopicModel,
+    category: u64,
+) -> Result<u64, ApiError> {
+    let res = sqlx::query(
+        "insert into dg_topics(app_id,user_id,category,title,content_format,content,content_html,excerpt,topped,locked,locked_by,moved_to,created_at,refreshed_at) values(?,?,?,?,?,'','','',0,1,?,?,?,?)",
+    )
+    .bind(topic.app_id)
+    .bind(topic.user_id)
+    .bind(category)
+    .bind(&topic.title)
+    .bind(content::FORMAT_PLAIN)
+    .bind(LOCKED_BY_MANAGER)
+    .bind(topic.id)
+    .bind(topic.created_at)
+    .bind(topic.refreshed_at)
+    .execute(conn)
+    .await
+    .map_err(|e| api_errore(ApiErrorCode::InvalidDatabase, &e))?;
+
+    Ok(res.last_insert_id())
+}
+
+pub async fn update_content(
+    conn: &mut SqlRawConnection,
+    topic: &mut TopicModel,
+) -> Result<(), ApiError> {
+    (topic.content_html, topic.excerpt) = content::render(topic.content_format, &topic.content);
+
+    sqlx::query(
+        "update dg_topics set title=?,content_format=?,content=?,content_html=?,excerpt=?,edited_at=NOW() where id=?",
+    )
+    .bind(&topic.title)
+    .bind(topic.content_format)
+    .bind(&topic.content)
+    .bind(&topic.content_html)
+    .bind(&topic.excerpt)
+    .bind(topic.id)
+    .execute(conn)
+    .await
+    .map_err(|e| api_errore(ApiErrorCode::InvalidDatabase, &e))?;
+
+    Ok(())
+}
+
 pub async fn update_reply_count(
     conn: &mut SqlConnection,
     id: u64,
     op: UpdateCountOp,
 ) -> Result<(), ApiError> {
     let mut sql = String::new();
-    sql.push_str("update dg_topics set reply_count=reply_count");
+    sql.push_str("update dg_topics set updated_at=updated_at,reply_count=reply_count");
 
     let part_sql = match op {
         UpdateCountOp::INCR => "+1,refreshed_at=NOW()",
@@ -154,20 +379,94 @@ pub async fn update_reply_count(
     Ok(())
 }
 
-pub async fn update_like_count(
+/// Adds `op` to the count of the reaction, the default reaction also keeps
+/// `like_count` up to date for the rankings. Likes and downvotes move `score`.
+pub async fn update_reaction_count(
+    conn: &mut SqlConnection,
+    id: u64,
+    reaction: &str,
+    op: UpdateCountOp,
+) -> Result<(), ApiError> {
+    let mut sql = String::new();
+    sql.push_str(
+        "update dg_topics set updated_at=updated_at,reaction_counts=JSON_SET(reaction_counts,?,GREATEST(CAST(COALESCE(JSON_EXTRACT(reaction_counts,?),0) AS SIGNED)+?,0))",
+    );
+
+    let delta = match op {
+        UpdateCountOp::INCR => 1,
+        _ => -1,
+    };
+    if reaction == DEFAULT_REACTION {
+        sql.push_str(match op {
+            UpdateCountOp::INCR => ",like_count=like_count+1,score=score+1",
+            _ => ",like_count=like_count-1,score=score-1",
+        });
+    } else if reaction == DOWNVOTE_REACTION {
+        sql.push_str(match op {
+            UpdateCountOp::INCR => ",score=score-1",
+            _ => ",score=score+1",
+        });
+    }
+    sql.push_str(" where id=?");
+
+    let path = format!("$.\"{}\"", reaction);
+    sqlx::query(&sql)
+        .bind(&path)
+        .bind(&path)
+        .bind(delta)
+        .bind(id)
+        .execute(conn)
+        .await
+        .map_err(|e| api_errore(ApiErrorCode::InvalidDatabase, &e))?;
+
+    Ok(())
+}
+
+/// Seeds `reaction_counts` from the likes counted before reactions existed.
+pub async fn init_reaction_counts(conn: &mut SqlConnection) -> Result<(), ApiError> {
+    sqlx::query(
+        "update dg_topics set updated_at=updated_at,reaction_counts=JSON_OBJECT(?,like_count) where like_count>0 and reaction_counts='{}'",
+    )
+    .bind(DEFAULT_REACTION)
+    .execute(conn)
+    .await
+    .map_err(|e| api_errore(ApiErrorCode::InvalidDatabase, &e))?;
+
+    Ok(())
+}
+
+/// Repairs `like_count` and `score` drifted from the likes and downvotes
+/// stored in `dg_likes`, returns how many topics were off.
+pub async fn reconcile_like_count(conn: &mut SqlConnection) -> Result<u64, ApiError> {
+    let res = sqlx::query(
+        "update dg_topics t left join (select target_id,sum(reaction=?) as likes,sum(reaction=?) as downvotes from dg_likes where target_type=? and reaction in (?,?) group by target_id) l on l.target_id=t.id set t.updated_at=t.updated_at,t.like_count=IFNULL(l.likes,0),t.score=IFNULL(l.likes,0)-IFNULL(l.downvotes,0),t.reaction_counts=JSON_SET(t.reaction_counts,?,IFNULL(l.likes,0)) where t.like_count<>IFNULL(l.likes,0) or t.score<>IFNULL(l.likes,0)-IFNULL(l.downvotes,0)",
+    )
+    .bind(DEFAULT_REACTION)
+    .bind(DOWNVOTE_REACTION)
+    .bind(TARGET_TOPIC)
+    .bind(DEFAULT_REACTION)
+    .bind(DOWNVOTE_REACTION)
+    .bind(format!("$.\"{}\"", DEFAULT_REACTION))
+    .execute(conn)
+    .await
+    .map_err(|e| api_errore(ApiErrorCode::InvalidDatabase, &e))?;
+
+    Ok(res.rows_affected())
+}
+
+pub async fn update_bookmark_count(
     conn: &mut SqlConnection,
     id: u64,
     op: UpdateCountOp,
 ) -> Result<(), ApiError> {
     let mut sql = String::new();
-    sql.push_str("update dg_topics set like_count=like_count");
+    sql.push_str("update dg_topics set updated_at=updated_at,bookmark_count=bookmark_count");
 
     let part_sql = match op {
-        UpdateCountOp::INCR => "+1",
-        _ => "-1",
+        UpdateCountOp::INCR => "+1 where id=?",
+        _ => "-1 where id=? and bookmark_count>0",
     };
     sql.push_str(part_sql);
-    sql.push_str(" where id=?");
 
     sqlx::query(&sql)
         .bind(id)
@@ -178,10 +477,26 @@ pub async fn update_like_count(
     Ok(())
 }
 
+pub async fn update_view_count(
+    conn: &mut SqlConnection,
+    id: u64,
+    count: u64,
+) -> Result<(), ApiError> {
+    sqlx::query("update dg_topics set updated_at=updated_at,view_count=view_count+? where id=?")
+        .bind(count)
+        .bind(id)
+        .execute(conn)
+        .await
+        .map_err(|e| api_errore(ApiErrorCode::InvalidDatabase, &e))?;
+
+    Ok(())
+}
+
 pub async fn update_status(
     conn: &mut SqlConnection,
     id: u64,
     action: StatusAction,
+    operator: StatusOperator,
 ) -> Result<(), ApiError> {
     let mut topped: i64 = 0;
     if action == StatusAction::MOVEUP {
@@ -189,12 +504,105 @@ pub async fn update_status(
     } else if action == StatusAction::HIDDEN {
         topped = STATUS_HIDDEN;
     } else if action == StatusAction::DELETE {
-        topped = STATUS_DELETE;
+        topped = match operator {
+            StatusOperator::AUTHOR => STATUS_DELETE_AUTHOR,
+            _ => STATUS_DELETE,
+        };
     }
 
-    sqlx::query("update dg_topics set topped=? where id=?")
+    sqlx::query("update dg_topics set topped=?,topped_until=NULL,publish_at=NULL where id=?")
         .bind(topped)
         .bind(id)
+        .execute(conn.as_mut())
+        .await
+        .map_err(|e| api_errore(ApiErrorCode::InvalidDatabase, &e))?;
+
+    // replies are searchable only while their topic is visible
+    sqlx::query("update dg_replies set updated_at=NOW() where topic_id=?")
+        .bind(id)
+        .execute(conn.as_mut())
+        .await
+        .map_err(|e| api_errore(ApiErrorCode::InvalidDatabase, &e))?;
+
+    Ok(())
+}
+
+/// Returns false when the topic was locked already.
+pub async fn lock(conn: &mut SqlConnection, id: u64, locked_by: i8) -> Result<bool, ApiError> {
+    let res = sqlx::query("update dg_topics set locked=1,locked_by=? where id=? and locked=0")
+        .bind(locked_by)
+        .bind(id)
+        .execute(conn)
+        .await
+        .map_err(|e| api_errore(ApiErrorCode::InvalidDatabase, &e))?;
+
+    Ok(res.rows_affected() > 0)
+}
+
+/// Returns false when the topic was not locked, or not by `locked_by` when
+/// given. `locked_by` is kept to tell topics unlocked by hand.
+pub async fn unlock(
+    conn: &mut SqlConnection,
+    id: u64,
+    locked_by: Option<i8>,
+) -> Result<bool, ApiError> {
+    let res = match locked_by {
+        Some(locked_by) => {
+            sqlx::query("update dg_topics set locked=0 where id=? and locked=1 and locked_by=?")
+                .bind(id)
+                .bind(locked_by)
+        }
+        None => sqlx::query("update dg_topics set locked=0 where id=? and locked=1").bind(id),
+    }
+    .execute(conn)
+    .await
+    .map_err(|e| api_errore(ApiErrorCode::InvalidDatabase, &e))?;
+
+    Ok(res.rows_affected() > 0)
+}
+
+/// Sets when a `MOVEUP` pin expires, call it right after `update_status`.
+pub async fn update_topped_until(
+    conn: &mut SqlConnection,
+    id: u64,
+    topped_until: Option<SqlDateTime>,
+) -> Result<(), ApiError> {
+    sqlx::query("update dg_topics set topped_until=? where id=? and topped>0")
+        .bind(topped_until)
+        .bind(id)
+        .execute(conn)
+        .await
+        .map_err(|e| api_errore(ApiErrorCode::InvalidDatabase, &e))?;
+
+    Ok(())
+}
+
+/// Marks the accepted answer, 0 clears it.
+pub async fn update_accepted_reply(
+    conn: &mut SqlConnection,
+    id: u64,
+    reply_id: u64,
+) -> Result<(), ApiError> {
+    sqlx::query("update dg_topics set accepted_reply_id=? where id=?")
+        .bind(reply_id)
+        .bind(id)
+        .execute(conn)
+        .await
+        .map_err(|e| api_errore(ApiErrorCode::InvalidDatabase, &e))?;
+
+    Ok(())
+}
+
+/// Clears the accepted answer when it is the given reply, used when the
+/// reply is deleted.
+pub async fn clear_accepted_reply(
+    conn: &mut SqlConnection,
+    id: u64,
+    reply_id: u64,
+) -> Result<(), ApiError> {
+    sqlx::query("update dg_topics set accepted_reply_id=0 where id=? and accepted_reply_id=?")
+        .bind(id)
+        .bind(reply_id)
         .execute(conn)
         .await
         .map_err(|e| api_errore(ApiErrorCode::InvalidDatabase, &e))?;
@@ -202,12 +610,66 @@ pub async fn update_status(
     Ok(())
 }
 
+/// Makes a scheduled topic visible, it is dated as if created right now.
+pub async fn publish(conn: &mut SqlConnection, id: u64) -> Result<(), ApiError> {
+    sqlx::query(
+        "update dg_topics set topped=0,publish_at=NULL,created_at=NOW(),refreshed_at=NOW() where id=? and topped=?",
+    )
+    .bind(id)
+    .bind(STATUS_HIDDEN)
+    .execute(conn)
+    .await
+    .map_err(|e| api_errore(ApiErrorCode::InvalidDatabase, &e))?;
+
+    Ok(())
+}
+
+pub async fn fetch_due_published(conn: &mut SqlConnection) -> Result<Vec<u64>, ApiError> {
+    let res: Vec<(u64,)> = sqlx::query_as(
+        "select id from dg_topics where topped=? and publish_at<=NOW() order by publish_at limit 100",
+    )
+    .bind(STATUS_HIDDEN)
+    .fetch_all(conn)
+    .await
+    .map_err(|e| api_errore(ApiErrorCode::InvalidDatabase, &e))?;
+
+    Ok(res.into_iter().map(|s| s.0).collect())
+}
+
+/// Locks topics without replies for longer than `auto_lock_days` of their app.
+pub async fn lock_inactive(conn: &mut SqlConnection) -> Result<u64, ApiError> {
+    // topics unlocked by hand keep their locked_by and are left alone
+    let res = sqlx::query(
+        "update dg_topics t join dg_apps a on a.id=t.app_id set t.locked=1,t.locked_by=? where a.auto_lock_days>0 and t.locked=0 and t.locked_by=0 and t.topped>=0 and t.refreshed_at<DATE_SUB(NOW(),INTERVAL a.auto_lock_days DAY)",
+    )
+    .bind(LOCKED_BY_AUTO)
+    .execute(conn)
+    .await
+    .map_err(|e| api_errore(ApiErrorCode::InvalidDatabase, &e))?;
+
+    Ok(res.rows_affected())
+}
+
+pub async fn unpin_expired(conn: &mut SqlConnection) -> Result<u64, ApiError> {
+    let res = sqlx::query(
+        "update dg_topics set topped=0,topped_until=NULL where topped>0 and topped_until<=NOW()",
+    )
+    .execute(conn)
+    .await
+    .map_err(|e| api_errore(ApiErrorCode::InvalidDatabase, &e))?;
+
+    Ok(res.rows_affected())
+}
+
 pub async fn fetch_pagging(
     conn: &mut SqlConnection,
     app_id: u64,
     category: u64,
+    tag_id: u64,
+    solved: Option<bool>,
     style: VisibleStyle,
     order_by: VisibleOrderBy,
+    window: VisibleTopWindow,
     cursor: u32,
     count: u32,
 ) -> Result<(u32, Vec<TopicModel>), ApiError> {
@@ -226,6 +688,22 @@ pub async fn fetch_pagging(
         part_binds.push(category);
     }
 
+    if tag_id > 0 {
+        let part_sql = " and id in (select topic_id from dg_topic_tags where tag_id=?)";
+        fetch_sql.push_str(part_sql);
+        count_sql.push_str(part_sql);
+        part_binds.push(tag_id);
+    }
+
+    if let Some(solved) = solved {
+        let part_sql = match solved {
+            true => " and accepted_reply_id>0",
+            _ => " and accepted_reply_id=0",
+        };
+        fetch_sql.push_str(part_sql);
+        count_sql.push_str(part_sql);
+    }
+
     let part_sql = match style {
         VisibleStyle::NORMAL => " and topped>=0",
         _ => " and topped>-2",
@@ -233,18 +711,32 @@ pub async fn fetch_pagging(
     fetch_sql.push_str(part_sql);
     count_sql.push_str(part_sql);
 
-    fetch_sql.push_str(" order by ");
+    if order_by == VisibleOrderBy::TOP {
+        let part_sql = " and (topped>0 or created_at>=DATE_SUB(NOW(),INTERVAL ? SECOND))";
+        fetch_sql.push_str(part_sql);
+        count_sql.push_str(part_sql);
+        part_binds.push(window.seconds() as u64);
+    }
+
+    fetch_sql.push_str(" order by topped desc,");
     let part_sql = match order_by {
-        VisibleOrderBy::REFRESH => "created_at desc",
-        _ => "topped desc,refreshed_at desc",
+        VisibleOrderBy::CREATE => "created_at desc".to_string(),
+        VisibleOrderBy::REFRESH => "refreshed_at desc".to_string(),
+        VisibleOrderBy::HOT => format!("{} desc,id desc", HOT_SCORE_SQL),
+        VisibleOrderBy::TOP => "like_count desc,id desc".to_string(),
+        VisibleOrderBy::VIEW => "view_count desc,id desc".to_string(),
+        VisibleOrderBy::SCORE => "score desc,id desc".to_string(),
     };
-    fetch_sql.push_str(part_sql);
+    fetch_sql.push_str(&part_sql);
     fetch_sql.push_str(" limit ?,?");
 
     let mut query = sqlx::query_as::<_, TopicModel>(&fetch_sql);
     for v in part_binds.iter() {
         query = query.bind(v);
     }
+    if order_by == VisibleOrderBy::HOT {
+        query = query.bind(sql_datetime(timestamp()));
+    }
     let topics = query
         .bind(cursor)
         .bind(count)
@@ -263,3 +755,145 @@ pub async fn fetch_pagging(
 
     Ok((total.0 as u32, topics))
 }
+
+/// Topics stored before the rendered HTML was kept, as `(id, content_format, content)`.
+pub async fn fetch_unrendered_after(
+    conn: &mut SqlConnection,
+    last_id: u64,
+    count: u32,
+) -> Result<Vec<(u64, i8, String)>, ApiError> {
+    let res = sqlx::query_as(
+        "select id,content_format,content from dg_topics where id>? and content_html='' and content<>'' order by id limit ?",
+    )
+    .bind(last_id)
+    .bind(count)
+    .fetch_all(conn)
+    .await
+    .map_err(|e| api_errore(ApiErrorCode::InvalidDatabase, &e))?;
+
+    Ok(res)
+}
+
+pub async fn update_rendered(
+    conn: &mut SqlConnection,
+    id: u64,
+    content_html: &str,
+    excerpt: &str,
+) -> Result<(), ApiError> {
+    sqlx::query("update dg_topics set content_html=?,excerpt=? where id=?")
+        .bind(content_html)
+        .bind(excerpt)
+        .bind(id)
+        .execute(conn)
+        .await
+        .map_err(|e| api_errore(ApiErrorCode::InvalidDatabase, &e))?;
+
+    Ok(())
+}
+
+/// Ids of the rows changed after `(updated_at, id)`, in that order, leaving
+/// out those changed within the last `settle` seconds.
+pub async fn fetch_changed_after(
+    conn: &mut SqlConnection,
+    updated_at: SqlDateTime,
+    last_id: u64,
+    settle: u32,
+    count: u32,
+) -> Result<Vec<(u64, SqlDateTime)>, ApiError> {
+    let res = sqlx::query_as(
+        "select id,updated_at from dg_topics where (updated_at>? or (updated_at=? and id>?)) and updated_at<DATE_SUB(NOW(),INTERVAL ? SECOND) order by updated_at,id limit ?",
+    )
+    .bind(updated_at)
+    .bind(updated_at)
+    .bind(last_id)
+    .bind(settle)
+    .bind(count)
+    .fetch_all(conn)
+    .await
+    .map_err(|e| api_errore(ApiErrorCode::InvalidDatabase, &e))?;
+
+    Ok(res)
+}
+
+pub async fn fetch_actived_after(
+    conn: &mut SqlConnection,
+    app_id: u64,
+    last_id: u64,
+    count: u32,
+) -> Result<Vec<TopicModel>, ApiError> {
+    let res = sqlx::query_as::<_, TopicModel>(
+        "select * from dg_topics where app_id=? and id>? and topped>=0 and moved_to=0 order by id limit ?",
+    )
+    .bind(app_id)
+    .bind(last_id)
+    .bind(count)
+    .fetch_all(conn)
+    .await
+    .map_err(|e| api_errore(ApiErrorCode::InvalidDatabase, &e))?;
+
+    Ok(res)
+}
+
+pub async fn fetch_pinned(
+    conn: &mut SqlConnection,
+    app_id: u64,
+    category: u64,
+) -> Result<Vec<TopicModel>, ApiError> {
+    let mut fetch_sql = String::from("select * from dg_topics where app_id=? and topped>0");
+    if category > 0 {
+        fetch_sql.push_str(" and category=?");
+    }
+    fetch_sql.push_str(" order by topped desc");
+
+    let mut query = sqlx::query_as::<_, TopicModel>(&fetch_sql).bind(app_id);
+    if category > 0 {
+        query = query.bind(category);
+    }
+
+    query
+        .fetch_all(conn)
+        .await
+        .map_err(|e| api_errore(ApiErrorCode::InvalidDatabase, &e))
+}
+
+pub async fn fetch_hot_candidates(
+    conn: &mut SqlConnection,
+    app_id: u64,
+    within: u32,
+) -> Result<Vec<TopicModel>, ApiError> {
+    sqlx::query_as::<_, TopicModel>(
+        "select * from dg_topics where app_id=? and topped=0 and moved_to=0 and created_at>=DATE_SUB(NOW(),INTERVAL ? SECOND)",
+    )
+    .bind(app_id)
+    .bind(within)
+    .fetch_all(conn)
+    .await
+    .map_err(|e| api_errore(ApiErrorCode::InvalidDatabase, &e))
+}
+
+/// Loads topics keeping the order of `ids`, missing ones are skipped.
+pub async fn get_list_by_ids(
+    conn: &mut SqlConnection,
+    ids: &Vec<u64>,
+) -> Result<Vec<TopicModel>, ApiError> {
+    if ids.len() < 1 {
+        return Ok(Vec::new());
+    }
+
+    let ids_str = ids
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join(",");
+    let mut res = sqlx::query_as::<_, TopicModel>(&format!(
+        "select * from dg_topics where id in ({})",
+        ids_str
+    ))
+    .fetch_all(conn)
+    .await
+    .map_err(|e| api_errore(ApiErrorCode::InvalidDatabase, &e))?;
+
+    res.sort_by_key(|s| ids.iter().position(|id| *id == s.id));
+
+    Ok(res)
+}
@@ -0,0 +1,132 @@
+use crate::shared::content;
+use crate::shared::data::*;
+use crate::shared::web::*;
+
+use super::topic::TopicModel;
+
+pub const EDITOR_USER: i8 = 0;
+pub const EDITOR_MANAGER: i8 = 1;
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct TopicRevisionModel {
+    pub id: u64,
+    pub app_id: u64,
+    pub topic_id: u64,
+    pub editor_type: i8,
+    pub editor_id: u64,
+    pub title: String,
+    pub content_format: i8,
+    pub content: String,
+    pub created_at: SqlDateTime,
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct TopicRevisionSimple {
+    pub id: u64,
+    pub editor_type: i8,
+    pub editor_id: u64,
+    pub title: String,
+    pub created_at: SqlDateTime,
+}
+
+impl TopicRevisionModel {
+    pub fn from_topic(topic: &TopicModel, editor_type: i8, editor_id: u64) -> TopicRevisionModel {
+        TopicRevisionModel {
+            app_id: topic.app_id,
+            topic_id: topic.id,
+            editor_type,
+            editor_id,
+            title: topic.title.clone(),
+            content_format: topic.content_format,
+            content: topic.content.clone(),
+            ..Default::default()
+        }
+    }
+
+    pub fn to_simple(self: &Self) -> TopicRevisionSimple {
+        TopicRevisionSimple {
+            id: self.id,
+            editor_type: self.editor_type,
+            editor_id: self.editor_id,
+            title: self.title.clone(),
+            created_at: self.created_at,
+        }
+    }
+}
+
+impl Default for TopicRevisionModel {
+    fn default() -> TopicRevisionModel {
+        TopicRevisionModel {
+            id: 0,
+            app_id: 0,
+            topic_id: 0,
+            editor_type: EDITOR_USER,
+            editor_id: 0,
+            title: String::new(),
+            content_format: content::FORMAT_PLAIN,
+            content: String::new(),
+            created_at: SqlDateTime::MIN,
+        }
+    }
+}
+
+pub async fn get_by_id(conn: &mut SqlConnection, id: u64) -> Result<TopicRevisionModel, ApiError> {
+    let res =
+        sqlx::query_as::<_, TopicRevisionModel>("select * from dg_topic_revisions where id=?")
+            .bind(id)
+            .fetch_optional(conn)
+            .await
+            .map_err(|e| api_errore(ApiErrorCode::InvalidDatabase, &e))?;
+
+    if res.is_none() {
+        return Err(api_error(ApiErrorCode::RevisionNotFound));
+    }
+
+    Ok(res.unwrap())
+}
+
+pub async fn create(
+    conn: &mut SqlRawConnection,
+    revision: &mut TopicRevisionModel,
+) -> Result<u64, ApiError> {
+    let res = sqlx::query(
+        "insert into dg_topic_revisions(app_id,topic_id,editor_type,editor_id,title,content_format,content) values(?,?,?,?,?,?,?)",
+    )
+    .bind(revision.app_id)
+    .bind(revision.topic_id)
+    .bind(revision.editor_type)
+    .bind(revision.editor_id)
+    .bind(&revision.title)
+    .bind(revision.content_format)
+    .bind(&revision.content)
+    .execute(conn)
+    .await
+    .map_err(|e| api_errore(ApiErrorCode::InvalidDatabase, &e))?;
+
+    Ok(res.last_insert_id())
+}
+
+pub async fn fetch_pagging(
+    conn: &mut SqlConnection,
+    topic_id: u64,
+    cursor: u32,
+    count: u32,
+) -> Result<(u32, Vec<TopicRevisionModel>), ApiError> {
+    let res = sqlx::query_as::<_, TopicRevisionModel>(
+        "select * from dg_topic_revisions where topic_id=? order by id desc limit ?,?",
+    )
+    .bind(topic_id)
+    .bind(cursor)
+    .bind(count)
+    .fetch_all(conn.as_mut())
+    .await
+    .map_err(|e| api_errore(ApiErrorCode::InvalidDatabase, &e))?;
+
+    let total: (i64,) = sqlx::query_as("select count(*) from dg_topic_revisions where topic_id=?")
+        .bind(topic_id)
+        .fetch_one(conn.as_mut())
+        .await
+        .map_err(|e| api_errore(ApiErrorCode::InvalidDatabase, &e))?;
+
+    Ok((total.0 as u32, res))
+}
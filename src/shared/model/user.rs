@@ -6,6 +6,8 @@ pub const SOURCE_FAKE: i8 = 0;
 pub const SOURCE_SYNC: i8 = 1;
 pub const STATUS_PENDING: i8 = 0;
 pub const STATUS_ACTIVED: i8 = 1;
+/// Mentions beyond this many in one content are ignored.
+pub const MAX_MENTIONS: usize = 10;
 
 #[derive(Debug, Serialize, sqlx::FromRow)]
 pub struct UserModel {
@@ -111,6 +113,84 @@ impl Default for UserSimple {
     }
 }
 
+fn is_mention_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '-' || c == '.'
+}
+
+/// Finds `@display_name` words in the content, deduplicated and capped at
+/// `MAX_MENTIONS`. An `@` glued to a word, as in an email address, is skipped
+/// and a trailing `.` ends the sentence rather than the name.
+pub fn extract_mentions(content: &str) -> Vec<String> {
+    let mut out: Vec<String> = Vec::new();
+    let mut prev: Option<char> = None;
+    let mut chars = content.char_indices().peekable();
+
+    while let Some((i, c)) = chars.next() {
+        if c != '@' || prev.map_or(false, |p| p.is_alphanumeric() || p == '_' || p == '.') {
+            prev = Some(c);
+            continue;
+        }
+
+        let start = i + 1;
+        let mut end = start;
+        while let Some((j, n)) = chars.peek() {
+            if !is_mention_char(*n) {
+                break;
+            }
+            end = j + n.len_utf8();
+            chars.next();
+        }
+        let name = content[start..end].trim_end_matches('.');
+        if !name.is_empty() && !out.iter().any(|s| s == name) {
+            out.push(String::from(name));
+            if out.len() >= MAX_MENTIONS {
+                break;
+            }
+        }
+        prev = Some('@');
+    }
+
+    out
+}
+
+/// Active users of the app called by the given display names. A name shared
+/// by several users is skipped rather than guessed.
+pub async fn get_ids_by_mentions(
+    conn: &mut SqlConnection,
+    app_id: u64,
+    names: &Vec<String>,
+) -> Result<Vec<u64>, ApiError> {
+    if names.len() < 1 {
+        return Ok(Vec::new());
+    }
+
+    let res: Vec<(u64, String)> = {
+        let sql = format!(
+            "select id,display_name from dg_users where app_id=? and status=? and display_name in ({})",
+            vec!["?"; names.len()].join(",")
+        );
+        let mut query = sqlx::query_as(&sql).bind(app_id).bind(STATUS_ACTIVED);
+        for name in names.iter() {
+            query = query.bind(name);
+        }
+        query
+            .fetch_all(conn)
+            .await
+            .map_err(|e| api_errore(ApiErrorCode::InvalidDatabase, &e))?
+    };
+
+    let mut counts: HashMap<String, u32> = HashMap::new();
+    for (_, name) in res.iter() {
+        *counts.entry(name.to_lowercase()).or_default() += 1;
+    }
+
+    Ok(res
+        .iter()
+        .filter(|(_, name)| counts.get(&name.to_lowercase()) == Some(&1))
+        .map(|(id, _)| *id)
+        .collect())
+}
+
 pub async fn get_by_id(conn: &mut SqlConnection, id: u64) -> Result<UserModel, ApiError> {
     let res = sqlx::query_as::<_, UserModel>("select * from dg_users where id=?")
         .bind(id)
@@ -276,3 +356,46 @@ pub async fn fetch_pagging(
 
     Ok((total.0 as u32, users))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn extract_mentions_skips_emails() {
+        assert_eq!(
+            extract_mentions("mail bob@example.com or @alice"),
+            vec!["alice"]
+        );
+        assert!(extract_mentions("a_b@c.d x.y@z").is_empty());
+    }
+
+    #[test]
+    fn extract_mentions_drops_trailing_dots() {
+        assert_eq!(extract_mentions("thanks @alice."), vec!["alice"]);
+        assert_eq!(extract_mentions("ask @j.r.r... now"), vec!["j.r.r"]);
+        assert!(extract_mentions("@. @...").is_empty());
+    }
+
+    #[test]
+    fn extract_mentions_keeps_unicode_names() {
+        assert_eq!(
+            extract_mentions("你好 @张三，还有 @Zoë-K!"),
+            vec!["张三", "Zoë-K"]
+        );
+    }
+
+    #[test]
+    fn extract_mentions_dedups_and_caps() {
+        assert_eq!(extract_mentions("@a @b @a"), vec!["a", "b"]);
+
+        let content = (0..MAX_MENTIONS + 5)
+            .map(|i| format!("@user{}", i))
+            .collect::<Vec<_>>()
+            .join(" ");
+        let names = extract_mentions(&content);
+        assert_eq!(names.len(), MAX_MENTIONS);
+        assert_eq!(names[0], "user0");
+        assert_eq!(names[MAX_MENTIONS - 1], format!("user{}", MAX_MENTIONS - 1));
+    }
+}
@@ -0,0 +1,188 @@
+use super::base::*;
+use super::data::*;
+use super::model::*;
+use super::web::*;
+
+/// Topics older than this drop out of the hot listings.
+const HOT_WITHIN: u32 = 30 * 24 * 3600;
+
+fn hot_key(app_id: u64, category: u64) -> String {
+    format!("topichot:{}:{}", app_id, category)
+}
+
+fn hot_categories_key(app_id: u64) -> String {
+    format!("topichotcats:{}", app_id)
+}
+
+/// Same formula as `topic::HOT_SCORE_SQL`, likes count once and replies twice,
+/// decayed by the age in hours.
+pub fn hot_score(topic: &topic::TopicModel, now: i64) -> f64 {
+    let hours = (now - topic.created_at.and_utc().timestamp()).max(0) / 3600;
+    let points = topic.like_count as f64 + topic.reply_count as f64 * 2.0;
+
+    points / ((hours + 2) as f64).powf(1.5)
+}
+
+/// Rebuilds the hot sorted sets of an app, one per category plus category 0
+/// for the whole app. Pinned topics are left out, they are listed first anyway.
+pub async fn refresh_hot(conn: &mut SqlConnection, app_id: u64) -> Result<u32, ApiError> {
+    let topics = topic::fetch_hot_candidates(conn, app_id, HOT_WITHIN).await?;
+
+    let now = timestamp();
+    let mut groups: HashMap<u64, Vec<(f64, u64)>> = HashMap::new();
+    for o in topics.iter() {
+        let score = hot_score(o, now);
+        groups.entry(0).or_default().push((score, o.id));
+        groups.entry(o.category).or_default().push((score, o.id));
+    }
+
+    let mut connr = redis_connect().await?;
+
+    let previous: Vec<u64> = redis::cmd("SMEMBERS")
+        .arg(hot_categories_key(app_id))
+        .query_async(&mut *connr)
+        .await
+        .map_err(|e| api_errore(ApiErrorCode::InvalidDatabase, &e))?;
+
+    let mut pipe = redis::pipe();
+    pipe.atomic();
+    for category in previous.iter().filter(|s| !groups.contains_key(s)) {
+        pipe.cmd("DEL").arg(hot_key(app_id, *category)).ignore();
+    }
+    pipe.cmd("DEL").arg(hot_categories_key(app_id)).ignore();
+    for (category, items) in groups.iter() {
+        let key = hot_key(app_id, *category);
+        pipe.cmd("DEL").arg(&key).ignore();
+        pipe.cmd("ZADD").arg(&key).arg(items).ignore();
+        pipe.cmd("SADD")
+            .arg(hot_categories_key(app_id))
+            .arg(*category)
+            .ignore();
+    }
+    pipe.query_async::<()>(&mut *connr)
+        .await
+        .map_err(|e| api_errore(ApiErrorCode::InvalidDatabase, &e))?;
+
+    Ok(topics.len() as u32)
+}
+
+/// Pages through the hot listing: pinned topics first, then the sorted set.
+/// Returns `None` when the set has not been built yet so the caller can fall
+/// back to ordering in SQL.
+pub async fn fetch_hot_pagging(
+    conn: &mut SqlConnection,
+    app_id: u64,
+    category: u64,
+    cursor: u32,
+    count: u32,
+) -> Result<Option<(u32, Vec<topic::TopicModel>)>, ApiError> {
+    let mut connr = redis_connect().await?;
+
+    let key = hot_key(app_id, category);
+    let hot_total: u32 = redis::cmd("ZCARD")
+        .arg(&key)
+        .query_async(&mut *connr)
+        .await
+        .map_err(|e| api_errore(ApiErrorCode::InvalidDatabase, &e))?;
+    if hot_total == 0 {
+        return Ok(None);
+    }
+
+    let pinned = topic::fetch_pinned(conn, app_id, category).await?;
+    let pinned_total = pinned.len() as u32;
+
+    let mut topics: Vec<topic::TopicModel> = pinned
+        .into_iter()
+        .skip(cursor as usize)
+        .take(count as usize)
+        .collect();
+
+    let remain = count - topics.len() as u32;
+    if remain > 0 {
+        let start = cursor.saturating_sub(pinned_total);
+        let ids: Vec<u64> = redis::cmd("ZREVRANGE")
+            .arg(&key)
+            .arg(start)
+            .arg(start + remain - 1)
+            .query_async(&mut *connr)
+            .await
+            .map_err(|e| api_errore(ApiErrorCode::InvalidDatabase, &e))?;
+
+        // status handlers drop topics from the sets, this only covers a race
+        let hot = topic::get_list_by_ids(conn, &ids).await?;
+        topics.extend(hot.into_iter().filter(|s| s.topped == 0));
+    }
+
+    Ok(Some((pinned_total + hot_total, topics)))
+}
+
+/// Drops a topic from the hot sets once it is pinned, hidden or deleted, so
+/// the pages and totals stay right until the next refresh.
+pub async fn remove_topic(app_id: u64, topic_id: u64, category: u64) -> Result<(), ApiError> {
+    let mut connr = redis_connect().await?;
+
+    let mut pipe = redis::pipe();
+    pipe.atomic();
+    pipe.cmd("ZREM")
+        .arg(hot_key(app_id, 0))
+        .arg(topic_id)
+        .ignore();
+    pipe.cmd("ZREM")
+        .arg(hot_key(app_id, category))
+        .arg(topic_id)
+        .ignore();
+    pipe.query_async::<()>(&mut *connr)
+        .await
+        .map_err(|e| api_errore(ApiErrorCode::InvalidDatabase, &e))?;
+
+    Ok(())
+}
+
+/// Carries a topic over to the hot set of its new category, reusing the score
+/// of the app wide set so the listings agree until the next refresh.
+pub async fn move_topic(app_id: u64, topic_id: u64, from: u64, to: u64) -> Result<(), ApiError> {
+    let mut connr = redis_connect().await?;
+
+    let score: Option<f64> = redis::cmd("ZSCORE")
+        .arg(hot_key(app_id, 0))
+        .arg(topic_id)
+        .query_async(&mut *connr)
+        .await
+        .map_err(|e| api_errore(ApiErrorCode::InvalidDatabase, &e))?;
+
+    let mut pipe = redis::pipe();
+    pipe.atomic();
+    pipe.cmd("ZREM")
+        .arg(hot_key(app_id, from))
+        .arg(topic_id)
+        .ignore();
+    if let Some(score) = score {
+        pipe.cmd("ZADD")
+            .arg(hot_key(app_id, to))
+            .arg(score)
+            .arg(topic_id)
+            .ignore();
+        pipe.cmd("SADD")
+            .arg(hot_categories_key(app_id))
+            .arg(to)
+            .ignore();
+    }
+    pipe.query_async::<()>(&mut *connr)
+        .await
+        .map_err(|e| api_errore(ApiErrorCode::InvalidDatabase, &e))?;
+
+    Ok(())
+}
+
+pub async fn refresh_all() -> Result<(), ApiError> {
+    let mut conn = database_connect().await?;
+
+    // one broken app must not keep the others from being refreshed
+    for app_id in app::get_all_ids(&mut conn).await? {
+        if let Err(e) = refresh_hot(&mut conn, app_id).await {
+            tracing::warn!("refresh_hot of app {} failed: {}", app_id, e);
+        }
+    }
+
+    Ok(())
+}
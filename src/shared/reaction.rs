@@ -0,0 +1,459 @@
+use super::base::*;
+use super::data::*;
+use super::model::*;
+use super::web::*;
+
+/// Reaction every app has, it is what a like used to be.
+pub const DEFAULT_REACTION: &'static str = "like";
+/// Reserved reaction of apps with downvotes enabled, it excludes the default
+/// reaction and counts against `score`.
+pub const DOWNVOTE_REACTION: &'static str = "downvote";
+pub const MAX_REACTION_TYPES: usize = 16;
+/// Users named in the summary of a topic detail.
+pub const SUMMARY_USERS: u32 = 2;
+
+pub const TARGET_TOPIC: i8 = 1;
+pub const TARGET_REPLY: i8 = 2;
+
+const MIGRATE_BATCH: u32 = 500;
+
+/// Set once the Redis sets hold everything in `dg_likes`, it is gone after
+/// Redis is flushed.
+const CACHED_KEY: &'static str = "reactions:cached";
+
+/// Users who reacted, scored by the time of the reaction.
+pub fn reaction_key(target_type: i8, target_id: u64, reaction: &str) -> String {
+    match target_type {
+        TARGET_TOPIC => format!("topicreact:{}:{}", target_id, reaction),
+        _ => format!("replyreact:{}:{}", target_id, reaction),
+    }
+}
+
+/// The reaction a user loses by reacting with `reaction`, likes and downvotes
+/// exclude each other.
+pub fn opposite(reaction: &str) -> Option<&'static str> {
+    match reaction {
+        DEFAULT_REACTION => Some(DOWNVOTE_REACTION),
+        DOWNVOTE_REACTION => Some(DEFAULT_REACTION),
+        _ => None,
+    }
+}
+
+/// Names go into Redis keys and JSON paths, keep them to `[a-z0-9_]`.
+pub fn is_valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.len() <= 32
+        && name
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_')
+}
+
+/// Stores the reaction in `dg_likes` and caches it in Redis. Returns false
+/// when the user already reacted so.
+pub async fn add(
+    conn: &mut SqlConnection,
+    app_id: u64,
+    target_type: i8,
+    target_id: u64,
+    user_id: u64,
+    reaction: &str,
+) -> Result<bool, ApiError> {
+    let now = timestamp();
+    let created = like::create(
+        conn,
+        app_id,
+        target_type,
+        target_id,
+        user_id,
+        reaction,
+        sql_datetime(now),
+    )
+    .await?;
+
+    let mut connr = redis_connect().await?;
+
+    // also refills the cache when the reaction was only missing from Redis
+    redis::cmd("ZADD")
+        .arg(reaction_key(target_type, target_id, reaction))
+        .arg("NX")
+        .arg(now)
+        .arg(user_id)
+        .query_async::<()>(&mut *connr)
+        .await
+        .map_err(|e| api_errore(ApiErrorCode::InvalidDatabase, &e))?;
+
+    Ok(created)
+}
+
+/// Returns false when the user had not reacted so.
+pub async fn remove(
+    conn: &mut SqlConnection,
+    target_type: i8,
+    target_id: u64,
+    user_id: u64,
+    reaction: &str,
+) -> Result<bool, ApiError> {
+    let deleted = like::delete(conn, target_type, target_id, user_id, reaction).await?;
+
+    let mut connr = redis_connect().await?;
+
+    redis::cmd("ZREM")
+        .arg(reaction_key(target_type, target_id, reaction))
+        .arg(user_id)
+        .query_async::<()>(&mut *connr)
+        .await
+        .map_err(|e| api_errore(ApiErrorCode::InvalidDatabase, &e))?;
+
+    Ok(deleted)
+}
+
+#[derive(Serialize)]
+pub struct ReactorItem {
+    pub user: Arc<user::UserSimple>,
+    pub reacted_at: SqlDateTime,
+}
+
+#[derive(Serialize)]
+pub struct ReactorSummary {
+    pub users: Vec<Arc<user::UserSimple>>,
+    pub others: u32,
+}
+
+/// Pages through the users who reacted, newest first.
+pub async fn fetch_reactors(
+    conn: &mut SqlConnection,
+    target_type: i8,
+    target_id: u64,
+    reaction: &str,
+    cursor: u32,
+    count: u32,
+) -> Result<(u32, Vec<ReactorItem>), ApiError> {
+    let mut connr = redis_connect().await?;
+
+    let key = reaction_key(target_type, target_id, reaction);
+    let (total, scores): (u32, Vec<(u64, i64)>) = redis::pipe()
+        .cmd("ZCARD")
+        .arg(&key)
+        .cmd("ZREVRANGE")
+        .arg(&key)
+        .arg(cursor)
+        .arg(cursor as i64 + count as i64 - 1)
+        .arg("WITHSCORES")
+        .query_async(&mut *connr)
+        .await
+        .map_err(|e| api_errore(ApiErrorCode::InvalidDatabase, &e))?;
+
+    let user_map = user::get_simple_map_by_ids(conn, scores.iter().map(|s| s.0).collect()).await?;
+
+    let items = scores
+        .iter()
+        .filter_map(|(user_id, ts)| {
+            user_map.opt(*user_id).map(|user| ReactorItem {
+                user,
+                reacted_at: sql_datetime(*ts),
+            })
+        })
+        .collect();
+
+    Ok((total, items))
+}
+
+/// The latest `count` users who reacted and how many more did, for
+/// "liked by X, Y and N others".
+pub async fn fetch_summary(
+    conn: &mut SqlConnection,
+    target_type: i8,
+    target_id: u64,
+    reaction: &str,
+    count: u32,
+) -> Result<ReactorSummary, ApiError> {
+    let (total, items) = fetch_reactors(conn, target_type, target_id, reaction, 0, count).await?;
+
+    Ok(ReactorSummary {
+        others: total.saturating_sub(items.len() as u32),
+        users: items.into_iter().map(|s| s.user).collect(),
+    })
+}
+
+/// Reactions of the user on each target, in the order of `reactions`.
+pub async fn fetch_user_reactions<C>(
+    conn: &mut C,
+    target_type: i8,
+    target_ids: &Vec<u64>,
+    user_id: u64,
+    reactions: &Vec<String>,
+) -> Result<HashMap<u64, Vec<String>>, ApiError>
+where
+    C: RedisConnectionLike,
+{
+    let mut out = HashMap::new();
+    if target_ids.is_empty() || reactions.is_empty() {
+        return Ok(out);
+    }
+
+    let mut pipe = redis::pipe();
+    for id in target_ids.iter() {
+        for reaction in reactions.iter() {
+            pipe.cmd("ZSCORE")
+                .arg(reaction_key(target_type, *id, reaction))
+                .arg(user_id);
+        }
+    }
+    let scores: Vec<Option<u64>> = pipe
+        .query_async(conn)
+        .await
+        .map_err(|e| api_errore(ApiErrorCode::InvalidDatabase, &e))?;
+
+    for (i, o) in scores.chunks(reactions.len()).enumerate() {
+        let mine: Vec<String> = o
+            .iter()
+            .zip(reactions.iter())
+            .filter(|(score, _)| score.is_some())
+            .map(|(_, reaction)| reaction.clone())
+            .collect();
+        out.insert(target_ids[i], mine);
+    }
+
+    Ok(out)
+}
+
+async fn migrate_keys(prefix: &str, target_type: i8) -> Result<(), ApiError> {
+    let mut connr = redis_connect().await?;
+
+    let mut cursor: u64 = 0;
+    loop {
+        let (next, keys): (u64, Vec<String>) = redis::cmd("SCAN")
+            .arg(cursor)
+            .arg("MATCH")
+            .arg(format!("{}*", prefix))
+            .arg("COUNT")
+            .arg(MIGRATE_BATCH)
+            .query_async(&mut *connr)
+            .await
+            .map_err(|e| api_errore(ApiErrorCode::InvalidDatabase, &e))?;
+
+        for key in keys.iter() {
+            let id: u64 = match key[prefix.len()..].parse() {
+                Ok(id) => id,
+                _ => continue,
+            };
+            let target = reaction_key(target_type, id, DEFAULT_REACTION);
+
+            // merge into the new key in case it was already written to
+            let mut pipe = redis::pipe();
+            pipe.atomic();
+            pipe.cmd("ZUNIONSTORE")
+                .arg(&target)
+                .arg(2)
+                .arg(&target)
+                .arg(key)
+                .arg("AGGREGATE")
+                .arg("MIN")
+                .ignore();
+            pipe.cmd("DEL").arg(key).ignore();
+            pipe.query_async::<()>(&mut *connr)
+                .await
+                .map_err(|e| api_errore(ApiErrorCode::InvalidDatabase, &e))?;
+        }
+
+        cursor = next;
+        if cursor == 0 {
+            break;
+        }
+    }
+
+    Ok(())
+}
+
+/// Copies the reactions kept only in Redis before `dg_likes` existed into the
+/// table. Part of the one-time migration, once the table is the source of
+/// truth stale members left in Redis must not be written back.
+async fn backfill_keys(
+    conn: &mut SqlConnection,
+    prefix: &str,
+    target_type: i8,
+) -> Result<(), ApiError> {
+    let mut connr = redis_connect().await?;
+
+    let mut cursor: u64 = 0;
+    loop {
+        let (next, keys): (u64, Vec<String>) = redis::cmd("SCAN")
+            .arg(cursor)
+            .arg("MATCH")
+            .arg(format!("{}*", prefix))
+            .arg("COUNT")
+            .arg(MIGRATE_BATCH)
+            .query_async(&mut *connr)
+            .await
+            .map_err(|e| api_errore(ApiErrorCode::InvalidDatabase, &e))?;
+
+        let mut targets: Vec<(&String, u64, &str)> = Vec::new();
+        for key in keys.iter() {
+            if let Some((id, name)) = key[prefix.len()..].split_once(':') {
+                if let Ok(id) = id.parse::<u64>() {
+                    targets.push((key, id, name));
+                }
+            }
+        }
+
+        let ids: Vec<u64> = targets.iter().map(|(_, id, _)| *id).collect();
+        let app_ids: HashMap<u64, u64> = match target_type {
+            TARGET_TOPIC => {
+                let topics = topic::get_map_by_ids(conn, ids.clone()).await?;
+                ids.iter()
+                    .filter_map(|id| topics.opt(*id).map(|t| (*id, t.app_id)))
+                    .collect()
+            }
+            _ => {
+                let replies = reply::get_map_by_ids(conn, ids.clone()).await?;
+                ids.iter()
+                    .filter_map(|id| replies.opt(*id).map(|r| (*id, r.app_id)))
+                    .collect()
+            }
+        };
+
+        let mut pipe = redis::pipe();
+        for (key, _, _) in targets.iter() {
+            pipe.cmd("ZRANGE")
+                .arg(*key)
+                .arg(0)
+                .arg(-1)
+                .arg("WITHSCORES");
+        }
+        let members: Vec<Vec<(u64, i64)>> = pipe
+            .query_async(&mut *connr)
+            .await
+            .map_err(|e| api_errore(ApiErrorCode::InvalidDatabase, &e))?;
+
+        let mut likes: Vec<like::LikeModel> = Vec::new();
+        for ((_, id, name), scores) in targets.iter().zip(members) {
+            let app_id = match app_ids.get(id) {
+                Some(app_id) => *app_id,
+                _ => continue,
+            };
+            for (user_id, ts) in scores {
+                likes.push(like::LikeModel {
+                    id: 0,
+                    app_id,
+                    target_type,
+                    target_id: *id,
+                    user_id,
+                    reaction: String::from(*name),
+                    created_at: sql_datetime(ts),
+                });
+            }
+            if likes.len() >= MIGRATE_BATCH as usize {
+                like::create_batch(conn, &likes).await?;
+                likes.clear();
+            }
+        }
+        like::create_batch(conn, &likes).await?;
+
+        cursor = next;
+        if cursor == 0 {
+            break;
+        }
+    }
+
+    Ok(())
+}
+
+/// Turns the likes stored before reactions existed into the default reaction
+/// and copies them into `dg_likes`. Run once before serving, see
+/// `scheduler::migrate`, it is safe to run again after a failure.
+pub async fn migrate_likes() -> Result<(), ApiError> {
+    let mut conn = database_connect().await?;
+
+    topic::init_reaction_counts(&mut conn).await?;
+    reply::init_reaction_counts(&mut conn).await?;
+
+    migrate_keys("topiclike:", TARGET_TOPIC).await?;
+    migrate_keys("replylike:", TARGET_REPLY).await?;
+
+    backfill_keys(&mut conn, "topicreact:", TARGET_TOPIC).await?;
+    backfill_keys(&mut conn, "replyreact:", TARGET_REPLY).await?;
+
+    Ok(())
+}
+
+/// Repopulates the Redis sets from `dg_likes`.
+async fn rebuild(conn: &mut SqlConnection) -> Result<(), ApiError> {
+    let mut connr = redis_connect().await?;
+
+    let mut last_id = 0;
+    loop {
+        let likes = like::fetch_after(conn, last_id, MIGRATE_BATCH).await?;
+        if likes.is_empty() {
+            break;
+        }
+
+        let mut pipe = redis::pipe();
+        for o in likes.iter() {
+            pipe.cmd("ZADD")
+                .arg(reaction_key(o.target_type, o.target_id, &o.reaction))
+                .arg("NX")
+                .arg(o.created_at.and_utc().timestamp())
+                .arg(o.user_id)
+                .ignore();
+        }
+        pipe.query_async::<()>(&mut *connr)
+            .await
+            .map_err(|e| api_errore(ApiErrorCode::InvalidDatabase, &e))?;
+
+        last_id = likes[likes.len() - 1].id;
+    }
+
+    Ok(())
+}
+
+async fn is_cached() -> Result<bool, ApiError> {
+    let mut connr = redis_connect().await?;
+
+    redis::cmd("EXISTS")
+        .arg(CACHED_KEY)
+        .query_async(&mut *connr)
+        .await
+        .map_err(|e| api_errore(ApiErrorCode::InvalidDatabase, &e))
+}
+
+/// Fills the Redis sets from `dg_likes` again when Redis is new or was
+/// flushed. Does nothing while the cache is known to be complete, or before
+/// the likes kept in Redis were migrated into the table.
+pub async fn restore() -> Result<(), ApiError> {
+    if is_cached().await? {
+        return Ok(());
+    }
+
+    let mut conn = database_connect().await?;
+    if !migration::is_applied(&mut conn, migration::LIKES_MIGRATED).await? {
+        return Ok(());
+    }
+
+    rebuild(&mut conn).await?;
+
+    let mut connr = redis_connect().await?;
+    redis::cmd("SET")
+        .arg(CACHED_KEY)
+        .arg(timestamp())
+        .query_async::<()>(&mut *connr)
+        .await
+        .map_err(|e| api_errore(ApiErrorCode::InvalidDatabase, &e))?;
+
+    Ok(())
+}
+
+/// Repairs `like_count` of topics and replies from `dg_likes`.
+pub async fn reconcile() -> Result<(), ApiError> {
+    let mut conn = database_connect().await?;
+
+    // until the backfill has finished, a target without rows in the table
+    // may still have its likes in Redis only
+    if !migration::is_applied(&mut conn, migration::LIKES_MIGRATED).await? {
+        return Ok(());
+    }
+
+    topic::reconcile_like_count(&mut conn).await?;
+    reply::reconcile_like_count(&mut conn).await?;
+
+    Ok(())
+}
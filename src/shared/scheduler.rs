@@ -0,0 +1,201 @@
+use std::future::Future;
+use std::time::Duration;
+
+use super::base::*;
+use super::content;
+use super::data::*;
+use super::inbox;
+use super::model::*;
+use super::ranking;
+use super::reaction;
+use super::search;
+use super::view;
+use super::web::*;
+
+const TICK_INTERVAL: u64 = 10;
+const RENDER_BATCH: u32 = 500;
+
+static INSTANCE_ID: Lazy<String> = Lazy::new(|| uuid::Uuid::new_v4().simple().to_string());
+
+fn lock_key(name: &str) -> String {
+    format!("joblock:{}", name)
+}
+
+/// Claims a job for the next `interval` seconds. The lock is never released,
+/// it expires by itself, so a job runs once per interval over all instances.
+async fn try_lock(name: &str, interval: u64) -> Result<bool, ApiError> {
+    let mut connr = redis_connect().await?;
+
+    let res: Option<String> = redis::cmd("SET")
+        .arg(lock_key(name))
+        .arg(INSTANCE_ID.as_str())
+        .arg("NX")
+        .arg("EX")
+        .arg(interval)
+        .query_async(&mut *connr)
+        .await
+        .map_err(|e| api_errore(ApiErrorCode::InvalidDatabase, &e))?;
+
+    Ok(res.is_some())
+}
+
+async fn unlock(name: &str) -> Result<(), ApiError> {
+    let mut connr = redis_connect().await?;
+
+    redis::cmd("DEL")
+        .arg(lock_key(name))
+        .query_async::<()>(&mut *connr)
+        .await
+        .map_err(|e| api_errore(ApiErrorCode::InvalidDatabase, &e))
+}
+
+async fn run_job<F, Fut>(name: &str, interval: u64, job: F)
+where
+    F: FnOnce() -> Fut,
+    Fut: Future<Output = Result<(), ApiError>>,
+{
+    match try_lock(name, interval).await {
+        Ok(true) => {
+            if let Err(e) = job().await {
+                tracing::error!("job {} failed: {}", name, e);
+            }
+        }
+        Ok(false) => {}
+        Err(e) => tracing::error!("job {} not locked: {}", name, e),
+    }
+}
+
+async fn publish_scheduled() -> Result<(), ApiError> {
+    let mut conn = database_connect().await?;
+
+    for id in topic::fetch_due_published(&mut conn).await? {
+        topic::publish(&mut conn, id).await?;
+
+        let topic = topic::get_by_id(&mut conn, id).await?;
+        search::sync_topic(&topic);
+        inbox::notify_topic(&mut conn, &topic).await;
+    }
+
+    Ok(())
+}
+
+/// Renders the content stored before `content_html` and `excerpt` existed,
+/// rows rendered since are skipped so running it again is harmless.
+async fn render_content() -> Result<(), ApiError> {
+    let mut conn = database_connect().await?;
+
+    let mut last_id = 0;
+    loop {
+        let topics = topic::fetch_unrendered_after(&mut conn, last_id, RENDER_BATCH).await?;
+        if topics.is_empty() {
+            break;
+        }
+
+        for (id, format, text) in topics.iter() {
+            let (html, excerpt) = content::render(*format, text);
+            topic::update_rendered(&mut conn, *id, &html, &excerpt).await?;
+        }
+        last_id = topics.last().unwrap().0;
+    }
+
+    let mut last_id = 0;
+    loop {
+        let replies = reply::fetch_unrendered_after(&mut conn, last_id, RENDER_BATCH).await?;
+        if replies.is_empty() {
+            break;
+        }
+
+        for (id, format, text) in replies.iter() {
+            let (html, excerpt) = content::render(*format, text);
+            reply::update_rendered(&mut conn, *id, &html, &excerpt).await?;
+        }
+        last_id = replies.last().unwrap().0;
+    }
+
+    Ok(())
+}
+
+async fn lock_inactive() -> Result<(), ApiError> {
+    let mut conn = database_connect().await?;
+
+    topic::lock_inactive(&mut conn).await?;
+
+    Ok(())
+}
+
+async fn purge_drafts() -> Result<(), ApiError> {
+    let mut conn = database_connect().await?;
+
+    draft::purge_expired(&mut conn).await?;
+
+    Ok(())
+}
+
+async fn purge_attachments() -> Result<(), ApiError> {
+    let mut conn = database_connect().await?;
+
+    attachment::purge_unbound(&mut conn, attachment::UNBOUND_TTL).await?;
+
+    Ok(())
+}
+
+async fn unpin_expired() -> Result<(), ApiError> {
+    let mut conn = database_connect().await?;
+
+    topic::unpin_expired(&mut conn).await?;
+
+    Ok(())
+}
+
+/// Data migrations the handlers depend on, awaited before serving. One
+/// instance runs them while the others wait for `dg_migrations` to record it.
+pub async fn migrate() -> Result<(), ApiError> {
+    loop {
+        let mut conn = database_connect().await?;
+        if migration::is_applied(&mut conn, migration::LIKES_MIGRATED).await? {
+            return Ok(());
+        }
+        drop(conn);
+
+        if try_lock("migrate_likes", 3600).await? {
+            if let Err(e) = reaction::migrate_likes().await {
+                // let another instance try without waiting for the lock
+                unlock("migrate_likes").await?;
+                return Err(e);
+            }
+
+            let mut conn = database_connect().await?;
+            migration::create(&mut conn, migration::LIKES_MIGRATED).await?;
+            return Ok(());
+        }
+
+        tokio::time::sleep(Duration::from_secs(1)).await;
+    }
+}
+
+/// Background jobs of the server, every instance runs this loop and the
+/// Redis locks decide which one does the work.
+pub async fn run() {
+    // one instance renders the content stored before HTML was kept, on start
+    run_job("render_content", 3600, render_content).await;
+
+    let mut interval = tokio::time::interval(Duration::from_secs(TICK_INTERVAL));
+    loop {
+        interval.tick().await;
+
+        // every instance follows the database into its own search index
+        if let Err(e) = search::catch_up().await {
+            tracing::warn!("search catch_up failed: {}", e);
+        }
+
+        run_job("publish_scheduled", 30, publish_scheduled).await;
+        run_job("unpin_expired", 30, unpin_expired).await;
+        run_job("refresh_hot", 300, ranking::refresh_all).await;
+        run_job("flush_views", 60, view::flush).await;
+        run_job("lock_inactive", 3600, lock_inactive).await;
+        run_job("purge_drafts", 3600, purge_drafts).await;
+        run_job("purge_attachments", 3600, purge_attachments).await;
+        run_job("restore_reactions", 60, reaction::restore).await;
+        run_job("reconcile_likes", 3600, reaction::reconcile).await;
+    }
+}
@@ -0,0 +1,703 @@
+use std::sync::Mutex;
+
+use jieba_rs::{Jieba, TokenizeMode};
+use tantivy::collector::{Count, TopDocs};
+use tantivy::directory::MmapDirectory;
+use tantivy::query::{BooleanQuery, BoostQuery, Occur, Query, TermQuery};
+use tantivy::schema::{
+    Field, IndexRecordOption, Schema, TextFieldIndexing, TextOptions, Value, FAST, INDEXED, STORED,
+    STRING,
+};
+use tantivy::snippet::SnippetGenerator;
+use tantivy::tokenizer::{LowerCaser, TextAnalyzer, Token, TokenStream, Tokenizer};
+use tantivy::{doc, Index, IndexReader, IndexWriter, ReloadPolicy, TantivyDocument, Term};
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender};
+
+use super::base::*;
+use super::data::*;
+use super::model::{reply, topic, user};
+use super::web::*;
+
+pub const KIND_TOPIC: u64 = 1;
+pub const KIND_REPLY: u64 = 2;
+
+const TOKENIZER_NAME: &'static str = "jieba";
+const WRITER_MEMORY: usize = 50_000_000;
+const SNIPPET_CHARS: usize = 120;
+const REBUILD_BATCH: u32 = 500;
+/// Rows are followed once they are this old, so changes of transactions
+/// still in flight are not skipped over.
+const SETTLE_SECONDS: u32 = 5;
+
+static JIEBA: Lazy<Jieba> = Lazy::new(Jieba::new);
+
+static SEARCH_INDEX: Lazy<SearchIndex> = Lazy::new(|| {
+    let index_dir = std::env::var("SEARCH_INDEX_DIR").unwrap_or("data/search".to_string());
+    std::fs::create_dir_all(&index_dir).expect("can't create SEARCH_INDEX_DIR");
+
+    SearchIndex::open(&index_dir).expect("can't open search index")
+});
+
+/// Documents to replace by key, `None` only drops the old one.
+type SearchOps = Vec<(String, Option<TantivyDocument>)>;
+
+static SEARCH_QUEUE: once_cell::sync::OnceCell<UnboundedSender<SearchOps>> =
+    once_cell::sync::OnceCell::new();
+
+pub fn init() {
+    Lazy::force(&SEARCH_INDEX);
+
+    let (sender, receiver) = unbounded_channel();
+    SEARCH_QUEUE.set(sender).ok();
+    tokio::spawn(run_queue(receiver));
+}
+
+/// Writes the queued documents in the background, whatever piled up while
+/// the previous commit ran goes into the next one.
+async fn run_queue(mut receiver: UnboundedReceiver<SearchOps>) {
+    while let Some(mut ops) = receiver.recv().await {
+        while let Ok(more) = receiver.try_recv() {
+            ops.extend(more);
+        }
+
+        if let Err(e) = write_ops(ops, None).await {
+            tracing::warn!("search index write failed: {}", e);
+        }
+    }
+}
+
+fn enqueue(ops: SearchOps) {
+    match SEARCH_QUEUE.get() {
+        Some(sender) if sender.send(ops).is_ok() => {}
+        _ => tracing::warn!("search queue closed, left to catch_up"),
+    }
+}
+
+/// Splits CJK text into words with jieba, other scripts fall back on its
+/// whitespace and punctuation handling.
+#[derive(Clone)]
+struct JiebaTokenizer;
+
+struct JiebaTokenStream {
+    tokens: Vec<Token>,
+    index: usize,
+}
+
+impl Tokenizer for JiebaTokenizer {
+    type TokenStream<'a> = JiebaTokenStream;
+
+    fn token_stream<'a>(&'a mut self, text: &'a str) -> JiebaTokenStream {
+        let offsets: Vec<usize> = text
+            .char_indices()
+            .map(|(i, _)| i)
+            .chain(std::iter::once(text.len()))
+            .collect();
+
+        let mut tokens = Vec::new();
+        for o in JIEBA.tokenize(text, TokenizeMode::Search, true) {
+            if !o.word.chars().any(char::is_alphanumeric) {
+                continue;
+            }
+            tokens.push(Token {
+                offset_from: offsets[o.start],
+                offset_to: offsets[o.end],
+                position: tokens.len(),
+                text: String::from(o.word),
+                position_length: 1,
+            });
+        }
+
+        JiebaTokenStream { tokens, index: 0 }
+    }
+}
+
+impl TokenStream for JiebaTokenStream {
+    fn advance(&mut self) -> bool {
+        self.index += 1;
+        self.index <= self.tokens.len()
+    }
+
+    fn token(&self) -> &Token {
+        &self.tokens[self.index - 1]
+    }
+
+    fn token_mut(&mut self) -> &mut Token {
+        &mut self.tokens[self.index - 1]
+    }
+}
+
+struct SearchFields {
+    key: Field,
+    kind: Field,
+    id: Field,
+    app_id: Field,
+    category: Field,
+    topic_id: Field,
+    title: Field,
+    content: Field,
+}
+
+/// Position in the `updated_at` order of topics and replies up to which the
+/// index has followed the database, kept in the payload of each commit.
+#[derive(Clone, Copy, Default, Serialize, Deserialize)]
+struct SyncMark {
+    topic_at: i64,
+    topic_id: u64,
+    reply_at: i64,
+    reply_id: u64,
+}
+
+struct SearchWriter {
+    writer: IndexWriter,
+    mark: SyncMark,
+}
+
+struct SearchIndex {
+    index: Index,
+    reader: IndexReader,
+    writer: Mutex<SearchWriter>,
+    fields: SearchFields,
+}
+
+impl SearchIndex {
+    fn open(index_dir: &str) -> tantivy::Result<Self> {
+        let text_options = TextOptions::default()
+            .set_indexing_options(
+                TextFieldIndexing::default()
+                    .set_tokenizer(TOKENIZER_NAME)
+                    .set_index_option(IndexRecordOption::WithFreqsAndPositions),
+            )
+            .set_stored();
+
+        let mut builder = Schema::builder();
+        let fields = SearchFields {
+            key: builder.add_text_field("key", STRING),
+            kind: builder.add_u64_field("kind", INDEXED | STORED),
+            id: builder.add_u64_field("id", STORED),
+            app_id: builder.add_u64_field("app_id", INDEXED | FAST),
+            category: builder.add_u64_field("category", INDEXED | FAST),
+            topic_id: builder.add_u64_field("topic_id", INDEXED | STORED),
+            title: builder.add_text_field("title", text_options.clone()),
+            content: builder.add_text_field("content", text_options),
+        };
+
+        let directory = MmapDirectory::open(index_dir)?;
+        let index = Index::open_or_create(directory, builder.build())?;
+        index.tokenizers().register(
+            TOKENIZER_NAME,
+            TextAnalyzer::builder(JiebaTokenizer)
+                .filter(LowerCaser)
+                .build(),
+        );
+
+        let reader = index
+            .reader_builder()
+            .reload_policy(ReloadPolicy::OnCommitWithDelay)
+            .try_into()?;
+        let writer = index.writer(WRITER_MEMORY)?;
+        let mark = index
+            .load_metas()?
+            .payload
+            .and_then(|s| serde_json::from_str(&s).ok())
+            .unwrap_or_default();
+
+        Ok(Self {
+            index,
+            reader,
+            writer: Mutex::new(SearchWriter { writer, mark }),
+            fields,
+        })
+    }
+
+    fn topic_doc(self: &Self, topic: &topic::TopicModel) -> TantivyDocument {
+        let f = &self.fields;
+        doc!(
+            f.key => topic_key(topic.id),
+            f.kind => KIND_TOPIC,
+            f.id => topic.id,
+            f.app_id => topic.app_id,
+            f.category => topic.category,
+            f.topic_id => topic.id,
+            f.title => topic.title.as_str(),
+            f.content => topic.content.as_str(),
+        )
+    }
+
+    fn reply_doc(self: &Self, reply: &reply::ReplyModel, category: u64) -> TantivyDocument {
+        let f = &self.fields;
+        doc!(
+            f.key => reply_key(reply.id),
+            f.kind => KIND_REPLY,
+            f.id => reply.id,
+            f.app_id => reply.app_id,
+            f.category => category,
+            f.topic_id => reply.topic_id,
+            f.title => "",
+            f.content => reply.content.as_str(),
+        )
+    }
+
+    fn mark(self: &Self) -> Result<SyncMark, ApiError> {
+        self.writer
+            .lock()
+            .map(|s| s.mark)
+            .map_err(|_| api_error2(ApiErrorCode::InvalidSearch, "writer poisoned"))
+    }
+
+    /// Commits `op`, moving the sync mark along when one is given. Blocks,
+    /// run it through `write_blocking`.
+    fn write<F>(self: &Self, op: F, mark: Option<SyncMark>) -> Result<(), ApiError>
+    where
+        F: FnOnce(&mut IndexWriter, &SearchFields) -> tantivy::Result<()>,
+    {
+        let mut state = self
+            .writer
+            .lock()
+            .map_err(|_| api_error2(ApiErrorCode::InvalidSearch, "writer poisoned"))?;
+        let mark = mark.unwrap_or(state.mark);
+        let payload = serde_json::to_string(&mark)
+            .map_err(|e| api_errore(ApiErrorCode::InvalidSearch, &e))?;
+
+        let writer = &mut state.writer;
+        op(writer, &self.fields)
+            .and_then(|_| {
+                let mut commit = writer.prepare_commit()?;
+                commit.set_payload(&payload);
+                commit.commit().map(|_| ())
+            })
+            .map_err(|e| api_errore(ApiErrorCode::InvalidSearch, &e))?;
+        state.mark = mark;
+
+        self.reader
+            .reload()
+            .map_err(|e| api_errore(ApiErrorCode::InvalidSearch, &e))
+    }
+}
+
+/// Keeps the tokio workers free while tantivy commits.
+async fn write_blocking<F>(op: F, mark: Option<SyncMark>) -> Result<(), ApiError>
+where
+    F: FnOnce(&mut IndexWriter, &SearchFields) -> tantivy::Result<()> + Send + 'static,
+{
+    tokio::task::spawn_blocking(move || SEARCH_INDEX.write(op, mark))
+        .await
+        .map_err(|e| api_errore(ApiErrorCode::InvalidSearch, &e))?
+}
+
+async fn write_ops(ops: SearchOps, mark: Option<SyncMark>) -> Result<(), ApiError> {
+    write_blocking(
+        move |writer, f| {
+            for (key, doc) in ops {
+                writer.delete_term(Term::from_field_text(f.key, &key));
+                if let Some(doc) = doc {
+                    writer.add_document(doc)?;
+                }
+            }
+            Ok(())
+        },
+        mark,
+    )
+    .await
+}
+
+fn topic_key(id: u64) -> String {
+    format!("topic:{}", id)
+}
+
+fn reply_key(id: u64) -> String {
+    format!("reply:{}", id)
+}
+
+fn topic_op(topic: &topic::TopicModel) -> (String, Option<TantivyDocument>) {
+    // placeholders of moved topics carry no content of their own
+    let doc = (topic.is_actived() && topic.moved_to == 0).then(|| SEARCH_INDEX.topic_doc(topic));
+    (topic_key(topic.id), doc)
+}
+
+/// Replies are only searchable while their topic is too.
+fn reply_op(
+    reply: &reply::ReplyModel,
+    topic: &topic::TopicModel,
+) -> (String, Option<TantivyDocument>) {
+    let doc = (reply.is_actived() && topic.is_actived())
+        .then(|| SEARCH_INDEX.reply_doc(reply, topic.category));
+    (reply_key(reply.id), doc)
+}
+
+/// Indexes the topic when it is visible, otherwise drops it from the index.
+/// Written in the background, a failed write is logged and left to `catch_up`.
+pub fn sync_topic(topic: &topic::TopicModel) {
+    enqueue(vec![topic_op(topic)]);
+}
+
+/// Indexes the reply when it and its topic are visible, otherwise drops it
+/// from the index. Written in the background like `sync_topic`.
+pub fn sync_reply(reply: &reply::ReplyModel, topic: &topic::TopicModel) {
+    enqueue(vec![reply_op(reply, topic)]);
+}
+
+/// Indexes the topic and all of its replies again in one commit, used when
+/// the category or the visibility of a topic changes.
+pub async fn sync_topic_tree(conn: &mut SqlConnection, topic: &topic::TopicModel) {
+    let mut ops = vec![topic_op(topic)];
+
+    let mut last_id = 0;
+    loop {
+        let replies =
+            match reply::fetch_actived_by_topic(conn, topic.id, last_id, REBUILD_BATCH).await {
+                Ok(replies) => replies,
+                Err(e) => {
+                    tracing::warn!("search sync of topic {} failed: {}", topic.id, e);
+                    break;
+                }
+            };
+        if replies.is_empty() {
+            break;
+        }
+
+        ops.extend(replies.iter().map(|s| reply_op(s, topic)));
+        last_id = replies.last().unwrap().id;
+    }
+
+    enqueue(ops);
+}
+
+/// Follows the topics and replies changed through any instance. Each instance
+/// keeps an index of its own, so every one of them runs this rather than a
+/// single locked job. Starting without a sync mark indexes everything.
+pub async fn catch_up() -> Result<(), ApiError> {
+    let mut conn = database_connect().await?;
+    let mut mark = SEARCH_INDEX.mark()?;
+
+    loop {
+        let changed = topic::fetch_changed_after(
+            &mut conn,
+            sql_datetime(mark.topic_at),
+            mark.topic_id,
+            SETTLE_SECONDS,
+            REBUILD_BATCH,
+        )
+        .await?;
+        if changed.is_empty() {
+            break;
+        }
+
+        let ids: Vec<u64> = changed.iter().map(|s| s.0).collect();
+        let topics = topic::get_list_by_ids(&mut conn, &ids).await?;
+        let mut ops: SearchOps = ids.iter().map(|s| (topic_key(*s), None)).collect();
+        ops.extend(topics.iter().map(topic_op));
+
+        let (id, at) = changed.last().unwrap();
+        mark.topic_at = at.and_utc().timestamp();
+        mark.topic_id = *id;
+        write_ops(ops, Some(mark)).await?;
+
+        if changed.len() < REBUILD_BATCH as usize {
+            break;
+        }
+    }
+
+    loop {
+        let changed = reply::fetch_changed_after(
+            &mut conn,
+            sql_datetime(mark.reply_at),
+            mark.reply_id,
+            SETTLE_SECONDS,
+            REBUILD_BATCH,
+        )
+        .await?;
+        if changed.is_empty() {
+            break;
+        }
+
+        let ids: Vec<u64> = changed.iter().map(|s| s.0).collect();
+        let reply_map = reply::get_map_by_ids(&mut conn, ids.clone()).await?;
+        let replies: Vec<_> = ids.iter().filter_map(|s| reply_map.opt(*s)).collect();
+        let topic_ids: Vec<u64> = replies
+            .iter()
+            .map(|s| s.topic_id)
+            .collect::<HashSet<_>>()
+            .into_iter()
+            .collect();
+        let topics = topic::get_list_by_ids(&mut conn, &topic_ids).await?;
+        let topic_map: HashMap<u64, &topic::TopicModel> =
+            topics.iter().map(|s| (s.id, s)).collect();
+
+        let mut ops: SearchOps = ids.iter().map(|s| (reply_key(*s), None)).collect();
+        ops.extend(
+            replies
+                .iter()
+                .filter_map(|s| topic_map.get(&s.topic_id).map(|t| reply_op(s, t))),
+        );
+
+        let (id, at) = changed.last().unwrap();
+        mark.reply_at = at.and_utc().timestamp();
+        mark.reply_id = *id;
+        write_ops(ops, Some(mark)).await?;
+
+        if changed.len() < REBUILD_BATCH as usize {
+            break;
+        }
+    }
+
+    Ok(())
+}
+
+/// Drops every document of the app and indexes its visible topics and
+/// replies again from the database.
+pub async fn rebuild(conn: &mut SqlConnection, app_id: u64) -> Result<(u32, u32), ApiError> {
+    write_blocking(
+        move |writer, f| {
+            writer.delete_term(Term::from_field_u64(f.app_id, app_id));
+            Ok(())
+        },
+        None,
+    )
+    .await?;
+
+    let mut categories = HashMap::new();
+    let mut last_id = 0;
+    loop {
+        let topics = topic::fetch_actived_after(conn, app_id, last_id, REBUILD_BATCH).await?;
+        if topics.is_empty() {
+            break;
+        }
+
+        let docs: Vec<_> = topics.iter().filter_map(|s| topic_op(s).1).collect();
+        write_blocking(
+            move |writer, _| {
+                for doc in docs {
+                    writer.add_document(doc)?;
+                }
+                Ok(())
+            },
+            None,
+        )
+        .await?;
+
+        for o in topics.iter() {
+            categories.insert(o.id, o.category);
+        }
+        last_id = topics.last().unwrap().id;
+    }
+
+    let mut reply_total = 0;
+    let mut last_id = 0;
+    loop {
+        let replies = reply::fetch_actived_after(conn, app_id, last_id, REBUILD_BATCH).await?;
+        if replies.is_empty() {
+            break;
+        }
+
+        let docs: Vec<_> = replies
+            .iter()
+            .filter_map(|s| {
+                categories
+                    .get(&s.topic_id)
+                    .map(|c| SEARCH_INDEX.reply_doc(s, *c))
+            })
+            .collect();
+        reply_total += docs.len() as u32;
+        write_blocking(
+            move |writer, _| {
+                for doc in docs {
+                    writer.add_document(doc)?;
+                }
+                Ok(())
+            },
+            None,
+        )
+        .await?;
+
+        last_id = replies.last().unwrap().id;
+    }
+
+    Ok((categories.len() as u32, reply_total))
+}
+
+struct SearchHit {
+    kind: u64,
+    id: u64,
+    topic_id: u64,
+    score: f32,
+    title: String,
+    content: String,
+}
+
+/// Ranks topics and replies of the app by relevance to the keyword, the
+/// snippets mark matched words with `<b>` tags. Blocks, run it through
+/// `spawn_blocking`.
+fn query_index(
+    app_id: u64,
+    category: u64,
+    keyword: &str,
+    cursor: u32,
+    count: u32,
+) -> Result<(u32, Vec<SearchHit>), ApiError> {
+    let f = &SEARCH_INDEX.fields;
+
+    let mut analyzer = SEARCH_INDEX
+        .index
+        .tokenizer_for_field(f.content)
+        .map_err(|e| api_errore(ApiErrorCode::InvalidSearch, &e))?;
+    let mut words = Vec::new();
+    analyzer
+        .token_stream(keyword)
+        .process(&mut |t| words.push(t.text.clone()));
+    if words.is_empty() {
+        return Ok((0, Vec::new()));
+    }
+
+    let mut word_queries: Vec<(Occur, Box<dyn Query>)> = Vec::new();
+    for word in words.iter() {
+        let title = TermQuery::new(
+            Term::from_field_text(f.title, word),
+            IndexRecordOption::WithFreqs,
+        );
+        let content = TermQuery::new(
+            Term::from_field_text(f.content, word),
+            IndexRecordOption::WithFreqs,
+        );
+        word_queries.push((
+            Occur::Should,
+            Box::new(BoostQuery::new(Box::new(title), 2.0)),
+        ));
+        word_queries.push((Occur::Should, Box::new(content)));
+    }
+
+    let mut queries: Vec<(Occur, Box<dyn Query>)> = vec![
+        (Occur::Must, Box::new(BooleanQuery::new(word_queries))),
+        (
+            Occur::Must,
+            Box::new(TermQuery::new(
+                Term::from_field_u64(f.app_id, app_id),
+                IndexRecordOption::Basic,
+            )),
+        ),
+    ];
+    if category > 0 {
+        queries.push((
+            Occur::Must,
+            Box::new(TermQuery::new(
+                Term::from_field_u64(f.category, category),
+                IndexRecordOption::Basic,
+            )),
+        ));
+    }
+    let query = BooleanQuery::new(queries);
+
+    let searcher = SEARCH_INDEX.reader.searcher();
+    let (docs, total) = searcher
+        .search(
+            &query,
+            &(
+                TopDocs::with_limit(count as usize).and_offset(cursor as usize),
+                Count,
+            ),
+        )
+        .map_err(|e| api_errore(ApiErrorCode::InvalidSearch, &e))?;
+
+    let mut title_snippet = SnippetGenerator::create(&searcher, &query, f.title)
+        .map_err(|e| api_errore(ApiErrorCode::InvalidSearch, &e))?;
+    title_snippet.set_max_num_chars(SNIPPET_CHARS);
+    let mut content_snippet = SnippetGenerator::create(&searcher, &query, f.content)
+        .map_err(|e| api_errore(ApiErrorCode::InvalidSearch, &e))?;
+    content_snippet.set_max_num_chars(SNIPPET_CHARS);
+
+    let mut hits = Vec::new();
+    for (score, address) in docs {
+        let doc: TantivyDocument = searcher
+            .doc(address)
+            .map_err(|e| api_errore(ApiErrorCode::InvalidSearch, &e))?;
+        let get_u64 = |field| doc.get_first(field).and_then(|v| v.as_u64()).unwrap_or(0);
+
+        hits.push(SearchHit {
+            kind: get_u64(f.kind),
+            id: get_u64(f.id),
+            topic_id: get_u64(f.topic_id),
+            score,
+            title: title_snippet.snippet_from_doc(&doc).to_html(),
+            content: content_snippet.snippet_from_doc(&doc).to_html(),
+        });
+    }
+
+    Ok((total as u32, hits))
+}
+
+#[derive(Serialize)]
+pub struct SearchItem {
+    pub score: f32,
+    pub snippet_title: String,
+    pub snippet_content: String,
+    pub topic: topic::TopicSimple,
+    pub reply: Option<reply::ReplySimple>,
+    pub user: Arc<user::UserSimple>,
+}
+
+/// Searches the visible topics and replies of the app and loads what the hits
+/// point at. Only visible content is indexed, a hit is skipped only while the
+/// index has yet to follow a change made a moment ago.
+pub async fn query(
+    conn: &mut SqlConnection,
+    app_id: u64,
+    category: u64,
+    keyword: &str,
+    cursor: u32,
+    count: u32,
+) -> Result<(u32, Vec<SearchItem>), ApiError> {
+    let keyword = String::from(keyword);
+    let (total, hits) =
+        tokio::task::spawn_blocking(move || query_index(app_id, category, &keyword, cursor, count))
+            .await
+            .map_err(|e| api_errore(ApiErrorCode::Unexpected, &e))??;
+
+    let topic_map = topic::get_map_by_ids(conn, hits.iter().map(|s| s.topic_id).collect()).await?;
+    let reply_map = reply::get_map_by_ids(
+        conn,
+        hits.iter()
+            .filter(|s| s.kind == KIND_REPLY)
+            .map(|s| s.id)
+            .collect(),
+    )
+    .await?;
+
+    let mut matched = Vec::new();
+    for hit in hits {
+        let topic = match topic_map.opt(hit.topic_id) {
+            Some(topic) if topic.app_id == app_id && topic.is_actived() => topic,
+            _ => continue,
+        };
+        let reply = if hit.kind == KIND_REPLY {
+            match reply_map.opt(hit.id) {
+                Some(reply) if reply.is_actived() => Some(reply),
+                _ => continue,
+            }
+        } else {
+            None
+        };
+        matched.push((hit, topic, reply));
+    }
+
+    let user_map = user::get_simple_map_by_ids(
+        conn,
+        matched
+            .iter()
+            .map(|(_, t, r)| r.as_ref().map_or(t.user_id, |r| r.user_id))
+            .collect(),
+    )
+    .await?;
+
+    let items = matched
+        .into_iter()
+        .map(|(hit, topic, reply)| SearchItem {
+            score: hit.score,
+            snippet_title: hit.title,
+            snippet_content: hit.content,
+            user: user_map.get(reply.as_ref().map_or(topic.user_id, |r| r.user_id)),
+            topic: topic.to_preview(),
+            reply: reply.map(|r| r.to_simple()),
+        })
+        .collect();
+
+    Ok((total, items))
+}
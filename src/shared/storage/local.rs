@@ -0,0 +1,54 @@
+use std::path::PathBuf;
+
+use axum::async_trait;
+
+use super::Storage;
+use crate::shared::web::*;
+
+/// Keeps files under `STORAGE_LOCAL_DIR`, a web server is expected to
+/// publish that directory at `STORAGE_PUBLIC_URL`.
+pub struct LocalStorage {
+    root: PathBuf,
+    public_url: String,
+}
+
+impl LocalStorage {
+    pub fn from_env() -> Self {
+        let root = std::env::var("STORAGE_LOCAL_DIR").unwrap_or("data/upload".to_string());
+        std::fs::create_dir_all(&root).expect("can't create STORAGE_LOCAL_DIR");
+
+        Self {
+            root: PathBuf::from(root),
+            public_url: std::env::var("STORAGE_PUBLIC_URL").unwrap_or("/upload".to_string()),
+        }
+    }
+}
+
+#[async_trait]
+impl Storage for LocalStorage {
+    async fn put(self: &Self, key: &str, data: Vec<u8>, _mime_type: &str) -> Result<(), ApiError> {
+        let path = self.root.join(key);
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent)
+                .await
+                .map_err(|e| api_errore(ApiErrorCode::InvalidStorage, &e))?;
+        }
+
+        tokio::fs::write(path, data)
+            .await
+            .map_err(|e| api_errore(ApiErrorCode::InvalidStorage, &e))
+    }
+
+    async fn delete(self: &Self, key: &str) -> Result<(), ApiError> {
+        match tokio::fs::remove_file(self.root.join(key)).await {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+                Err(api_errore(ApiErrorCode::InvalidStorage, &e))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn url(self: &Self, key: &str) -> String {
+        format!("{}/{}", self.public_url.trim_end_matches('/'), key)
+    }
+}
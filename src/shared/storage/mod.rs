@@ -0,0 +1,43 @@
+use axum::async_trait;
+
+use super::base::*;
+use super::web::*;
+
+mod local;
+mod s3;
+
+#[async_trait]
+pub trait Storage: Send + Sync {
+    async fn put(self: &Self, key: &str, data: Vec<u8>, mime_type: &str) -> Result<(), ApiError>;
+
+    async fn delete(self: &Self, key: &str) -> Result<(), ApiError>;
+
+    fn url(self: &Self, key: &str) -> String;
+}
+
+static STORAGE: Lazy<Box<dyn Storage>> = Lazy::new(|| {
+    let backend = std::env::var("STORAGE_BACKEND").unwrap_or("local".to_string());
+    match backend.as_str() {
+        "local" => Box::new(local::LocalStorage::from_env()),
+        "s3" => Box::new(s3::S3Storage::from_env()),
+        _ => panic!("STORAGE_BACKEND must be local or s3"),
+    }
+});
+
+pub fn init() {
+    Lazy::force(&STORAGE);
+}
+
+pub fn storage() -> &'static dyn Storage {
+    STORAGE.as_ref()
+}
+
+pub fn build_key(app_id: u64, extension: &str) -> String {
+    format!(
+        "{}/{}/{}.{}",
+        app_id,
+        chrono::Utc::now().format("%Y%m"),
+        uuid::Uuid::new_v4().simple(),
+        extension
+    )
+}
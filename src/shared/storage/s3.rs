@@ -0,0 +1,151 @@
+use axum::async_trait;
+use hmac::{Hmac, Mac};
+use reqwest::{Client, Method, Url};
+use sha2::{Digest, Sha256};
+
+use super::Storage;
+use crate::shared::web::*;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Talks to any S3-compatible service with path-style URLs and SigV4.
+pub struct S3Storage {
+    client: Client,
+    endpoint: Url,
+    region: String,
+    bucket: String,
+    access_key: String,
+    secret_key: String,
+    public_url: String,
+}
+
+impl S3Storage {
+    pub fn from_env() -> Self {
+        let endpoint = std::env::var("S3_ENDPOINT").expect("S3_ENDPOINT must be set");
+        let bucket = std::env::var("S3_BUCKET").expect("S3_BUCKET must be set");
+        let public_url = std::env::var("STORAGE_PUBLIC_URL").unwrap_or(format!(
+            "{}/{}",
+            endpoint.trim_end_matches('/'),
+            bucket
+        ));
+
+        Self {
+            client: Client::new(),
+            endpoint: Url::parse(&endpoint).expect("S3_ENDPOINT must be an URL"),
+            region: std::env::var("S3_REGION").unwrap_or("us-east-1".to_string()),
+            bucket,
+            access_key: std::env::var("S3_ACCESS_KEY").expect("S3_ACCESS_KEY must be set"),
+            secret_key: std::env::var("S3_SECRET_KEY").expect("S3_SECRET_KEY must be set"),
+            public_url,
+        }
+    }
+
+    async fn send(
+        self: &Self,
+        method: Method,
+        key: &str,
+        data: Vec<u8>,
+        mime_type: Option<&str>,
+    ) -> Result<(), ApiError> {
+        let path = format!("/{}/{}", self.bucket, encode_path(key));
+        let url = self
+            .endpoint
+            .join(&path)
+            .map_err(|e| api_errore(ApiErrorCode::InvalidStorage, &e))?;
+        let host = match url.port() {
+            Some(port) => format!("{}:{}", url.host_str().unwrap_or_default(), port),
+            None => url.host_str().unwrap_or_default().to_string(),
+        };
+
+        let now = chrono::Utc::now();
+        let amz_date = now.format("%Y%m%dT%H%M%SZ").to_string();
+        let date = now.format("%Y%m%d").to_string();
+        let payload_hash = hex_sha256(&data);
+
+        let canonical = format!(
+            "{}\n{}\n\nhost:{}\nx-amz-content-sha256:{}\nx-amz-date:{}\n\nhost;x-amz-content-sha256;x-amz-date\n{}",
+            method, path, host, payload_hash, amz_date, payload_hash
+        );
+        let scope = format!("{}/{}/s3/aws4_request", date, self.region);
+        let string_to_sign = format!(
+            "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+            amz_date,
+            scope,
+            hex_sha256(canonical.as_bytes())
+        );
+
+        let mut key_bytes = hmac_sha256(format!("AWS4{}", self.secret_key).as_bytes(), &date);
+        for part in [self.region.as_str(), "s3", "aws4_request"] {
+            key_bytes = hmac_sha256(&key_bytes, part);
+        }
+        let signature = base16ct::lower::encode_string(&hmac_sha256(&key_bytes, &string_to_sign));
+
+        let mut request = self
+            .client
+            .request(method, url)
+            .header("x-amz-date", amz_date)
+            .header("x-amz-content-sha256", payload_hash)
+            .header(
+                "authorization",
+                format!(
+                    "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders=host;x-amz-content-sha256;x-amz-date, Signature={}",
+                    self.access_key, scope, signature
+                ),
+            );
+        if let Some(mime_type) = mime_type {
+            request = request.header("content-type", mime_type);
+        }
+
+        let res = request
+            .body(data)
+            .send()
+            .await
+            .map_err(|e| api_errore(ApiErrorCode::InvalidStorage, &e))?;
+        if !res.status().is_success() {
+            return Err(api_error2(
+                ApiErrorCode::InvalidStorage,
+                &format!("status {}", res.status()),
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Storage for S3Storage {
+    async fn put(self: &Self, key: &str, data: Vec<u8>, mime_type: &str) -> Result<(), ApiError> {
+        self.send(Method::PUT, key, data, Some(mime_type)).await
+    }
+
+    async fn delete(self: &Self, key: &str) -> Result<(), ApiError> {
+        self.send(Method::DELETE, key, Vec::new(), None).await
+    }
+
+    fn url(self: &Self, key: &str) -> String {
+        format!("{}/{}", self.public_url.trim_end_matches('/'), key)
+    }
+}
+
+fn hex_sha256(data: &[u8]) -> String {
+    base16ct::lower::encode_string(&Sha256::digest(data))
+}
+
+fn hmac_sha256(key: &[u8], data: &str) -> Vec<u8> {
+    let mut mac = HmacSha256::new_from_slice(key).expect("HMAC accepts any key size");
+    mac.update(data.as_bytes());
+    mac.finalize().into_bytes().to_vec()
+}
+
+fn encode_path(key: &str) -> String {
+    let mut out = String::new();
+    for b in key.bytes() {
+        match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' | b'/' => {
+                out.push(b as char)
+            }
+            _ => out.push_str(&format!("%{:02X}", b)),
+        }
+    }
+    out
+}
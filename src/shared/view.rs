@@ -0,0 +1,101 @@
+use super::base::*;
+use super::data::*;
+use super::model::*;
+use super::web::*;
+
+/// A viewer is counted once per topic within this many seconds.
+const VIEW_WINDOW: i64 = 3600;
+
+const PENDING_KEY: &'static str = "topicviews";
+const FLUSHING_KEY: &'static str = "topicviews:flushing";
+
+fn viewers_key(topic_id: u64, bucket: i64) -> String {
+    format!("topicviewers:{}:{}", topic_id, bucket)
+}
+
+/// Counts a view of the topic unless the viewer (`u:{user_id}` or
+/// `ip:{addr}`) has been seen in the current window. The HyperLogLog
+/// answers whether the viewer is new, the hash collects views until
+/// `flush` writes them to MySQL. A view lost to a Redis error is only
+/// logged, it never fails the topic detail.
+pub async fn record(topic_id: u64, viewer: &str) {
+    if let Err(e) = add_view(topic_id, viewer).await {
+        tracing::warn!("record view of topic {} failed: {}", topic_id, e);
+    }
+}
+
+async fn add_view(topic_id: u64, viewer: &str) -> Result<(), ApiError> {
+    let mut connr = redis_connect().await?;
+
+    let key = viewers_key(topic_id, timestamp() / VIEW_WINDOW);
+    let (added,): (u64,) = redis::pipe()
+        .cmd("PFADD")
+        .arg(&key)
+        .arg(viewer)
+        .cmd("EXPIRE")
+        .arg(&key)
+        .arg(VIEW_WINDOW * 2)
+        .ignore()
+        .query_async(&mut *connr)
+        .await
+        .map_err(|e| api_errore(ApiErrorCode::InvalidDatabase, &e))?;
+
+    if added > 0 {
+        redis::cmd("HINCRBY")
+            .arg(PENDING_KEY)
+            .arg(topic_id)
+            .arg(1)
+            .query_async::<()>(&mut *connr)
+            .await
+            .map_err(|e| api_errore(ApiErrorCode::InvalidDatabase, &e))?;
+    }
+
+    Ok(())
+}
+
+/// Moves the pending views aside and adds them to `dg_topics.view_count`.
+/// Left-overs of a failed run are flushed first.
+pub async fn flush() -> Result<(), ApiError> {
+    let mut connr = redis_connect().await?;
+
+    let (flushing, pending): (bool, bool) = redis::pipe()
+        .cmd("EXISTS")
+        .arg(FLUSHING_KEY)
+        .cmd("EXISTS")
+        .arg(PENDING_KEY)
+        .query_async(&mut *connr)
+        .await
+        .map_err(|e| api_errore(ApiErrorCode::InvalidDatabase, &e))?;
+
+    if !flushing {
+        if !pending {
+            return Ok(());
+        }
+        redis::cmd("RENAME")
+            .arg(PENDING_KEY)
+            .arg(FLUSHING_KEY)
+            .query_async::<()>(&mut *connr)
+            .await
+            .map_err(|e| api_errore(ApiErrorCode::InvalidDatabase, &e))?;
+    }
+
+    let views: HashMap<u64, u64> = redis::cmd("HGETALL")
+        .arg(FLUSHING_KEY)
+        .query_async(&mut *connr)
+        .await
+        .map_err(|e| api_errore(ApiErrorCode::InvalidDatabase, &e))?;
+
+    let mut conn = database_connect().await?;
+    for (topic_id, count) in views.iter() {
+        topic::update_view_count(&mut conn, *topic_id, *count).await?;
+
+        redis::cmd("HDEL")
+            .arg(FLUSHING_KEY)
+            .arg(*topic_id)
+            .query_async::<()>(&mut *connr)
+            .await
+            .map_err(|e| api_errore(ApiErrorCode::InvalidDatabase, &e))?;
+    }
+
+    Ok(())
+}
@@ -3,10 +3,13 @@ use std::collections::HashMap;
 use super::super::base::*;
 
 #[allow(dead_code)]
+#[derive(Clone, Copy)]
 pub enum ApiErrorCode {
     Success = 0,
     // inner error
     InvalidDatabase = 1001,
+    InvalidSearch = 1002,
+    InvalidStorage = 1003,
     // public error
     InvalidParams = 2001,
     InvalidSign = 2002,
@@ -15,9 +18,24 @@ pub enum ApiErrorCode {
     AccountOrPasswordFailed = 3002,
     NoPermission = 3003,
     AccountNotActived = 3004,
+    InvalidAvatar = 3005,
     AppNotFound = 4001,
     TopicNotFound = 5001,
+    TopicNotEditable = 5002,
+    RevisionNotFound = 5003,
+    TopicLocked = 5004,
+    TopicMoved = 5005,
+    TopicNotQa = 5006,
     ReplyNotFound = 5101,
+    ReplyNotEditable = 5102,
+    TagNotFound = 5201,
+    AttachmentTooLarge = 5301,
+    AttachmentTypeNotAllowed = 5302,
+    PollNotFound = 5401,
+    PollClosed = 5402,
+    PollAlreadyVoted = 5403,
+    DraftNotFound = 5501,
+    ReactionNotAllowed = 5601,
     Unexpected = 9999,
 }
 
@@ -6,6 +6,7 @@ use axum::{
 use serde::Serialize;
 use serde_json::json;
 use std::error::Error;
+use std::fmt;
 
 use super::error_code::*;
 
@@ -61,6 +62,14 @@ pub struct ApiError {
     error: String,
 }
 
+/// For logging errors that are not returned to a client.
+impl fmt::Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (code, error) = render_error(self.code, &self.error);
+        write!(f, "{} {}", code, error)
+    }
+}
+
 impl IntoResponse for ApiError {
     fn into_response(self) -> Response {
         let (code, error) = render_error(self.code, &self.error);
@@ -1,5 +1,7 @@
 use validator::ValidationError;
 
+use super::super::base::*;
+
 pub fn validate_url(value: &str) -> Result<(), ValidationError> {
     if value.is_empty() || value.starts_with("https://") || value.starts_with("https://") {
         return Ok(());
@@ -16,6 +18,22 @@ pub fn validate_gender(value: i8) -> Result<(), ValidationError> {
     Err(ValidationError::new("between 0 to 2"))
 }
 
+pub fn validate_content_format(value: i8) -> Result<(), ValidationError> {
+    if value >= 0 && value <= 1 {
+        return Ok(());
+    }
+
+    Err(ValidationError::new("between 0 to 1"))
+}
+
+pub fn validate_future_time(value: i64) -> Result<(), ValidationError> {
+    if value > timestamp() {
+        return Ok(());
+    }
+
+    Err(ValidationError::new("must be in the future"))
+}
+
 pub fn validate_page_count(value: u32) -> Result<(), ValidationError> {
     if value <= 500 {
         return Ok(());